@@ -0,0 +1,47 @@
+use accesskit::{Live, Node, Role};
+use bevy::{a11y::AccessibilityNode, prelude::*};
+
+// bevy_ui names a button after its text once, when it's spawned. Buttons whose
+// text shows a state ('ON', 'Set: All') are given a name of their own, the text
+// goes in the node's value and follows it
+#[derive(Component)]
+pub struct AccessibleName(pub String);
+
+// text that screen readers read out whenever it changes, like the result of a
+// recognition
+#[derive(Component)]
+pub struct Announced;
+
+pub fn announced() -> impl Bundle {
+    let mut node = Node::new(Role::Label);
+    node.set_live(Live::Polite);
+    (Announced, AccessibilityNode(node))
+}
+
+// runs the frame after bevy_ui has added the button's node, or whenever its
+// text changes
+pub fn name_buttons(
+    mut buttons: Query<(&AccessibleName, &Children, &mut AccessibilityNode), With<Button>>,
+    texts: Query<Ref<Text>>,
+) {
+    for (name, children, mut node) in &mut buttons {
+        let Some(text) = children.iter().find_map(|child| texts.get(*child).ok()) else {
+            continue;
+        };
+        if !node.is_added() && !text.is_changed() {
+            continue;
+        }
+        node.set_label(name.0.as_str());
+        if text.0 == name.0 {
+            node.clear_value();
+        } else {
+            node.set_value(text.0.as_str());
+        }
+    }
+}
+
+pub fn announce(mut texts: Query<(&Text, &mut AccessibilityNode), (With<Announced>, Changed<Text>)>) {
+    for (text, mut node) in &mut texts {
+        node.set_value(text.0.as_str());
+    }
+}
@@ -0,0 +1,66 @@
+use bevy::{
+    math::{Rot2, Vec2},
+    prelude::Resource,
+};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::{recognizer::Candidate, templates::Template};
+
+// about the noise `--bench` tests with, the jitter is in the template's
+// normalized coordinates where the bigger side of the bounding box is 1
+const MAX_ROTATION_DEGREES: f32 = 15.0;
+const MAX_SCALE_JITTER: f32 = 0.15;
+const MAX_POINT_JITTER: f32 = 0.015;
+
+// `--augment=<k>` adds k distorted copies along with the first template of a
+// gesture so it isn't left with a single sample, `--augment-seed=<n>` picks
+// the distortions
+#[derive(Resource, Default)]
+pub struct Augmentation {
+    pub variants: usize,
+    pub seed: u64,
+}
+
+impl Augmentation {
+    // slightly turned, stretched and noisy copies of a gesture's template, the
+    // same every time for the same seed and name
+    pub fn variants(&self, name: &str, template: &Template, n: usize, preserve_corners: bool) -> Vec<Template> {
+        let seed = name.bytes().fold(self.seed, |seed, byte| seed.wrapping_mul(31).wrapping_add(byte as u64));
+        let mut rng = StdRng::seed_from_u64(seed);
+        (0..self.variants)
+            .map(|_| {
+                let strokes = distort(&template.strokes(), &mut rng);
+                let mut variant = Template::new(
+                    Candidate::new(&strokes, n, preserve_corners).points,
+                    "augmented",
+                    template.stroke_count,
+                );
+                variant.size = template.size;
+                variant
+            })
+            .collect()
+    }
+}
+
+fn distort(strokes: &[Vec<Vec2>], rng: &mut StdRng) -> Vec<Vec<Vec2>> {
+    let rotation = Rot2::degrees(rng.gen_range(-MAX_ROTATION_DEGREES..=MAX_ROTATION_DEGREES));
+    let scale = Vec2::new(
+        rng.gen_range(1.0 - MAX_SCALE_JITTER..=1.0 + MAX_SCALE_JITTER),
+        rng.gen_range(1.0 - MAX_SCALE_JITTER..=1.0 + MAX_SCALE_JITTER),
+    );
+    strokes
+        .iter()
+        .map(|stroke| {
+            stroke
+                .iter()
+                .map(|point| {
+                    let jitter = Vec2::new(
+                        rng.gen_range(-MAX_POINT_JITTER..=MAX_POINT_JITTER),
+                        rng.gen_range(-MAX_POINT_JITTER..=MAX_POINT_JITTER),
+                    );
+                    rotation * (*point * scale) + jitter
+                })
+                .collect()
+        })
+        .collect()
+}
@@ -0,0 +1,240 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use bevy::math::{Rot2, Vec2};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::{
+    dataset::{self, Gesture},
+    library::{GestureLibrary, GestureSet},
+    options::Options,
+    recognizer::{recognize_points, RecognitionConfig},
+    templates::Template,
+};
+
+// templates are stored normalized (roughly -0.5..0.5), so blow them up to a
+// plausible on-screen size before adding noise
+const CANVAS_SCALE: f32 = 300.0;
+const MAX_ROTATION_DEGREES: f32 = 15.0;
+const MAX_SCALE_JITTER: f32 = 0.15;
+const MAX_POINT_JITTER: f32 = 4.0;
+
+pub struct BenchConfig {
+    pub samples_per_template: usize,
+    pub seed: u64,
+    // dataset runs only: samples per class and subject used as templates,
+    // the remaining ones are recognized against them
+    pub train_per_class: usize,
+}
+
+impl Default for BenchConfig {
+    fn default() -> Self {
+        Self {
+            samples_per_template: 200,
+            seed: 0,
+            train_per_class: 1,
+        }
+    }
+}
+
+impl BenchConfig {
+    // `--samples=<n>`, `--seed=<n>` and `--train=<n>`, everything else is left at the default
+    pub fn from_options(options: &Options) -> Self {
+        let default = Self::default();
+        Self {
+            samples_per_template: options.samples.unwrap_or(default.samples_per_template),
+            seed: options.seed.unwrap_or(default.seed),
+            train_per_class: options.train.unwrap_or(default.train_per_class),
+        }
+    }
+}
+
+fn synthesize(template: &Template, rng: &mut StdRng) -> Vec<Vec<Vec2>> {
+    let rotation = Rot2::degrees(rng.gen_range(-MAX_ROTATION_DEGREES..=MAX_ROTATION_DEGREES));
+    let scale = Vec2::new(
+        CANVAS_SCALE * rng.gen_range(1.0 - MAX_SCALE_JITTER..=1.0 + MAX_SCALE_JITTER),
+        CANVAS_SCALE * rng.gen_range(1.0 - MAX_SCALE_JITTER..=1.0 + MAX_SCALE_JITTER),
+    );
+    let offset = Vec2::new(rng.gen_range(200.0..600.0), rng.gen_range(200.0..400.0));
+
+    template
+        .strokes()
+        .into_iter()
+        .map(|stroke| {
+            stroke
+                .into_iter()
+                .map(|point| {
+                    let jitter = Vec2::new(
+                        rng.gen_range(-MAX_POINT_JITTER..=MAX_POINT_JITTER),
+                        rng.gen_range(-MAX_POINT_JITTER..=MAX_POINT_JITTER),
+                    );
+                    rotation * (point * scale) + offset + jitter
+                })
+                .collect()
+        })
+        .collect()
+}
+
+fn percentile(sorted: &[Duration], p: f32) -> Duration {
+    if sorted.is_empty() {
+        return Duration::ZERO;
+    }
+    let index = ((sorted.len() - 1) as f32 * p).round() as usize;
+    sorted[index]
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+#[derive(Default)]
+struct ClassStats {
+    total: usize,
+    correct: usize,
+    predicted: usize,
+}
+
+// recognizes every (expected name, strokes) candidate and prints the results
+fn evaluate<'a>(
+    candidates: impl IntoIterator<Item = (&'a str, Vec<Vec<Vec2>>, &'a GestureLibrary)>,
+    config: &RecognitionConfig,
+) {
+    println!(
+        "{} matching, {} points, epsilon {}, protractor top-k {}",
+        config.matcher.name(),
+        config.n_points,
+        config.epsilon,
+        config.protractor_top_k.map_or("off".to_string(), |k| k.to_string())
+    );
+
+    let mut stats: HashMap<String, ClassStats> = HashMap::new();
+    let mut latencies = Vec::new();
+    let mut correct = 0;
+    let mut truncated = 0;
+
+    for (name, candidate_vectors, library) in candidates {
+        let start_time = Instant::now();
+        let prediction = recognize_points(&candidate_vectors, library, config, 1)
+            .into_iter()
+            .next()
+            .inspect(|result| truncated += result.truncated as usize)
+            .map(|result| result.name)
+            .unwrap_or_else(|| "not recognized".to_string());
+        latencies.push(start_time.elapsed());
+
+        let class = stats.entry(name.to_string()).or_default();
+        class.total += 1;
+        if prediction == name {
+            class.correct += 1;
+            correct += 1;
+        }
+        stats.entry(prediction).or_default().predicted += 1;
+    }
+
+    let total = latencies.len();
+    latencies.sort();
+    let mean = latencies.iter().sum::<Duration>() / total.max(1) as u32;
+
+    println!(
+        "accuracy: {:.2}%",
+        100.0 * correct as f32 / total.max(1) as f32
+    );
+    println!(
+        "latency: mean {:.3} ms, p50 {:.3} ms, p90 {:.3} ms, p99 {:.3} ms, max {:.3} ms",
+        millis(mean),
+        millis(percentile(&latencies, 0.5)),
+        millis(percentile(&latencies, 0.9)),
+        millis(percentile(&latencies, 0.99)),
+        millis(latencies.last().copied().unwrap_or_default()),
+    );
+    if let Some(budget) = config.latency_budget {
+        println!(
+            "latency budget {:.3} ms: {} of {} ran out of time",
+            millis(budget),
+            truncated,
+            total
+        );
+    }
+
+    println!("\n{:<20} {:>10} {:>10}", "class", "precision", "recall");
+    let mut classes: Vec<_> = stats.into_iter().filter(|(_, class)| class.total > 0).collect();
+    classes.sort_by(|(a, _), (b, _)| a.cmp(b));
+    for (name, class) in classes {
+        let precision = if class.predicted == 0 {
+            0.0
+        } else {
+            100.0 * class.correct as f32 / class.predicted as f32
+        };
+        let recall = 100.0 * class.correct as f32 / class.total as f32;
+        println!("{:<20} {:>9.2}% {:>9.2}%", name, precision, recall);
+    }
+}
+
+pub fn run(templates: GestureSet, config: &BenchConfig, recognition: &RecognitionConfig) {
+    let mut rng = StdRng::seed_from_u64(config.seed);
+    let mut library = GestureLibrary::new("bench", templates);
+    library.set_resampling(recognition.n_points, recognition.preserve_corners);
+    let templates = library.active_sets().next().unwrap().1;
+
+    // sort so a given seed always generates the same candidates
+    let mut names: Vec<&String> = templates.keys().collect();
+    names.sort();
+
+    let mut candidates = Vec::new();
+    for name in names {
+        for template in templates[name].iter() {
+            for _ in 0..config.samples_per_template {
+                candidates.push((name.as_str(), synthesize(template, &mut rng), &library));
+            }
+        }
+    }
+
+    println!(
+        "{} synthetic candidates ({} per template, seed {})",
+        candidates.len(),
+        config.samples_per_template,
+        config.seed
+    );
+    evaluate(candidates, recognition);
+}
+
+// user-dependent testing like in the $1/$N papers: per subject, the first
+// `train_per_class` samples of every class become the templates and the rest
+// of that subject's samples are recognized against them
+pub fn run_dataset(gestures: &[Gesture], config: &BenchConfig, recognition: &RecognitionConfig) {
+    let mut by_subject: HashMap<Option<&str>, Vec<&Gesture>> = HashMap::new();
+    for gesture in gestures {
+        by_subject.entry(gesture.subject.as_deref()).or_default().push(gesture);
+    }
+
+    let mut subjects = Vec::new();
+    for gestures in by_subject.into_values() {
+        let mut seen: HashMap<&str, usize> = HashMap::new();
+        let (train, test): (Vec<&Gesture>, Vec<&Gesture>) = gestures.into_iter().partition(|gesture| {
+            let count = seen.entry(gesture.name.as_str()).or_default();
+            *count += 1;
+            *count <= config.train_per_class
+        });
+        let mut library = GestureLibrary::new("dataset", dataset::into_templates(train, "dataset"));
+        library.set_resampling(recognition.n_points, recognition.preserve_corners);
+        subjects.push((library, test));
+    }
+
+    let candidates: Vec<_> = subjects
+        .iter()
+        .flat_map(|(library, test)| {
+            test.iter()
+                .map(move |gesture| (gesture.name.as_str(), gesture.strokes.clone(), library))
+        })
+        .collect();
+
+    println!(
+        "{} dataset candidates from {} subjects ({} templates per class)",
+        candidates.len(),
+        subjects.len(),
+        config.train_per_class
+    );
+    evaluate(candidates, recognition);
+}
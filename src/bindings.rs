@@ -0,0 +1,157 @@
+use std::fmt::Display;
+
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::storage;
+
+// file the bindings are read from when there's no `--bindings=<file>`
+const DEFAULT_BINDINGS_FILE: &str = "bindings.toml";
+
+#[derive(Clone, Copy)]
+pub enum Action {
+    Draw,
+    Recognize,
+    Undo,
+    ToggleBrush,
+    AddGesture,
+    ToggleOverlay,
+    ToggleRecording,
+    ToggleCompare,
+    Pan,
+    Diagnose,
+    ToggleContrast,
+    SaveSession,
+    RestoreSession,
+}
+
+// a key is written as its KeyCode name ("Space", "KeyZ", "Enter"...) and a
+// mouse button as an inline table: { mouse = "Left" }
+#[derive(Clone, Copy, Deserialize)]
+#[serde(untagged)]
+pub enum Binding {
+    Key(KeyCode),
+    Mouse { mouse: MouseButton },
+}
+
+// which keys and buttons trigger what, every action can have any number of
+// them (or none, to turn it off)
+#[derive(Resource, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct InputBindings {
+    pub draw: Vec<Binding>,
+    pub recognize: Vec<Binding>,
+    pub undo: Vec<Binding>,
+    pub toggle_brush: Vec<Binding>,
+    pub add_gesture: Vec<Binding>,
+    // the FPS and latency overlay
+    pub toggle_overlay: Vec<Binding>,
+    // appending the drawings to the session log
+    pub toggle_recording: Vec<Binding>,
+    // the drawing and the template it matched side by side
+    pub toggle_compare: Vec<Binding>,
+    // dragging the view of the board around
+    pub pan: Vec<Binding>,
+    // looking for templates that are likely to be confused, see `diagnostics`
+    pub diagnose: Vec<Binding>,
+    // the high-contrast colors, see `theme`
+    pub toggle_contrast: Vec<Binding>,
+    // the drawing on the board, see `saved_session`
+    pub save_session: Vec<Binding>,
+    pub restore_session: Vec<Binding>,
+}
+
+impl Default for InputBindings {
+    fn default() -> Self {
+        Self {
+            draw: vec![
+                Binding::Mouse {
+                    mouse: MouseButton::Left,
+                },
+                Binding::Key(KeyCode::Space),
+            ],
+            recognize: vec![Binding::Mouse {
+                mouse: MouseButton::Right,
+            }],
+            undo: vec![Binding::Key(KeyCode::Backspace)],
+            toggle_brush: vec![Binding::Key(KeyCode::KeyB)],
+            // not a letter, it would end up in the name box it opens
+            add_gesture: vec![Binding::Key(KeyCode::Tab)],
+            toggle_overlay: vec![Binding::Key(KeyCode::F3)],
+            toggle_recording: vec![Binding::Key(KeyCode::F5)],
+            toggle_compare: vec![Binding::Key(KeyCode::F4)],
+            pan: vec![Binding::Mouse {
+                mouse: MouseButton::Middle,
+            }],
+            diagnose: vec![Binding::Key(KeyCode::F6)],
+            toggle_contrast: vec![Binding::Key(KeyCode::F7)],
+            save_session: vec![Binding::Key(KeyCode::F8)],
+            restore_session: vec![Binding::Key(KeyCode::F9)],
+        }
+    }
+}
+
+impl InputBindings {
+    // actions missing from the file keep their default bindings, see
+    // `storage` for where it's read from
+    pub fn load(name: &str) -> Result<Self, String> {
+        let text = storage::read(name)?.ok_or_else(|| format!("{}: no such file", name))?;
+        Self::parse(&text, name)
+    }
+
+    // from `bindings.toml` if there is one (see `storage`), the defaults otherwise
+    pub fn load_default() -> Result<Self, String> {
+        match storage::read(DEFAULT_BINDINGS_FILE)? {
+            Some(text) => Self::parse(&text, DEFAULT_BINDINGS_FILE),
+            None => Ok(Self::default()),
+        }
+    }
+
+    fn parse(text: &str, name: impl Display) -> Result<Self, String> {
+        toml::from_str(text).map_err(|e| format!("{}: {}", name, e))
+    }
+
+    fn bindings(&self, action: Action) -> &[Binding] {
+        match action {
+            Action::Draw => &self.draw,
+            Action::Recognize => &self.recognize,
+            Action::Undo => &self.undo,
+            Action::ToggleBrush => &self.toggle_brush,
+            Action::AddGesture => &self.add_gesture,
+            Action::ToggleOverlay => &self.toggle_overlay,
+            Action::ToggleRecording => &self.toggle_recording,
+            Action::ToggleCompare => &self.toggle_compare,
+            Action::Pan => &self.pan,
+            Action::Diagnose => &self.diagnose,
+            Action::ToggleContrast => &self.toggle_contrast,
+            Action::SaveSession => &self.save_session,
+            Action::RestoreSession => &self.restore_session,
+        }
+    }
+
+    pub fn pressed(&self, action: Action, keys: &ButtonInput<KeyCode>, mouse: &ButtonInput<MouseButton>) -> bool {
+        self.bindings(action).iter().any(|binding| match *binding {
+            Binding::Key(key) => keys.pressed(key),
+            Binding::Mouse { mouse: button } => mouse.pressed(button),
+        })
+    }
+
+    pub fn just_pressed(&self, action: Action, keys: &ButtonInput<KeyCode>, mouse: &ButtonInput<MouseButton>) -> bool {
+        self.bindings(action).iter().any(|binding| match *binding {
+            Binding::Key(key) => keys.just_pressed(key),
+            Binding::Mouse { mouse: button } => mouse.just_pressed(button),
+        })
+    }
+
+    pub fn just_released(&self, action: Action, keys: &ButtonInput<KeyCode>, mouse: &ButtonInput<MouseButton>) -> bool {
+        self.bindings(action).iter().any(|binding| match *binding {
+            Binding::Key(key) => keys.just_released(key),
+            Binding::Mouse { mouse: button } => mouse.just_released(button),
+        })
+    }
+
+    // the one `script` presses, None for an action that's turned off
+    pub fn first(&self, action: Action) -> Option<Binding> {
+        self.bindings(action).first().copied()
+    }
+}
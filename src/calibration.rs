@@ -0,0 +1,108 @@
+use std::collections::HashMap;
+
+use bevy::{math::Vec2, prelude::Resource};
+
+use crate::{
+    library::GestureLibrary,
+    recognizer::{cloud_distance, Match, RecognitionConfig},
+    templates::Template,
+};
+
+// big classes are calibrated on their first few templates only, it's all
+// pairwise distances
+const MAX_CALIBRATION_TEMPLATES: usize = 20;
+
+// raw greedy distances depend on how complicated a gesture is, so every class
+// gets a typical distance that its own samples are apart from each other and
+// matches are scored relative to it
+#[derive(Resource, Default)]
+pub struct Calibration {
+    // (set, gesture name) -> typical distance
+    typical_distances: HashMap<(String, String), f32>,
+    // for classes added since the last calibration
+    fallback: f32,
+}
+
+impl Calibration {
+    pub fn new(library: &GestureLibrary, config: &RecognitionConfig) -> Self {
+        let classes: Vec<((String, String), Vec<&Vec<Vec2>>)> = library
+            .active_sets()
+            .flat_map(|(set, templates)| {
+                templates.iter().map(move |(name, templates)| {
+                    // the same first few every run, see `Template::stable_cmp`
+                    let mut templates: Vec<&Template> =
+                        templates.iter().filter(|template| template.cloud.len() == config.n_points).collect();
+                    templates.sort_by(|a, b| a.stable_cmp(b));
+                    let clouds: Vec<&Vec<Vec2>> = templates
+                        .into_iter()
+                        .take(MAX_CALIBRATION_TEMPLATES)
+                        .map(|template| &template.cloud)
+                        .collect();
+                    ((set.to_string(), name.clone()), clouds)
+                })
+            })
+            .filter(|(_, clouds)| !clouds.is_empty())
+            .collect();
+
+        let mut typical_distances = HashMap::new();
+        for (i, (class, clouds)) in classes.iter().enumerate() {
+            let typical = if clouds.len() > 1 {
+                // how far a sample is from the closest other sample of its
+                // class, which is what a new drawing of it is up against
+                let nearest: Vec<f32> = clouds
+                    .iter()
+                    .enumerate()
+                    .map(|(j, a)| {
+                        clouds
+                            .iter()
+                            .enumerate()
+                            .filter(|(k, _)| *k != j)
+                            .map(|(_, b)| cloud_distance(a, b, config))
+                            .fold(f32::MAX, f32::min)
+                    })
+                    .collect();
+                nearest.iter().sum::<f32>() / nearest.len() as f32
+            } else {
+                // a single template can't tell how much it varies, half way to
+                // the closest other class is where a match becomes a toss-up
+                let closest_other = classes
+                    .iter()
+                    .enumerate()
+                    .filter(|(j, _)| *j != i)
+                    .flat_map(|(_, (_, others))| others.iter())
+                    .map(|other| cloud_distance(clouds[0], other, config))
+                    .fold(f32::MAX, f32::min);
+                closest_other / 2.0
+            };
+            if typical.is_finite() && typical > 0.0 && typical < f32::MAX / 2.0 {
+                typical_distances.insert(class.clone(), typical);
+            }
+        }
+
+        let mut typical: Vec<f32> = typical_distances.values().copied().collect();
+        typical.sort_by(f32::total_cmp);
+        let fallback = typical.get(typical.len() / 2).copied().unwrap_or(1.0);
+
+        Self {
+            typical_distances,
+            fallback,
+        }
+    }
+
+    // 1 for a perfect match, 0.5 at the class's typical distance and towards 0 beyond
+    pub fn confidence(&self, result: &Match) -> f32 {
+        self.confidence_at(&result.set, &result.name, result.distance)
+    }
+
+    // how sure a match with the class at `distance` would be
+    pub fn confidence_at(&self, set: &str, name: &str, distance: f32) -> f32 {
+        0.5f32.powf(distance / self.typical_distance(set, name))
+    }
+
+    pub fn typical_distance(&self, set: &str, name: &str) -> f32 {
+        self.typical_distances
+            .get(&(set.to_string(), name.to_string()))
+            .copied()
+            .unwrap_or(self.fallback)
+    }
+}
@@ -0,0 +1,238 @@
+use bevy::{
+    asset::RenderAssetUsages,
+    input::mouse::AccumulatedMouseMotion,
+    prelude::*,
+    render::{
+        camera::RenderTarget,
+        render_resource::{Extent3d, TextureDimension, TextureFormat},
+        view::RenderLayers,
+    },
+    window::{WindowRef, WindowResized, WindowResolution},
+};
+use web_time::Instant;
+
+use crate::{
+    bindings::{Action, InputBindings},
+    calibration::Calibration,
+    fill_pixel, latency_since,
+    library::GestureLibrary,
+    paint_segment,
+    recognizer::{recognize_top, RecognitionConfig},
+    reset_board,
+    theme::Theme,
+    ui_strings::{fill, UiStrings},
+    BrushConfig, BrushEnabled, CandidateVectors, DrawMoment, DrawState, GestureRecognized, PenSample,
+};
+
+const CANVAS_WINDOW_SIZE: Vec2 = Vec2::new(640.0, 480.0);
+
+// `--canvases=<names>;<names>;...` opens a window with a canvas of its own for
+// every entry, matched against the gestures or sets named in it (comma
+// separated) or, for `*`, against the active sets like the main canvas
+#[derive(Resource, Default, Clone)]
+pub struct ExtraCanvases(Vec<Option<Vec<String>>>);
+
+impl ExtraCanvases {
+    // every entry names something, and `*` goes on its own
+    pub fn parse(text: &str) -> Result<Self, String> {
+        text.split(';')
+            .map(|entry| {
+                let classes: Vec<String> = entry
+                    .split(',')
+                    .map(|name| name.trim().to_string())
+                    .filter(|name| !name.is_empty())
+                    .collect();
+                if classes.is_empty() {
+                    return Err(format!("{}: no gestures, it's * for the active sets", entry));
+                }
+                if classes.len() > 1 && classes.iter().any(|name| name == "*") {
+                    return Err(format!("{}: * goes on its own", entry));
+                }
+                Ok((classes != ["*"]).then_some(classes))
+            })
+            .collect::<Result<_, _>>()
+            .map(Self)
+    }
+}
+
+// on the window of an extra canvas, along with its own `DrawState` and
+// `CandidateVectors`. The board covers the whole window, board and window
+// coordinates are the same
+#[derive(Component)]
+pub struct ExtraCanvas {
+    // 1 and up, the main canvas is 0. Recognitions on it are tagged with it
+    pub index: usize,
+    pub board: Handle<Image>,
+    painted: Option<URect>,
+    // what drawings on it can be, None for anything in the active sets
+    classes: Option<Vec<String>>,
+}
+
+// every canvas gets a window, a camera and a render layer so the cameras only
+// show their own board
+pub fn spawn_extra_canvases(
+    mut commands: Commands,
+    canvases: Res<ExtraCanvases>,
+    mut images: ResMut<Assets<Image>>,
+    strings: Res<UiStrings>,
+    theme: Res<Theme>,
+) {
+    for (i, classes) in canvases.0.iter().enumerate() {
+        let index = i + 1;
+        let title = match classes {
+            Some(classes) => fill(&strings.canvas_classes, &[("index", &index), ("classes", &classes.join(", "))]),
+            None => fill(&strings.canvas, &[("index", &index)]),
+        };
+        let window = commands
+            .spawn(Window {
+                title,
+                resolution: WindowResolution::new(CANVAS_WINDOW_SIZE.x, CANVAS_WINDOW_SIZE.y),
+                ..default()
+            })
+            .id();
+        let layer = RenderLayers::layer(index);
+        commands.spawn((
+            Camera2d,
+            Camera {
+                target: RenderTarget::Window(WindowRef::Entity(window)),
+                ..default()
+            },
+            layer.clone(),
+        ));
+        let board = images.add(Image::new_fill(
+            Extent3d {
+                width: CANVAS_WINDOW_SIZE.x as u32,
+                height: CANVAS_WINDOW_SIZE.y as u32,
+                depth_or_array_layers: 1,
+            },
+            TextureDimension::D2,
+            &(theme.board.to_srgba().to_u8_array()),
+            TextureFormat::Rgba8UnormSrgb,
+            RenderAssetUsages::MAIN_WORLD | RenderAssetUsages::RENDER_WORLD,
+        ));
+        commands.spawn((Sprite::from_image(board.clone()), layer));
+        commands.entity(window).insert((
+            ExtraCanvas {
+                index,
+                board,
+                painted: None,
+                classes: classes.clone(),
+            },
+            DrawState(DrawMoment::Idle),
+            CandidateVectors::default(),
+        ));
+    }
+}
+
+// drawing and recognizing on the extra canvases, with the same bindings as the
+// main one. Every drawing ends with a recognition, the next stroke after it
+// starts a new one; the result goes in the window's title and out as a
+// `GestureRecognized` with the canvas's index
+pub fn draw_on_extra_canvases(
+    mut canvases: Query<(Entity, &mut Window, &mut ExtraCanvas, &mut DrawState, &mut CandidateVectors)>,
+    mut resized: EventReader<WindowResized>,
+    bindings: Res<InputBindings>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    buttons: Res<ButtonInput<MouseButton>>,
+    mouse_move_delta: Res<AccumulatedMouseMotion>,
+    mut images: ResMut<Assets<Image>>,
+    time: Res<Time>,
+    brush: Res<BrushConfig>,
+    brush_enabled: Res<BrushEnabled>,
+    library: Res<GestureLibrary>,
+    config: Res<RecognitionConfig>,
+    calibration: Res<Calibration>,
+    mut recognized: EventWriter<GestureRecognized>,
+    strings: Res<UiStrings>,
+    theme: Res<Theme>,
+) {
+    let resized: Vec<Entity> = resized.read().map(|event| event.window).collect();
+    for (entity, mut window, mut canvas, mut draw_state, mut drawing) in &mut canvases {
+        // `get_mut` marks the whole board as changed and it's sent to the GPU
+        // again, so only when it's painted on
+        let ExtraCanvas { board, painted, .. } = &mut *canvas;
+        let handle = &*board;
+        if resized.contains(&entity) {
+            // the drawing goes, it's a new board
+            let board = images.get_mut(handle).expect("Board not found!!");
+            reset_board(window.size(), board, painted, &theme);
+            *drawing = CandidateVectors::default();
+            draw_state.0 = DrawMoment::Idle;
+        }
+
+        let size = images.get(handle).expect("Board not found!!").size_f32();
+        let cursor = window
+            .cursor_position()
+            .filter(|cursor| Rect::from_corners(Vec2::ZERO, size).contains(*cursor));
+        if let Some(position) = cursor.filter(|_| bindings.just_pressed(Action::Draw, &keyboard, &buttons)) {
+            let board = images.get_mut(handle).expect("Board not found!!");
+            if draw_state.0 != DrawMoment::Paused {
+                *drawing = CandidateVectors::default();
+                reset_board(size, board, painted, &theme);
+            }
+            drawing.begin_stroke(&brush);
+            if let Some(point) = drawing.push(position, time.elapsed_secs(), PenSample::default()) {
+                fill_pixel(board, painted, point, true, brush_enabled.0, 1.0, &theme);
+            }
+            draw_state.0 = DrawMoment::Drawing(position, PenSample::default());
+        } else if let Some(position) = cursor.filter(|_| {
+            matches!(draw_state.0, DrawMoment::Drawing(..))
+                && bindings.pressed(Action::Draw, &keyboard, &buttons)
+                && mouse_move_delta.delta != Vec2::ZERO
+        }) {
+            let previous = drawing.last_point().unwrap_or(position);
+            if let Some(point) = drawing.push(position, time.elapsed_secs(), PenSample::default()) {
+                let board = images.get_mut(handle).expect("Board not found!!");
+                paint_segment(board, painted, previous, point, brush_enabled.0, 1.0, &theme);
+            }
+        }
+        if matches!(draw_state.0, DrawMoment::Drawing(..)) && bindings.just_released(Action::Draw, &keyboard, &buttons)
+        {
+            draw_state.0 = DrawMoment::Paused;
+        }
+
+        if cursor.is_none()
+            || !bindings.just_released(Action::Recognize, &keyboard, &buttons)
+            || drawing.strokes.iter().all(|stroke| stroke.is_empty())
+        {
+            continue;
+        }
+        draw_state.0 = DrawMoment::Idle;
+        let start_time = Instant::now();
+        let candidate = drawing
+            .candidate(&config)
+            .on_canvas(size)
+            .restrict(canvas.classes.clone());
+        let best = recognize_top(&library, &candidate, &config, 1)
+            .into_iter()
+            .next()
+            .map(|result| {
+                let confidence = calibration.confidence(&result);
+                (result, confidence)
+            })
+            .filter(|(_, confidence)| *confidence >= config.min_confidence);
+        let latency_ms = latency_since(start_time);
+        window.title = match best {
+            Some((result, confidence)) => {
+                let title = fill(
+                    &strings.canvas_recognized,
+                    &[
+                        ("index", &canvas.index),
+                        ("name", &result.name),
+                        ("confidence", &format!("{:.0}", confidence * 100.0)),
+                    ],
+                );
+                recognized.send(GestureRecognized {
+                    canvas: canvas.index,
+                    set: result.set,
+                    name: result.name,
+                    confidence,
+                    latency_ms,
+                    truncated: result.truncated,
+                });
+                title
+            }
+            None => fill(&strings.canvas_not_recognized, &[("index", &canvas.index)]),
+        };
+    }
+}
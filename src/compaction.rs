@@ -0,0 +1,147 @@
+use std::collections::{BTreeSet, HashSet};
+
+use bevy::{math::Vec2, prelude::Resource};
+
+use crate::{
+    recognizer::{cloud_distance, scale_and_translate, RecognitionConfig},
+    templates::Template,
+};
+
+// samples of the same gesture drawn a bit differently are about 0.0013 apart
+// and the closest other gesture about 0.0075, so this only catches near duplicates
+const DEFAULT_MERGE_THRESHOLD: f32 = 0.002;
+const DEFAULT_MAX_TEMPLATES: usize = 20;
+
+#[derive(Resource)]
+pub struct CompactionConfig {
+    // templates of a gesture closer than this are merged. It's the greedy
+    // distance divided by the number of points, so it means the same at every resolution
+    pub threshold: f32,
+    // a gesture with more templates gets its closest ones merged until it fits
+    pub max_templates: usize,
+}
+
+impl Default for CompactionConfig {
+    fn default() -> Self {
+        Self {
+            threshold: DEFAULT_MERGE_THRESHOLD,
+            max_templates: DEFAULT_MAX_TEMPLATES,
+        }
+    }
+}
+
+// groups the templates of a gesture, repeatedly joining the two closest groups
+// while they are within `threshold` or there are more than `max_templates`
+// groups, and keeps one template per group: the one closest to the others or,
+// with `average`, the average of all of them
+pub fn compact(
+    templates: &HashSet<Template>,
+    threshold: f32,
+    max_templates: usize,
+    average: bool,
+    config: &RecognitionConfig,
+) -> HashSet<Template> {
+    if templates.len() < 2 {
+        return templates.clone();
+    }
+
+    // oldest first, so the same library always compacts the same way
+    let mut templates: Vec<&Template> = templates.iter().collect();
+    templates.sort_by_key(|template| template.created);
+
+    let n = templates.len();
+    let mut distances = vec![vec![0.0; n]; n];
+    for i in 0..n {
+        for j in i + 1..n {
+            let distance = cloud_distance(&templates[i].cloud, &templates[j].cloud, config) / config.n_points as f32;
+            distances[i][j] = distance;
+            distances[j][i] = distance;
+        }
+    }
+
+    // complete linkage, two groups are as far apart as their farthest members,
+    // so every template in a group is near all of the others
+    let mut groups: Vec<Vec<usize>> = (0..n).map(|i| vec![i]).collect();
+    let mut group_distances = distances.clone();
+    loop {
+        let closest = (0..groups.len())
+            .flat_map(|a| (a + 1..groups.len()).map(move |b| (a, b)))
+            .min_by(|&(a, b), &(c, d)| group_distances[a][b].total_cmp(&group_distances[c][d]));
+        let Some((a, b)) = closest else {
+            break;
+        };
+        if group_distances[a][b] > threshold && groups.len() <= max_templates.max(1) {
+            break;
+        }
+
+        let merged = groups.swap_remove(b);
+        groups[a].extend(merged);
+        let merged_distances: Vec<f32> = group_distances[a]
+            .iter()
+            .zip(&group_distances[b])
+            .map(|(x, y)| x.max(*y))
+            .collect();
+        for (row, d) in group_distances.iter_mut().zip(&merged_distances) {
+            row[a] = *d;
+        }
+        group_distances[a] = merged_distances;
+        group_distances.swap_remove(b);
+        for row in group_distances.iter_mut() {
+            row.swap_remove(b);
+        }
+    }
+
+    groups
+        .into_iter()
+        .map(|group| {
+            let medoid = *group
+                .iter()
+                .min_by(|&&a, &&b| {
+                    let sum = |i: usize| group.iter().map(|&j| distances[i][j]).sum::<f32>();
+                    sum(a).total_cmp(&sum(b))
+                })
+                .expect("groups are never empty");
+            if average && group.len() > 1 {
+                let members: Vec<&Template> = group.iter().map(|&i| templates[i]).collect();
+                average_template(templates[medoid], &members)
+            } else {
+                templates[medoid].clone()
+            }
+        })
+        .collect()
+}
+
+// every member's cloud is lined up with `reference` (each reference point is
+// paired with the nearest point of the member that isn't paired yet) and the
+// pairs are averaged point by point
+fn average_template(reference: &Template, members: &[&Template]) -> Template {
+    let mut sum = vec![Vec2::ZERO; reference.cloud.len()];
+    for member in members {
+        let mut remaining = member.cloud.clone();
+        for (i, point) in reference.cloud.iter().enumerate() {
+            let nearest = remaining
+                .iter()
+                .enumerate()
+                .min_by(|(_, a), (_, b)| a.distance_squared(*point).total_cmp(&b.distance_squared(*point)))
+                .map(|(j, _)| j);
+            if let Some(j) = nearest {
+                sum[i] += remaining.swap_remove(j);
+            }
+        }
+    }
+
+    let mut points: Vec<Vec2> = sum.into_iter().map(|point| point / members.len() as f32).collect();
+    scale_and_translate(&mut points);
+    let mut template = Template::new(points, "merged", reference.stroke_count);
+    template.tags = members
+        .iter()
+        .flat_map(|member| member.tags.iter().cloned())
+        .collect::<BTreeSet<String>>();
+    // only if all of them know theirs
+    template.size = members
+        .iter()
+        .map(|member| member.size)
+        .sum::<Option<f32>>()
+        .map(|size| size / members.len() as f32);
+    template
+}
@@ -0,0 +1,108 @@
+use bevy::{prelude::*, window::PrimaryWindow};
+
+use crate::{
+    bindings::{Action, InputBindings},
+    recognizer::{matched_pairs, RecognitionConfig},
+    theme::Theme,
+    ui_strings::{fill, UiStrings},
+    window_to_world, BoardSprite, CanvasRect, IsTyping, ResampledPoints, ResultText,
+};
+
+// how much of its half of the canvas a cloud takes up
+const CLOUD_FILL: f32 = 0.8;
+
+// the compare mode shows the last drawing's normalized cloud on the left half
+// of the canvas and the template it was recognized as on the right, with a
+// line between every pair of points the greedy matching put together
+#[derive(Resource, Default)]
+pub struct CompareView {
+    pub enabled: bool,
+    // the template of the last recognized drawing, empty if it wasn't
+    pub name: String,
+    pub template: Vec<Vec2>,
+    // worked out when they're first shown
+    pub pairs: Option<Vec<(usize, usize)>>,
+}
+
+impl CompareView {
+    pub fn set_template(&mut self, name: String, template: Vec<Vec2>) {
+        self.name = name;
+        self.template = template;
+        self.pairs = None;
+    }
+
+    fn describe(&self, strings: &UiStrings) -> String {
+        if self.template.is_empty() {
+            strings.comparing_nothing.clone()
+        } else {
+            fill(&strings.comparing, &[("name", &self.name)])
+        }
+    }
+}
+
+// the compare mode replaces the board until it's toggled off again
+pub fn toggle_compare(
+    mut compare: ResMut<CompareView>,
+    mut board_sprite: Single<&mut Visibility, With<BoardSprite>>,
+    mut result_text: Single<&mut Text, With<ResultText>>,
+    bindings: Res<InputBindings>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    buttons: Res<ButtonInput<MouseButton>>,
+    typing: Res<IsTyping>,
+    strings: Res<UiStrings>,
+) {
+    if typing.0 || !bindings.just_pressed(Action::ToggleCompare, &keyboard, &buttons) {
+        return;
+    }
+    compare.enabled = !compare.enabled;
+    if compare.enabled {
+        **board_sprite = Visibility::Hidden;
+        result_text.0 = compare.describe(&strings);
+    } else {
+        **board_sprite = Visibility::Inherited;
+        result_text.0 = strings.stopped_comparing.clone();
+    }
+}
+
+pub fn draw_compare(
+    mut gizmos: Gizmos,
+    mut compare: ResMut<CompareView>,
+    candidate: Res<ResampledPoints>,
+    config: Res<RecognitionConfig>,
+    canvas: Res<CanvasRect>,
+    window: Single<&Window, With<PrimaryWindow>>,
+    theme: Res<Theme>,
+) {
+    if !compare.enabled {
+        return;
+    }
+    // the view isn't zoomed or panned, it's laid out on the canvas as it is
+    let to_world = |point: Vec2| window_to_world(window.size(), point);
+    let (min, max) = (canvas.0.min, canvas.0.max);
+    let middle = canvas.0.center().x;
+    gizmos.line_2d(to_world(Vec2::new(middle, min.y)), to_world(Vec2::new(middle, max.y)), theme.compare_divider);
+
+    let candidate = &candidate.0.points;
+    if compare.template.is_empty() || candidate.len() != compare.template.len() {
+        return;
+    }
+    // normalized clouds fit in a unit box around the origin, with y going down
+    // like on the board
+    let scale = CLOUD_FILL * (canvas.0.width() / 2.0).min(canvas.0.height());
+    let left = Vec2::new((min.x + middle) / 2.0, canvas.0.center().y);
+    let right = Vec2::new((middle + max.x) / 2.0, canvas.0.center().y);
+    let place = |center: Vec2, point: Vec2| to_world(center + point * scale);
+
+    if compare.pairs.is_none() {
+        compare.pairs = Some(matched_pairs(candidate, &compare.template, &config));
+    }
+    for &(i, j) in compare.pairs.iter().flatten() {
+        gizmos.line_2d(place(left, candidate[i]), place(right, compare.template[j]), theme.compare_pair);
+    }
+    for point in candidate {
+        gizmos.circle_2d(Isometry2d::from_translation(place(left, *point)), 3.0, theme.compare_candidate);
+    }
+    for point in &compare.template {
+        gizmos.circle_2d(Isometry2d::from_translation(place(right, *point)), 3.0, theme.overlay);
+    }
+}
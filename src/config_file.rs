@@ -0,0 +1,25 @@
+use std::fs;
+
+use clap::Parser;
+
+use crate::{options::Options, storage};
+
+// file the startup options are read from when there's no `--config=<file>`
+const DEFAULT_CONFIG_FILE: &str = "stroke_recognizer.toml";
+
+// the command line, with the options of the config file that it doesn't set
+// itself. The file is optional, but one that was asked for has to load.
+// Unknown or invalid options are an error, and `--help` lists them
+pub fn options() -> Result<Options, String> {
+    let options = Options::parse();
+    let (name, text) = match &options.config {
+        Some(path) => (path.clone(), fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?),
+        None => match storage::read(DEFAULT_CONFIG_FILE)? {
+            Some(text) => (DEFAULT_CONFIG_FILE.to_string(), text),
+            None => return Ok(options),
+        },
+    };
+    let from_file: Options = toml::from_str(&text).map_err(|e| format!("{}: {}", name, e))?;
+    from_file.check().map_err(|e| format!("{}: {}", name, e))?;
+    Ok(options.or(from_file))
+}
@@ -0,0 +1,114 @@
+use bevy::math::Vec2;
+
+use crate::recognizer::{resample_stroke, stroke_length};
+
+// ShortStraw (Wolin, Eoff and Hammond, 2008). The stroke is resampled so its
+// points are evenly spaced, then every point gets a "straw": the distance
+// between the points WINDOW before and after it. Along a straight line the
+// straw is as long as it can be, at a corner the stroke folds back and it's
+// much shorter than the median
+
+// the spacing is the diagonal of the stroke's bounding box divided by this
+const SPACING_DIVISOR: f32 = 40.0;
+const WINDOW: usize = 3;
+// a straw shorter than this times the median is a corner
+const STRAW_THRESHOLD: f32 = 0.95;
+// the path between two corners is a straight line if the direct distance is at
+// least this much of its length, then the corner between them isn't one
+const LINE_THRESHOLD: f32 = 0.95;
+
+fn is_line(points: &[Vec2]) -> bool {
+    let length = stroke_length(points);
+    length == 0.0 || points[0].distance(points[points.len() - 1]) >= LINE_THRESHOLD * length
+}
+
+// the stroke split at its corners, each piece starts where the previous one ended.
+// A single piece if there are no corners
+pub fn split_at_corners(stroke: &[Vec2]) -> Vec<Vec<Vec2>> {
+    let length = stroke_length(stroke);
+    let (min, max) = stroke
+        .iter()
+        .fold((stroke[0], stroke[0]), |(min, max), p| (min.min(*p), max.max(*p)));
+    let spacing = min.distance(max) / SPACING_DIVISOR;
+    if length == 0.0 || spacing == 0.0 {
+        return vec![stroke.to_vec()];
+    }
+
+    let points = resample_stroke(stroke, length, (length / spacing).round() as usize + 1);
+    if points.len() < 2 * WINDOW + 1 {
+        return vec![stroke.to_vec()];
+    }
+
+    let straws: Vec<f32> = (WINDOW..points.len() - WINDOW)
+        .map(|i| points[i - WINDOW].distance(points[i + WINDOW]))
+        .collect();
+    let mut sorted = straws.clone();
+    sorted.sort_by(f32::total_cmp);
+    let threshold = sorted[sorted.len() / 2] * STRAW_THRESHOLD;
+
+    // the evenly spaced points cut across the corners, so every corner is the
+    // stroke's own point closest to the shortest straw of a run of short ones.
+    // Indices into `stroke`, the search goes on from the previous corner so
+    // they stay in order
+    let mut corners = vec![0];
+    let mut i = 0;
+    while i < straws.len() {
+        if straws[i] < threshold {
+            let mut shortest = i;
+            while i < straws.len() && straws[i] < threshold {
+                if straws[i] < straws[shortest] {
+                    shortest = i;
+                }
+                i += 1;
+            }
+            let corner = points[shortest + WINDOW];
+            let previous = corners[corners.len() - 1];
+            let closest = (previous..stroke.len())
+                .min_by(|&a, &b| stroke[a].distance_squared(corner).total_cmp(&stroke[b].distance_squared(corner)))
+                .unwrap_or(previous);
+            if closest > previous && closest < stroke.len() - 1 {
+                corners.push(closest);
+            }
+        }
+        i += 1;
+    }
+    corners.push(stroke.len() - 1);
+
+    // a slow bend gives a run of short straws without a real corner in it
+    let mut i = 1;
+    while i + 1 < corners.len() {
+        if is_line(&stroke[corners[i - 1]..=corners[i + 1]]) {
+            corners.remove(i);
+        } else {
+            i += 1;
+        }
+    }
+
+    corners
+        .windows(2)
+        .map(|w| stroke[w[0]..=w[1]].to_vec())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(from: Vec2, to: Vec2, n: usize) -> Vec<Vec2> {
+        (0..n).map(|i| from.lerp(to, i as f32 / (n - 1) as f32)).collect()
+    }
+
+    #[test]
+    fn an_l_splits_at_its_corner() {
+        let mut l = line(Vec2::new(0.0, 0.0), Vec2::new(0.0, 100.0), 30);
+        l.extend_from_slice(&line(Vec2::new(0.0, 100.0), Vec2::new(60.0, 100.0), 20)[1..]);
+        let pieces = split_at_corners(&l);
+        assert_eq!(pieces.len(), 2);
+        assert!(pieces[0].last().unwrap().distance(Vec2::new(0.0, 100.0)) < 10.0);
+    }
+
+    #[test]
+    fn a_line_stays_in_one_piece() {
+        assert_eq!(split_at_corners(&line(Vec2::new(0.0, 0.0), Vec2::new(80.0, 50.0), 40)).len(), 1);
+    }
+}
@@ -0,0 +1,196 @@
+use std::{fs, path::Path};
+
+use bevy::math::Vec2;
+
+use crate::{
+    library::GestureSet,
+    recognizer::{Candidate, N_RESAMPLED_POINTS},
+    templates::Template,
+};
+
+// one <Gesture> file from the $1 (unistroke) or MMG (multistroke) datasets
+pub struct Gesture {
+    pub name: String,
+    pub subject: Option<String>,
+    pub strokes: Vec<Vec<Vec2>>,
+}
+
+// "arrow01" ($1) and "arrowhead~01" (MMG) both belong to the same class as the
+// other samples without the number
+fn class_name(name: &str) -> String {
+    match name.split_once('~') {
+        Some((class, _)) => class.to_string(),
+        None => name.trim_end_matches(|c: char| c.is_ascii_digit()).to_string(),
+    }
+}
+
+fn parse_point(node: roxmltree::Node) -> Result<Vec2, String> {
+    let coordinate = |attribute: &str| -> Result<f32, String> {
+        node.attribute(attribute)
+            .ok_or(format!("point is missing the {} attribute", attribute))?
+            .parse::<f32>()
+            .map_err(|e| format!("invalid {} coordinate: {}", attribute, e))
+    };
+    Ok(Vec2::new(coordinate("X")?, coordinate("Y")?))
+}
+
+pub fn parse(xml: &str) -> Result<Gesture, String> {
+    let document = roxmltree::Document::parse(xml).map_err(|e| e.to_string())?;
+    let root = document.root_element();
+    if !root.has_tag_name("Gesture") {
+        return Err(format!("expected <Gesture>, found <{}>", root.tag_name().name()));
+    }
+
+    let name = root
+        .attribute("Name")
+        .ok_or("gesture is missing the Name attribute")?;
+
+    // MMG files group points into <Stroke>s, $1 files list them directly
+    let mut strokes = Vec::new();
+    let mut loose_points = Vec::new();
+    for child in root.children().filter(|n| n.is_element()) {
+        if child.has_tag_name("Stroke") {
+            strokes.push(
+                child
+                    .children()
+                    .filter(|n| n.has_tag_name("Point"))
+                    .map(parse_point)
+                    .collect::<Result<Vec<_>, _>>()?,
+            );
+        } else if child.has_tag_name("Point") {
+            loose_points.push(parse_point(child)?);
+        }
+    }
+    if !loose_points.is_empty() {
+        strokes.push(loose_points);
+    }
+    strokes.retain(|stroke| !stroke.is_empty());
+
+    if strokes.is_empty() {
+        return Err(format!("gesture {} has no points", name));
+    }
+
+    Ok(Gesture {
+        name: class_name(name),
+        subject: root.attribute("Subject").map(String::from),
+        strokes,
+    })
+}
+
+// every .xml file under `dir` (recursively), in path order so results are reproducible
+pub fn load_dir(dir: impl AsRef<Path>) -> Result<Vec<Gesture>, String> {
+    let mut paths = Vec::new();
+    let mut pending = vec![dir.as_ref().to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        let entries = fs::read_dir(&dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+        for entry in entries {
+            let path = entry.map_err(|e| e.to_string())?.path();
+            if path.is_dir() {
+                pending.push(path);
+            } else if path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("xml")) {
+                paths.push(path);
+            }
+        }
+    }
+    paths.sort();
+
+    paths
+        .iter()
+        .map(|path| {
+            let xml = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+            parse(&xml).map_err(|e| format!("{}: {}", path.display(), e))
+        })
+        .collect()
+}
+
+// None for gestures without any points
+pub fn to_template(gesture: &Gesture, source: &str) -> Option<Template> {
+    let candidate = Candidate::new(&gesture.strokes, N_RESAMPLED_POINTS, false);
+    if candidate.points.is_empty() {
+        return None;
+    }
+
+    let mut template = Template::new(candidate.points, source, candidate.stroke_count);
+    template.author = gesture.subject.clone();
+    Some(template)
+}
+
+pub fn into_templates<'a>(
+    gestures: impl IntoIterator<Item = &'a Gesture>,
+    source: &str,
+) -> GestureSet {
+    let mut templates = GestureSet::new();
+    for gesture in gestures {
+        if let Some(template) = to_template(gesture, source) {
+            templates
+                .entry(gesture.name.clone())
+                .or_default()
+                .insert(template);
+        }
+    }
+    templates
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a $1 unistroke sample, the points straight under <Gesture>
+    const UNISTROKE: &str = r#"<?xml version="1.0" encoding="utf-8" standalone="yes"?>
+<Gesture Name="arrow01" Subject="1" Speed="medium" Number="1" NumPts="3" Millseconds="400">
+  <Point X="10" Y="20" T="0" />
+  <Point X="15" Y="25" T="10" />
+  <Point X="20" Y="20" T="20" />
+</Gesture>"#;
+
+    // an MMG multistroke sample, the points grouped into <Stroke>s
+    const MULTISTROKE: &str = r#"<?xml version="1.0" encoding="utf-8" standalone="yes"?>
+<Gesture Name="arrowhead~02" Subject="10" Speed="FAST" Number="2" NumPts="5">
+  <Stroke index="1">
+    <Point X="0" Y="0" T="0" Pressure="128" />
+    <Point X="10" Y="10" T="10" Pressure="128" />
+  </Stroke>
+  <Stroke index="2">
+    <Point X="10" Y="10" T="30" Pressure="128" />
+    <Point X="20" Y="0" T="40" Pressure="128" />
+    <Point X="25" Y="-5" T="50" Pressure="128" />
+  </Stroke>
+</Gesture>"#;
+
+    #[test]
+    fn class_names_drop_the_sample_number() {
+        assert_eq!(class_name("arrow01"), "arrow");
+        assert_eq!(class_name("arrowhead~02"), "arrowhead");
+        assert_eq!(class_name("six_point_star~10"), "six_point_star");
+        assert_eq!(class_name("x"), "x");
+    }
+
+    #[test]
+    fn a_unistroke_sample_is_one_stroke() {
+        let gesture = parse(UNISTROKE).unwrap();
+        assert_eq!(gesture.name, "arrow");
+        assert_eq!(gesture.subject.as_deref(), Some("1"));
+        assert_eq!(
+            gesture.strokes,
+            vec![vec![Vec2::new(10.0, 20.0), Vec2::new(15.0, 25.0), Vec2::new(20.0, 20.0)]]
+        );
+    }
+
+    #[test]
+    fn a_multistroke_sample_keeps_its_strokes() {
+        let gesture = parse(MULTISTROKE).unwrap();
+        assert_eq!(gesture.name, "arrowhead");
+        assert_eq!(gesture.subject.as_deref(), Some("10"));
+        let lengths: Vec<usize> = gesture.strokes.iter().map(Vec::len).collect();
+        assert_eq!(lengths, [2, 3]);
+        assert_eq!(gesture.strokes[1][2], Vec2::new(25.0, -5.0));
+        assert_eq!(to_template(&gesture, "test").map(|template| template.stroke_count), Some(2));
+    }
+
+    #[test]
+    fn other_documents_are_rejected() {
+        assert!(parse("<Gestures/>").is_err());
+        assert!(parse("<Gesture Name=\"x\"></Gesture>").is_err());
+        assert!(parse("<Gesture><Point X=\"1\" Y=\"2\"/></Gesture>").is_err());
+    }
+}
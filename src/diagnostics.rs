@@ -0,0 +1,271 @@
+use std::collections::BTreeMap;
+
+use crate::{
+    calibration::Calibration,
+    library::{is_user_source, GestureLibrary},
+    recognizer::{cloud_distance, RecognitionConfig},
+    templates::Template,
+    ui_strings::{fill, UiStrings},
+};
+
+// it's all pairwise distances, big classes are looked at by their first few
+// templates like for the calibration
+const MAX_TEMPLATES_PER_CLASS: usize = 20;
+// a template of one class that would be recognized as another with at least
+// this confidence makes the two a conflict
+const CONFLICT_CONFIDENCE: f32 = 0.5;
+// a template this many times further from the rest of its gesture than the
+// gesture's templates usually are from each other is an outlier
+const OUTLIER_FACTOR: f32 = 2.5;
+
+// two gestures with templates so alike that drawings of one are likely to be
+// recognized as the other
+pub struct Conflict {
+    // (set, gesture name)
+    pub a: (String, String),
+    pub b: (String, String),
+    // between their closest templates
+    pub distance: f32,
+    // how sure the recognizer would be that the one is the other
+    pub confidence: f32,
+}
+
+pub struct Outlier {
+    pub set: String,
+    pub name: String,
+    // which of the gesture's templates, see `describe`
+    pub template: String,
+    // its mean distance to the other templates of its gesture, relative to
+    // the median of that over all of them
+    pub spread: f32,
+    // the gesture it's closer to than to its own, if there's one
+    pub closer_to: Option<(String, String)>,
+}
+
+// what's wrong with the templates of the active sets
+pub struct Diagnostics {
+    pub templates: usize,
+    pub gestures: usize,
+    // most confusable first
+    pub conflicts: Vec<Conflict>,
+    // misfits first
+    pub outliers: Vec<Outlier>,
+}
+
+struct Entry<'a> {
+    set: &'a str,
+    name: &'a str,
+    // 1 and up, in the order the gesture's templates were added
+    number: usize,
+    template: &'a Template,
+}
+
+// how a template is told apart from the others of its gesture, the ones
+// that aren't saved are all created when the app starts
+fn describe(entry: &Entry) -> String {
+    if !is_user_source(&entry.template.source) {
+        format!("#{} ({})", entry.number, entry.template.source)
+    } else {
+        format!(
+            "#{} ({}, added {})",
+            entry.number,
+            entry.template.source,
+            entry.template.created.format("%Y-%m-%d %H:%M:%S")
+        )
+    }
+}
+
+// cross-matches every template with every other one, at the library's
+// resolution. Quadratic in the number of templates
+pub fn diagnose(library: &GestureLibrary, config: &RecognitionConfig) -> Diagnostics {
+    let calibration = Calibration::new(library, config);
+    let mut entries = Vec::new();
+    let mut gestures = 0;
+    for (set, templates) in library.active_sets() {
+        for (name, templates) in templates {
+            let mut templates: Vec<&Template> =
+                templates.iter().filter(|template| template.cloud.len() == config.n_points).collect();
+            // the same numbers every time
+            templates.sort_by(|a, b| a.stable_cmp(b));
+            gestures += 1;
+            entries.extend(templates.into_iter().take(MAX_TEMPLATES_PER_CLASS).enumerate().map(
+                |(i, template)| Entry {
+                    set,
+                    name,
+                    number: i + 1,
+                    template,
+                },
+            ));
+        }
+    }
+
+    let mut distances = vec![vec![0.0; entries.len()]; entries.len()];
+    for i in 0..entries.len() {
+        for j in i + 1..entries.len() {
+            // both ways round already, see `greedy_5`
+            let distance = cloud_distance(&entries[i].template.cloud, &entries[j].template.cloud, config);
+            distances[i][j] = distance;
+            distances[j][i] = distance;
+        }
+    }
+    // the first entry of every entry's gesture, they're all next to each other
+    let class: Vec<usize> = (0..entries.len()).map(|i| i + 1 - entries[i].number).collect();
+    let same_class = |i: usize, j: usize| class[i] == class[j];
+
+    // the closest pair of templates of every two gestures
+    let mut closest: BTreeMap<(usize, usize), f32> = BTreeMap::new();
+    for i in 0..entries.len() {
+        for j in i + 1..entries.len() {
+            if !same_class(i, j) {
+                let distance = closest.entry((class[i], class[j])).or_insert(f32::MAX);
+                *distance = distance.min(distances[i][j]);
+            }
+        }
+    }
+    let mut conflicts: Vec<Conflict> = closest
+        .into_iter()
+        .filter_map(|((a, b), distance)| {
+            let (a, b) = (&entries[a], &entries[b]);
+            // a drawing of either one could go to the other
+            let confidence = calibration
+                .confidence_at(b.set, b.name, distance)
+                .max(calibration.confidence_at(a.set, a.name, distance));
+            (confidence >= CONFLICT_CONFIDENCE).then(|| Conflict {
+                a: (a.set.to_string(), a.name.to_string()),
+                b: (b.set.to_string(), b.name.to_string()),
+                distance,
+                confidence,
+            })
+        })
+        .collect();
+    conflicts.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+
+    let nearest = |i: usize, own: bool| {
+        (0..entries.len())
+            .filter(|&j| j != i && same_class(i, j) == own)
+            .min_by(|&a, &b| distances[i][a].total_cmp(&distances[i][b]))
+    };
+    let mean_own: Vec<Option<f32>> = (0..entries.len())
+        .map(|i| {
+            let own: Vec<f32> = (0..entries.len())
+                .filter(|&j| j != i && same_class(i, j))
+                .map(|j| distances[i][j])
+                .collect();
+            (!own.is_empty()).then(|| own.iter().sum::<f32>() / own.len() as f32)
+        })
+        .collect();
+    // the median, a far off template would pull a mean along with it
+    let mut usual: BTreeMap<usize, Vec<f32>> = BTreeMap::new();
+    for (i, distance) in mean_own.iter().enumerate() {
+        usual.entry(class[i]).or_default().extend(*distance);
+    }
+    let usual: BTreeMap<usize, f32> = usual
+        .into_iter()
+        .map(|(class, mut means)| {
+            means.sort_by(f32::total_cmp);
+            (class, means.get(means.len() / 2).copied().unwrap_or(0.0))
+        })
+        .collect();
+
+    let mut outliers = Vec::new();
+    for (i, entry) in entries.iter().enumerate() {
+        // the only template of its gesture has nothing to stand out from
+        let (Some(own), Some(mean)) = (nearest(i, true), mean_own[i]) else {
+            continue;
+        };
+        let spread = mean / usual[&class[i]].max(f32::EPSILON);
+        let closer_to = nearest(i, false)
+            .filter(|&other| distances[i][other] < distances[i][own])
+            .map(|other| (entries[other].set.to_string(), entries[other].name.to_string()));
+        if closer_to.is_some() || spread > OUTLIER_FACTOR {
+            outliers.push(Outlier {
+                set: entry.set.to_string(),
+                name: entry.name.to_string(),
+                template: describe(entry),
+                spread,
+                closer_to,
+            });
+        }
+    }
+
+    // the ones that would be misrecognized first, then the furthest off
+    outliers.sort_by(|a, b| {
+        b.closer_to
+            .is_some()
+            .cmp(&a.closer_to.is_some())
+            .then(b.spread.total_cmp(&a.spread))
+    });
+
+    Diagnostics {
+        templates: entries.len(),
+        gestures,
+        conflicts,
+        outliers,
+    }
+}
+
+impl Diagnostics {
+    pub fn summary(&self, strings: &UiStrings) -> String {
+        fill(
+            &strings.diagnostics,
+            &[
+                ("conflicts", &self.conflicts.len()),
+                ("outliers", &self.outliers.len()),
+                ("templates", &self.templates),
+                ("gestures", &self.gestures),
+            ],
+        )
+    }
+
+    // the conflicts and outliers, one per line
+    pub fn lines(&self, strings: &UiStrings) -> Vec<String> {
+        let gesture = |set: &str, name: &str| fill(&strings.gesture_in_set, &[("name", &name), ("set", &set)]);
+        let conflicts = self.conflicts.iter().map(|conflict| {
+            fill(
+                &strings.conflict,
+                &[
+                    ("a", &gesture(&conflict.a.0, &conflict.a.1)),
+                    ("b", &gesture(&conflict.b.0, &conflict.b.1)),
+                    ("confidence", &format!("{:.0}", conflict.confidence * 100.0)),
+                    ("distance", &format!("{:.3}", conflict.distance)),
+                ],
+            )
+        });
+        let outliers = self.outliers.iter().map(|outlier| match &outlier.closer_to {
+            Some((set, name)) => fill(
+                &strings.outlier_closer,
+                &[
+                    ("gesture", &gesture(&outlier.set, &outlier.name)),
+                    ("template", &outlier.template),
+                    ("other", &gesture(set, name)),
+                ],
+            ),
+            None => fill(
+                &strings.outlier,
+                &[
+                    ("gesture", &gesture(&outlier.set, &outlier.name)),
+                    ("template", &outlier.template),
+                    ("spread", &format!("{:.1}", outlier.spread)),
+                ],
+            ),
+        });
+        conflicts.chain(outliers).collect()
+    }
+
+    // for the terminal, what `--diagnose` prints and F6 logs. Always English
+    pub fn report(&self) -> String {
+        let strings = UiStrings::default();
+        let mut report = self.summary(&strings);
+        if !self.conflicts.is_empty() {
+            report.push_str("\n\nconflicts, gestures likely to be mistaken for each other:");
+        }
+        for (i, line) in self.lines(&strings).into_iter().enumerate() {
+            if i == self.conflicts.len() {
+                report.push_str("\n\noutliers, templates unlike the rest of their gesture:");
+            }
+            report.push_str("\n  ");
+            report.push_str(&line);
+        }
+        report
+    }
+}
@@ -0,0 +1,108 @@
+use bevy::math::Vec2;
+
+// how far (as a share of the points) the warping path may stray from the
+// diagonal, the Sakoe-Chiba band. Keeps it from matching the start of one
+// drawing with the end of the other, and the matching O(n * band)
+const BAND: f32 = 0.1;
+
+// dynamic time warping between two point sequences in drawing order: the
+// cheapest way to walk both from start to end, pairing every point with at
+// least one of the other sequence's, summing the squared distances of the
+// pairs. Unlike the point cloud matching it cares about order, and unlike
+// comparing point i with point i it lets one drawing speed up or slow down
+// along the way. Gives up once a whole row is over `bound`, every path from
+// there is too, and some value above the bound comes back instead
+pub fn distance(a: &[Vec2], b: &[Vec2], bound: f32) -> f32 {
+    if a.is_empty() || b.is_empty() {
+        return f32::MAX;
+    }
+    let band = ((a.len().max(b.len()) as f32 * BAND).ceil() as usize).max(a.len().abs_diff(b.len())) + 1;
+
+    // only the previous row of the table is needed
+    let mut previous = vec![f32::MAX; b.len()];
+    let mut current = vec![f32::MAX; b.len()];
+    for (i, point) in a.iter().enumerate() {
+        let first = i.saturating_sub(band);
+        let last = (i + band).min(b.len() - 1);
+        current.fill(f32::MAX);
+        let mut row_min = f32::MAX;
+        for j in first..=last {
+            let cheapest_before = if i == 0 && j == 0 {
+                0.0
+            } else {
+                let up = previous[j];
+                let left = if j > 0 { current[j - 1] } else { f32::MAX };
+                let diagonal = if j > 0 { previous[j - 1] } else { f32::MAX };
+                up.min(left).min(diagonal)
+            };
+            if cheapest_before == f32::MAX {
+                continue;
+            }
+            current[j] = cheapest_before + point.distance_squared(b[j]);
+            row_min = row_min.min(current[j]);
+        }
+        if row_min > bound {
+            return row_min;
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+    previous[b.len() - 1]
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    use super::*;
+
+    // the whole table, no band and no bound
+    fn unbanded(a: &[Vec2], b: &[Vec2]) -> f32 {
+        let mut table = vec![vec![f32::MAX; b.len()]; a.len()];
+        for i in 0..a.len() {
+            for j in 0..b.len() {
+                let cheapest_before = match (i, j) {
+                    (0, 0) => 0.0,
+                    (0, _) => table[0][j - 1],
+                    (_, 0) => table[i - 1][0],
+                    _ => table[i - 1][j].min(table[i][j - 1]).min(table[i - 1][j - 1]),
+                };
+                table[i][j] = cheapest_before + a[i].distance_squared(b[j]);
+            }
+        }
+        table[a.len() - 1][b.len() - 1]
+    }
+
+    fn random_points(rng: &mut StdRng, n: usize) -> Vec<Vec2> {
+        (0..n).map(|_| Vec2::new(rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0))).collect()
+    }
+
+    #[test]
+    fn the_band_covers_short_and_uneven_inputs() {
+        let mut rng = StdRng::seed_from_u64(0);
+        // short enough or uneven enough for the band to span the whole table
+        for (n, m) in [(1, 1), (1, 3), (2, 2), (3, 3), (2, 6), (6, 2)] {
+            for _ in 0..20 {
+                let (a, b) = (random_points(&mut rng, n), random_points(&mut rng, m));
+                let expected = unbanded(&a, &b);
+                assert!((distance(&a, &b, f32::MAX) - expected).abs() <= expected * 1e-5);
+            }
+        }
+    }
+
+    #[test]
+    fn the_band_never_finds_a_cheaper_path() {
+        let mut rng = StdRng::seed_from_u64(1);
+        for n in [4, 8, 16, 32] {
+            for _ in 0..20 {
+                let (a, b) = (random_points(&mut rng, n), random_points(&mut rng, n));
+                assert!(distance(&a, &b, f32::MAX) >= unbanded(&a, &b) * (1.0 - 1e-5));
+            }
+        }
+    }
+
+    #[test]
+    fn a_sequence_is_no_distance_from_itself() {
+        let points = random_points(&mut StdRng::seed_from_u64(2), 32);
+        assert_eq!(distance(&points, &points, f32::MAX), 0.0);
+    }
+}
@@ -0,0 +1,65 @@
+use std::{
+    fmt::Write,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use bevy::{image::Image, math::Vec2};
+use chrono::Utc;
+
+use crate::theme::Theme;
+
+// how thick the polylines are in the SVG, roughly what the brush paints
+const SVG_STROKE_WIDTH: f32 = 6.0;
+
+// the raw strokes as one polyline each, in board coordinates and the theme's
+// colors like the PNG
+pub fn to_svg(strokes: &[Vec<Vec2>], size: Vec2, theme: &Theme) -> String {
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{0}\" height=\"{1}\" viewBox=\"0 0 {0} {1}\">\n\
+         <rect width=\"100%\" height=\"100%\" fill=\"{2}\"/>\n",
+        size.x,
+        size.y,
+        theme.board.to_srgba().to_hex()
+    );
+    for stroke in strokes.iter().filter(|stroke| !stroke.is_empty()) {
+        let points: Vec<String> = stroke.iter().map(|p| format!("{:.1},{:.1}", p.x, p.y)).collect();
+        let _ = writeln!(
+            svg,
+            "<polyline points=\"{}\" fill=\"none\" stroke=\"{}\" stroke-width=\"{}\" \
+             stroke-linecap=\"round\" stroke-linejoin=\"round\"/>",
+            points.join(" "),
+            theme.brush.to_srgba().to_hex(),
+            SVG_STROKE_WIDTH
+        );
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+// writes the board as `stroke-<timestamp>.png` into `dir`, plus the strokes
+// as an SVG next to it if there are any, and returns the paths written
+pub fn export(
+    board: &Image,
+    strokes: &[Vec<Vec2>],
+    theme: &Theme,
+    dir: impl AsRef<Path>,
+) -> Result<Vec<PathBuf>, String> {
+    let stem = format!("stroke-{}", Utc::now().format("%Y%m%d-%H%M%S-%3f"));
+    let png_path = dir.as_ref().join(format!("{}.png", stem));
+    board
+        .clone()
+        .try_into_dynamic()
+        .map_err(|e| e.to_string())?
+        .save(&png_path)
+        .map_err(|e| format!("{}: {}", png_path.display(), e))?;
+
+    let mut written = vec![png_path];
+    if strokes.iter().any(|stroke| !stroke.is_empty()) {
+        let svg_path = dir.as_ref().join(format!("{}.svg", stem));
+        fs::write(&svg_path, to_svg(strokes, board.size_f32(), theme))
+            .map_err(|e| format!("{}: {}", svg_path.display(), e))?;
+        written.push(svg_path);
+    }
+    Ok(written)
+}
@@ -0,0 +1,75 @@
+use bevy::{prelude::*, window::PrimaryWindow};
+
+use crate::{
+    gesture_input::CandidateComplete, theme::Theme, BoardView, CanvasRect, DrawMoment, DrawState, IsTyping, PenSample,
+};
+
+// board pixels per second with the stick all the way over
+const CURSOR_SPEED: f32 = 600.0;
+const CURSOR_RADIUS: f32 = 6.0;
+// holding it down draws, like the left mouse button
+const PEN_BUTTON: GamepadButton = GamepadButton::RightTrigger2;
+const RECOGNIZE_BUTTON: GamepadButton = GamepadButton::South;
+
+// where the right stick has moved the gamepad's cursor to, in board
+// coordinates. None until a stick is first touched
+#[derive(Resource, Default)]
+pub struct GamepadCursor(Option<Vec2>);
+
+// the right stick moves a cursor over the board, the right trigger draws at it
+// and the south face button (A on Xbox layouts) recognizes. Everything goes
+// through `DrawState` and `CandidateComplete` like the mouse, so it runs after
+// `draw_state_handler` and only takes over while a gamepad is being used
+pub fn gamepad_input(
+    gamepads: Query<&Gamepad>,
+    time: Res<Time>,
+    canvas: Res<CanvasRect>,
+    is_typing: Res<IsTyping>,
+    mut cursor: ResMut<GamepadCursor>,
+    mut draw_state: ResMut<DrawState>,
+    mut complete: EventWriter<CandidateComplete>,
+) {
+    if is_typing.0 {
+        return;
+    }
+    let size = canvas.0.size();
+    for gamepad in &gamepads {
+        let stick = gamepad.right_stick();
+        let moved = stick != Vec2::ZERO;
+        if moved || cursor.0.is_some() {
+            let position = cursor.0.unwrap_or(size / 2.0);
+            // the board's y goes down, the stick's up
+            let step = Vec2::new(stick.x, -stick.y) * CURSOR_SPEED * time.delta_secs();
+            cursor.0 = Some((position + step).clamp(Vec2::ZERO, size - 1.0));
+        }
+        let Some(position) = cursor.0 else {
+            continue;
+        };
+
+        if gamepad.just_pressed(PEN_BUTTON) {
+            draw_state.0 = DrawMoment::Began(position, draw_state.0 == DrawMoment::Paused, PenSample::default());
+        } else if gamepad.pressed(PEN_BUTTON) && moved {
+            draw_state.0 = DrawMoment::Drawing(position, PenSample::default());
+        } else if gamepad.just_released(PEN_BUTTON) {
+            draw_state.0 = DrawMoment::Paused;
+        }
+        if gamepad.just_released(RECOGNIZE_BUTTON) {
+            complete.send(CandidateComplete);
+        }
+    }
+}
+
+pub fn draw_gamepad_cursor(
+    mut gizmos: Gizmos,
+    cursor: Res<GamepadCursor>,
+    canvas: Res<CanvasRect>,
+    view: Res<BoardView>,
+    window: Single<&Window, With<PrimaryWindow>>,
+    theme: Res<Theme>,
+) {
+    let Some(position) = cursor.0 else {
+        return;
+    };
+    let world = view.to_world(&canvas, window.size(), position);
+    gizmos.circle_2d(Isometry2d::from_translation(world), CURSOR_RADIUS, theme.overlay);
+}
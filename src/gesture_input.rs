@@ -0,0 +1,155 @@
+use std::collections::{HashMap, HashSet};
+
+use bevy::prelude::*;
+
+use crate::{
+    fill_pixel, paint_segment, start_drawing, theme::Theme, ActiveReplay, BoardView, BrushConfig, BrushEnabled,
+    CandidateVectors, CanvasRect, DrawMoment, DrawState, DrawingBoard, IsTyping, MatchOverlay, OverAButton, PenSample,
+    ResultText,
+};
+
+// strokes from anywhere (touches, a VR controller, a network stream...) go
+// through these into the drawing and the recognition. Positions are in board
+// coordinates, pixels from the top-left corner of the canvas, and `stroke` is
+// whatever id tells the sender's strokes apart while they're being drawn.
+// Touches use their touch id
+
+// starts a stroke, the first one after a recognition starts a new drawing
+#[derive(Event)]
+pub struct StrokeBegan {
+    pub stroke: u64,
+    pub position: Vec2,
+    pub pen: PenSample,
+}
+
+#[derive(Event)]
+pub struct StrokePoint {
+    pub stroke: u64,
+    pub position: Vec2,
+    pub pen: PenSample,
+}
+
+#[derive(Event)]
+pub struct StrokeEnded {
+    pub stroke: u64,
+}
+
+// recognizes the drawing so far, like 'Recognize' does
+#[derive(Event)]
+pub struct CandidateComplete;
+
+// turns touches into strokes, every finger draws its own
+pub fn send_touches(
+    touches: Res<Touches>,
+    // the touches that are drawing a stroke
+    mut drawing: Local<HashSet<u64>>,
+    canvas: Res<CanvasRect>,
+    view: Res<BoardView>,
+    is_typing: Res<IsTyping>,
+    over_button: Res<OverAButton>,
+    mut began: EventWriter<StrokeBegan>,
+    mut points: EventWriter<StrokePoint>,
+    mut ended: EventWriter<StrokeEnded>,
+) {
+    for touch in touches.iter_just_released().chain(touches.iter_just_canceled()) {
+        if drawing.remove(&touch.id()) {
+            ended.send(StrokeEnded { stroke: touch.id() });
+        }
+    }
+    if is_typing.0 || over_button.0 {
+        return;
+    }
+
+    for touch in touches.iter_just_pressed() {
+        let Some(position) = view.to_board(&canvas, touch.position()) else {
+            continue;
+        };
+        drawing.insert(touch.id());
+        began.send(StrokeBegan {
+            stroke: touch.id(),
+            position,
+            pen: PenSample::from_force(touch.force()),
+        });
+    }
+
+    for touch in touches.iter().filter(|touch| touch.delta() != Vec2::ZERO) {
+        let Some(position) = view.to_board(&canvas, touch.position()) else {
+            continue;
+        };
+        if drawing.contains(&touch.id()) {
+            points.send(StrokePoint {
+                stroke: touch.id(),
+                position,
+                pen: PenSample::from_force(touch.force()),
+            });
+        }
+    }
+}
+
+// paints the strokes and adds them to the drawing
+pub fn apply_gesture_input(
+    mut began: EventReader<StrokeBegan>,
+    mut points: EventReader<StrokePoint>,
+    mut ended: EventReader<StrokeEnded>,
+    mut complete: EventReader<CandidateComplete>,
+    // stroke id -> index of the stroke in `candidate_vectors`
+    mut strokes: Local<HashMap<u64, usize>>,
+    mut draw_state: ResMut<DrawState>,
+    mut candidate_vectors: ResMut<CandidateVectors>,
+    mut overlay: ResMut<MatchOverlay>,
+    mut replay: ResMut<ActiveReplay>,
+    mut result_text: Single<&mut Text, With<ResultText>>,
+    mut drawingboard: ResMut<DrawingBoard>,
+    mut images: ResMut<Assets<Image>>,
+    canvas: Res<CanvasRect>,
+    time: Res<Time>,
+    brush: Res<BrushConfig>,
+    // together, a system can't take more than 16 parameters
+    (brush_enabled, theme): (Res<BrushEnabled>, Res<Theme>),
+) {
+    // `get_mut` marks the whole board as changed and it's sent to the GPU
+    // again, so only when there's something to paint on it
+    if !began.is_empty() || !points.is_empty() {
+        let board = images.get_mut(&drawingboard.image).expect("Board not found!!");
+
+        for event in began.read() {
+            // the first stroke after recognizing starts a new drawing, the rest
+            // add strokes to it
+            if strokes.is_empty() && draw_state.0 != DrawMoment::Paused {
+                start_drawing(board, &mut drawingboard.painted, canvas.0.size(), &mut candidate_vectors, &mut overlay, &theme);
+            }
+            replay.0 = None;
+            result_text.0 = "".to_string();
+
+            candidate_vectors.begin_stroke(&brush);
+            strokes.insert(event.stroke, candidate_vectors.strokes.len() - 1);
+            if let Some(point) = candidate_vectors.push(event.position, time.elapsed_secs(), event.pen) {
+                fill_pixel(board, &mut drawingboard.painted, point, true, brush_enabled.0, event.pen.pressure, &theme);
+            }
+        }
+
+        for event in points.read() {
+            let Some(&stroke) = strokes.get(&event.stroke) else {
+                continue;
+            };
+            let Some(&previous_pos) = candidate_vectors.strokes.get(stroke).and_then(|points| points.last()) else {
+                continue;
+            };
+            if let Some(point) = candidate_vectors.push_to(stroke, event.position, time.elapsed_secs(), event.pen) {
+                paint_segment(board, &mut drawingboard.painted, previous_pos, point, brush_enabled.0, event.pen.pressure, &theme);
+            }
+        }
+    }
+
+    for event in ended.read() {
+        // between strokes, so the next one doesn't start a new drawing. Not
+        // while the mouse is drawing or a recognition is due
+        if strokes.remove(&event.stroke).is_some() && draw_state.0 == DrawMoment::Idle {
+            draw_state.0 = DrawMoment::Paused;
+        }
+    }
+
+    if complete.read().count() > 0 {
+        draw_state.0 = DrawMoment::Ended;
+    }
+}
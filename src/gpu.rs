@@ -0,0 +1,421 @@
+use bevy::{
+    asset::load_internal_asset,
+    prelude::*,
+    render::{
+        extract_resource::{ExtractResource, ExtractResourcePlugin},
+        gpu_readback::{Readback, ReadbackComplete},
+        render_asset::RenderAssets,
+        render_graph::{self, RenderGraph, RenderLabel},
+        render_resource::{
+            binding_types::{storage_buffer_read_only_sized, storage_buffer_sized},
+            BindGroup, BindGroupEntries, BindGroupLayout, BindGroupLayoutEntries, BufferUsages,
+            CachedComputePipelineId, ComputePassDescriptor, ComputePipelineDescriptor,
+            PipelineCache, ShaderStages,
+        },
+        renderer::{RenderContext, RenderDevice},
+        storage::{GpuShaderStorageBuffer, ShaderStorageBuffer},
+        Render, RenderApp, RenderSet,
+    },
+};
+use web_time::Instant;
+
+use crate::{
+    library::GestureLibrary,
+    recognizer::{active_templates, n_starting_points, penalty, recognize_top, Candidate, Match, Matcher, RecognitionConfig},
+    templates::Template,
+    CandidateVectors,
+};
+
+const GPU_MATCH_SHADER: Handle<Shader> = Handle::weak_from_u128(0x5f2c_9a41_7d3e_4b8a_96c1_2e7f_0b4d_8a63);
+// smaller libraries are faster on the CPU, where the Protractor pre-filter
+// leaves only a handful of templates for the greedy matching
+const GPU_MIN_TEMPLATES: usize = 1000;
+// has to be the same as MAX_POINTS in gpu_match.wgsl, the shader keeps its
+// taken flags in a fixed size array
+const GPU_MAX_POINTS: usize = 96;
+const WORKGROUP_SIZE: u32 = 64;
+// matching falls back to the CPU if the distances take longer than this
+const GPU_TIMEOUT_MS: u128 = 2000;
+
+// matches candidates against every template at once in a compute shader, for
+// libraries too big to go through on the CPU
+pub struct GpuMatchPlugin;
+
+impl Plugin for GpuMatchPlugin {
+    fn build(&self, app: &mut App) {
+        load_internal_asset!(app, GPU_MATCH_SHADER, "gpu_match.wgsl", Shader::from_wgsl);
+
+        app.add_plugins(ExtractResourcePlugin::<GpuMatchBuffers>::default())
+            .init_resource::<GpuMatchBuffers>()
+            .init_resource::<GpuMatcher>()
+            .add_systems(
+                Update,
+                (
+                    upload_templates.run_if(
+                        resource_changed::<GestureLibrary>.or(resource_changed::<RecognitionConfig>),
+                    ),
+                    send_request,
+                )
+                    .chain()
+                    .after(crate::recognize_drawing),
+            );
+    }
+
+    // the pipeline needs the render device, which isn't there yet in `build`
+    fn finish(&self, app: &mut App) {
+        let render_app = app.sub_app_mut(RenderApp);
+        render_app
+            .init_resource::<GpuMatchPipeline>()
+            .add_systems(Render, prepare_bind_group.in_set(RenderSet::PrepareBindGroups));
+        render_app
+            .world_mut()
+            .resource_mut::<RenderGraph>()
+            .add_node(GpuMatchLabel, GpuMatchNode);
+    }
+}
+
+// a drawing waiting for its distances
+pub struct PendingMatch {
+    pub candidate: Candidate,
+    pub drawing: CandidateVectors,
+    pub start_time: Instant,
+}
+
+#[derive(Resource, Default)]
+pub struct GpuMatcher {
+    // (set, gesture name, template) in the order they are in the templates buffer
+    templates: Vec<(String, String, Template)>,
+    n_points: usize,
+    n_starting_points: usize,
+    // id of the last request written to the buffers, the shader copies it
+    // into the results so older results can be told apart
+    last_request: u32,
+    pending: Option<PendingMatch>,
+    // whether `pending` still has to be written to the buffers
+    unsent: bool,
+    // read back for `pending`, one per template
+    distances: Option<Vec<f32>>,
+}
+
+impl GpuMatcher {
+    // the shader only does the point cloud matching
+    pub fn handles(&self, candidate: &Candidate, config: &RecognitionConfig) -> bool {
+        config.custom.is_none()
+            && config.matcher == Matcher::PointCloud
+            && self.templates.len() >= GPU_MIN_TEMPLATES
+            && candidate.points.len() == self.n_points
+    }
+
+    // replaces the pending request if there is one
+    pub fn request(&mut self, pending: PendingMatch) {
+        self.pending = Some(pending);
+        self.unsent = true;
+        self.distances = None;
+    }
+
+    // the pending request and the best matches of its `k` closest classes,
+    // once its distances are back
+    pub fn take_finished(
+        &mut self,
+        library: &GestureLibrary,
+        config: &RecognitionConfig,
+        k: usize,
+    ) -> Option<(PendingMatch, Vec<Match>)> {
+        let pending = self.pending.as_ref()?;
+        let timed_out = pending.start_time.elapsed().as_millis() > GPU_TIMEOUT_MS;
+        // the resolution changed while it was waiting, the candidate can't be
+        // matched against the new templates
+        let outdated = pending.candidate.points.len() != self.n_points;
+        if timed_out || outdated {
+            if timed_out {
+                warn!("no distances from the GPU after {} ms, matching on the CPU", GPU_TIMEOUT_MS);
+            }
+            let pending = self.pending.take()?;
+            let results = recognize_top(library, &pending.candidate, config, k);
+            return Some((pending, results));
+        }
+
+        let distances = self.distances.take()?;
+        let pending = self.pending.take()?;
+        // like on the CPU, templates with about as many strokes are preferred
+        let candidate = &pending.candidate;
+        let any_stroke_count_matches = self
+            .templates
+            .iter()
+            .any(|(set, name, template)| candidate.allows(set, name) && candidate.stroke_count_matches(template));
+        let mut scored: Vec<_> = self
+            .templates
+            .iter()
+            .zip(distances)
+            .filter(|((set, name, _), _)| candidate.allows(set, name))
+            .filter(|((_, _, template), _)| !any_stroke_count_matches || candidate.stroke_count_matches(template))
+            .map(|(entry, distance)| {
+                let penalty = penalty(candidate, &entry.2, config);
+                (entry, distance + penalty)
+            })
+            .collect();
+        scored.sort_by(|a, b| a.1.total_cmp(&b.1));
+
+        // only each class's closest template
+        let mut results: Vec<Match> = Vec::with_capacity(k);
+        for ((set, name, template), distance) in scored {
+            if results.len() == k {
+                break;
+            }
+            if results.iter().any(|result| result.set == *set && result.name == *name) {
+                continue;
+            }
+            results.push(Match {
+                set: set.clone(),
+                name: name.clone(),
+                distance,
+                cloud: template.cloud.clone(),
+                stroke_count: template.stroke_count,
+                // every template is matched, there's no budget on the GPU
+                truncated: false,
+            });
+        }
+        Some((pending, results))
+    }
+}
+
+// what the shader works on, extracted to the render world whenever it changes
+#[derive(Resource, ExtractResource, Clone)]
+struct GpuMatchBuffers {
+    // request id, points per cloud, number of templates, number of starting points
+    params: Handle<ShaderStorageBuffer>,
+    candidate: Handle<ShaderStorageBuffer>,
+    // the template clouds one after the other
+    templates: Handle<ShaderStorageBuffer>,
+    // the request id, then the bits of every template's distance
+    results: Handle<ShaderStorageBuffer>,
+    // how many templates to match, while a request is waiting for its distances
+    dispatch: Option<u32>,
+}
+
+impl FromWorld for GpuMatchBuffers {
+    fn from_world(world: &mut World) -> Self {
+        let mut buffers = world.resource_mut::<Assets<ShaderStorageBuffer>>();
+        let mut results = ShaderStorageBuffer::from(vec![0u32; 1]);
+        // so it can be read back
+        results.buffer_description.usage |= BufferUsages::COPY_SRC;
+        Self {
+            params: buffers.add(ShaderStorageBuffer::from(vec![0u32; 4])),
+            candidate: buffers.add(ShaderStorageBuffer::from(vec![Vec2::ZERO])),
+            templates: buffers.add(ShaderStorageBuffer::from(vec![Vec2::ZERO])),
+            results: buffers.add(results),
+            dispatch: None,
+        }
+    }
+}
+
+fn upload_templates(
+    library: Res<GestureLibrary>,
+    config: Res<RecognitionConfig>,
+    mut matcher: ResMut<GpuMatcher>,
+    gpu_buffers: Res<GpuMatchBuffers>,
+    mut buffers: ResMut<Assets<ShaderStorageBuffer>>,
+) {
+    let n = config.n_points;
+    let templates = active_templates(&library, n);
+    matcher.templates = if templates.len() >= GPU_MIN_TEMPLATES && n <= GPU_MAX_POINTS {
+        templates
+            .into_iter()
+            .map(|(set, name, template)| (set.to_string(), name.clone(), template.clone()))
+            .collect()
+    } else {
+        Vec::new()
+    };
+    matcher.n_points = n;
+    matcher.n_starting_points = n_starting_points(n, &config);
+
+    let mut clouds: Vec<Vec2> = matcher
+        .templates
+        .iter()
+        .flat_map(|(_, _, template)| template.cloud.iter().copied())
+        .collect();
+    // empty buffers can't be bound
+    if clouds.is_empty() {
+        clouds.push(Vec2::ZERO);
+    }
+    if let Some(buffer) = buffers.get_mut(&gpu_buffers.templates) {
+        buffer.set_data(clouds);
+    }
+    if let Some(buffer) = buffers.get_mut(&gpu_buffers.results) {
+        buffer.set_data(vec![0u32; matcher.templates.len() + 1]);
+    }
+
+    // whatever was pending was matched against the old templates
+    if matcher.pending.is_some() {
+        matcher.unsent = true;
+        matcher.distances = None;
+    }
+}
+
+fn send_request(
+    mut commands: Commands,
+    mut matcher: ResMut<GpuMatcher>,
+    mut gpu_buffers: ResMut<GpuMatchBuffers>,
+    mut buffers: ResMut<Assets<ShaderStorageBuffer>>,
+) {
+    if matcher.unsent {
+        matcher.unsent = false;
+        matcher.last_request += 1;
+        if let Some(pending) = matcher.pending.as_ref() {
+            let params = vec![
+                matcher.last_request,
+                matcher.n_points as u32,
+                matcher.templates.len() as u32,
+                matcher.n_starting_points as u32,
+            ];
+            let points = pending.candidate.points.clone();
+            if let Some(buffer) = buffers.get_mut(&gpu_buffers.params) {
+                buffer.set_data(params);
+            }
+            if let Some(buffer) = buffers.get_mut(&gpu_buffers.candidate) {
+                buffer.set_data(points);
+            }
+            // reads the results every frame until it finds the ones for this request
+            commands
+                .spawn(Readback::buffer(gpu_buffers.results.clone()))
+                .observe(receive_distances);
+        }
+    }
+
+    let waiting = matcher.pending.is_some() && matcher.distances.is_none();
+    let dispatch = waiting.then_some(matcher.templates.len() as u32);
+    if gpu_buffers.dispatch != dispatch {
+        gpu_buffers.dispatch = dispatch;
+    }
+}
+
+fn receive_distances(trigger: Trigger<ReadbackComplete>, mut matcher: ResMut<GpuMatcher>, mut commands: Commands) {
+    if matcher.pending.is_none() {
+        commands.entity(trigger.entity()).despawn();
+        return;
+    }
+    let results: Vec<u32> = trigger.event().to_shader_type();
+    // results of an earlier request, or from before the shader ran
+    if matcher.unsent || results.first() != Some(&matcher.last_request) || results.len() <= matcher.templates.len() {
+        return;
+    }
+    let count = matcher.templates.len();
+    matcher.distances = Some(results[1..=count].iter().map(|&bits| f32::from_bits(bits)).collect());
+    commands.entity(trigger.entity()).despawn();
+}
+
+#[derive(Resource)]
+struct GpuMatchPipeline {
+    layout: BindGroupLayout,
+    pipeline: CachedComputePipelineId,
+}
+
+impl FromWorld for GpuMatchPipeline {
+    fn from_world(world: &mut World) -> Self {
+        let layout = world.resource::<RenderDevice>().create_bind_group_layout(
+            "gpu_match_layout",
+            &BindGroupLayoutEntries::sequential(
+                ShaderStages::COMPUTE,
+                (
+                    storage_buffer_read_only_sized(false, None),
+                    storage_buffer_read_only_sized(false, None),
+                    storage_buffer_read_only_sized(false, None),
+                    storage_buffer_sized(false, None),
+                ),
+            ),
+        );
+        let pipeline = world
+            .resource::<PipelineCache>()
+            .queue_compute_pipeline(ComputePipelineDescriptor {
+                label: Some("gpu_match_pipeline".into()),
+                layout: vec![layout.clone()],
+                push_constant_ranges: Vec::new(),
+                shader: GPU_MATCH_SHADER,
+                shader_defs: Vec::new(),
+                entry_point: "main".into(),
+                zero_initialize_workgroup_memory: false,
+            });
+        Self { layout, pipeline }
+    }
+}
+
+// only there on frames the shader has to run
+#[derive(Resource)]
+struct GpuMatchBindGroup {
+    bind_group: BindGroup,
+    template_count: u32,
+}
+
+fn prepare_bind_group(
+    mut commands: Commands,
+    pipeline: Res<GpuMatchPipeline>,
+    render_device: Res<RenderDevice>,
+    gpu_buffers: Option<Res<GpuMatchBuffers>>,
+    buffers: Res<RenderAssets<GpuShaderStorageBuffer>>,
+) {
+    commands.remove_resource::<GpuMatchBindGroup>();
+    let Some(gpu_buffers) = gpu_buffers else {
+        return;
+    };
+    let Some(template_count) = gpu_buffers.dispatch else {
+        return;
+    };
+    let (Some(params), Some(candidate), Some(templates), Some(results)) = (
+        buffers.get(&gpu_buffers.params),
+        buffers.get(&gpu_buffers.candidate),
+        buffers.get(&gpu_buffers.templates),
+        buffers.get(&gpu_buffers.results),
+    ) else {
+        return;
+    };
+
+    // buffers are recreated whenever their data changes, so this is done every time
+    let bind_group = render_device.create_bind_group(
+        "gpu_match_bind_group",
+        &pipeline.layout,
+        &BindGroupEntries::sequential((
+            params.buffer.as_entire_buffer_binding(),
+            candidate.buffer.as_entire_buffer_binding(),
+            templates.buffer.as_entire_buffer_binding(),
+            results.buffer.as_entire_buffer_binding(),
+        )),
+    );
+    commands.insert_resource(GpuMatchBindGroup {
+        bind_group,
+        template_count,
+    });
+}
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, RenderLabel)]
+struct GpuMatchLabel;
+
+struct GpuMatchNode;
+
+impl render_graph::Node for GpuMatchNode {
+    fn run(
+        &self,
+        _graph: &mut render_graph::RenderGraphContext,
+        render_context: &mut RenderContext,
+        world: &World,
+    ) -> Result<(), render_graph::NodeRunError> {
+        let Some(bind_group) = world.get_resource::<GpuMatchBindGroup>() else {
+            return Ok(());
+        };
+        let pipeline_id = world.resource::<GpuMatchPipeline>().pipeline;
+        // still compiling
+        let Some(pipeline) = world.resource::<PipelineCache>().get_compute_pipeline(pipeline_id) else {
+            return Ok(());
+        };
+
+        let mut pass = render_context
+            .command_encoder()
+            .begin_compute_pass(&ComputePassDescriptor {
+                label: Some("gpu_match_pass"),
+                ..default()
+            });
+        pass.set_bind_group(0, &bind_group.bind_group, &[]);
+        pass.set_pipeline(pipeline);
+        pass.dispatch_workgroups(bind_group.template_count.div_ceil(WORKGROUP_SIZE), 1, 1);
+        Ok(())
+    }
+}
@@ -0,0 +1,75 @@
+// one invocation per template, the same greedy matching as `greedy_5` in
+// recognizer.rs without the grid: both directions from every starting point,
+// keeping the smallest weighted sum
+
+// has to be the same as GPU_MAX_POINTS in gpu.rs
+const MAX_POINTS: u32 = 96u;
+const WORKGROUP_SIZE: u32 = 64u;
+
+// request id, points per cloud, number of templates, number of starting points
+@group(0) @binding(0) var<storage, read> params: array<u32, 4>;
+@group(0) @binding(1) var<storage, read> candidate: array<vec2<f32>>;
+// the template clouds one after the other
+@group(0) @binding(2) var<storage, read> templates: array<vec2<f32>>;
+// the request id, then the bits of every template's distance
+@group(0) @binding(3) var<storage, read_write> results: array<u32>;
+
+fn cloud_point(of_candidate: bool, offset: u32, i: u32) -> vec2<f32> {
+    if of_candidate {
+        return candidate[i];
+    }
+    return templates[offset + i];
+}
+
+// matches the candidate with the template at `offset` (or the other way around),
+// giving up once the sum goes over `bound`
+fn greedy_match(candidate_to_template: bool, offset: u32, n: u32, start: u32, bound: f32) -> f32 {
+    var taken: array<bool, MAX_POINTS>;
+    for (var i = 0u; i < n; i++) {
+        taken[i] = false;
+    }
+
+    var total = 0.0;
+    for (var step = 0u; step < n; step++) {
+        let query = cloud_point(candidate_to_template, offset, (start + step) % n);
+        var nearest_dist = 3.4e38;
+        var nearest_index = 0u;
+        for (var j = 0u; j < n; j++) {
+            if taken[j] {
+                continue;
+            }
+            let d = query - cloud_point(!candidate_to_template, offset, j);
+            if dot(d, d) < nearest_dist {
+                nearest_dist = dot(d, d);
+                nearest_index = j;
+            }
+        }
+        taken[nearest_index] = true;
+        // earlier points weigh more
+        total += (1.0 - f32(step) / f32(n)) * nearest_dist;
+        if total > bound {
+            break;
+        }
+    }
+    return total;
+}
+
+@compute @workgroup_size(WORKGROUP_SIZE)
+fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+    // tells the reader which request these distances are for
+    if id.x == 0u {
+        results[0] = params[0];
+    }
+    let n = params[1];
+    if id.x >= params[2] || n > MAX_POINTS {
+        return;
+    }
+
+    let offset = id.x * n;
+    var least_distance = 3.4e38;
+    for (var start = 0u; start < params[3]; start++) {
+        least_distance = min(least_distance, greedy_match(true, offset, n, start, least_distance));
+        least_distance = min(least_distance, greedy_match(false, offset, n, start, least_distance));
+    }
+    results[id.x + 1u] = bitcast<u32>(least_distance);
+}
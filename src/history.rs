@@ -0,0 +1,116 @@
+use std::collections::VecDeque;
+
+use bevy::{
+    asset::RenderAssetUsages,
+    prelude::*,
+    render::render_resource::{Extent3d, TextureDimension, TextureFormat},
+};
+
+use crate::{theme::Theme, CandidateVectors};
+
+// older recognitions are dropped
+pub const HISTORY_LENGTH: usize = 10;
+pub const THUMBNAIL_SIZE: u32 = 40;
+// the latency statistics are over this many of the last recognitions
+const LATENCY_WINDOW: usize = 100;
+
+pub struct HistoryEntry {
+    // stays the same while the entry moves down the list
+    pub id: u64,
+    pub drawing: CandidateVectors,
+    // "not recognized" if nothing matched
+    pub name: String,
+    // None if nothing matched
+    pub confidence: Option<f32>,
+    pub latency_ms: f64,
+    pub thumbnail: Handle<Image>,
+}
+
+// in milliseconds
+pub struct LatencyStats {
+    pub count: usize,
+    pub min: f64,
+    pub mean: f64,
+    pub p95: f64,
+}
+
+// the last recognitions, newest first
+#[derive(Resource, Default)]
+pub struct RecognitionHistory {
+    entries: VecDeque<HistoryEntry>,
+    next_id: u64,
+    // of more recognitions than there are entries
+    latencies: VecDeque<f64>,
+}
+
+impl RecognitionHistory {
+    pub fn push(
+        &mut self,
+        drawing: CandidateVectors,
+        name: String,
+        confidence: Option<f32>,
+        latency_ms: f64,
+        thumbnail: Handle<Image>,
+    ) {
+        self.entries.push_front(HistoryEntry {
+            id: self.next_id,
+            drawing,
+            name,
+            confidence,
+            latency_ms,
+            thumbnail,
+        });
+        self.next_id += 1;
+        self.entries.truncate(HISTORY_LENGTH);
+        self.latencies.push_front(latency_ms);
+        self.latencies.truncate(LATENCY_WINDOW);
+    }
+
+    // None before the first recognition
+    pub fn latency_stats(&self) -> Option<LatencyStats> {
+        let mut sorted: Vec<f64> = self.latencies.iter().copied().collect();
+        sorted.sort_by(f64::total_cmp);
+        let min = *sorted.first()?;
+        Some(LatencyStats {
+            count: sorted.len(),
+            min,
+            mean: sorted.iter().sum::<f64>() / sorted.len() as f64,
+            p95: sorted[((sorted.len() - 1) as f64 * 0.95).round() as usize],
+        })
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = &HistoryEntry> {
+        self.entries.iter()
+    }
+
+    pub fn get(&self, id: u64) -> Option<&HistoryEntry> {
+        self.entries.iter().find(|entry| entry.id == id)
+    }
+}
+
+// a small picture of a normalized cloud (centered on the origin, about one unit wide)
+pub fn thumbnail(points: &[Vec2], theme: &Theme) -> Image {
+    let mut image = Image::new_fill(
+        Extent3d {
+            width: THUMBNAIL_SIZE,
+            height: THUMBNAIL_SIZE,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        &(theme.board.to_srgba().to_u8_array()),
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::MAIN_WORLD | RenderAssetUsages::RENDER_WORLD,
+    );
+
+    // leave a couple of pixels of margin around the edges
+    let size = (THUMBNAIL_SIZE - 4) as f32;
+    for point in points {
+        let pixel = (*point + 0.5) * size + 2.0;
+        if pixel.x >= 0.0 && pixel.y >= 0.0 {
+            image
+                .set_color_at(pixel.x as u32, pixel.y as u32, theme.brush)
+                .unwrap_or(());
+        }
+    }
+    image
+}
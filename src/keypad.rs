@@ -0,0 +1,201 @@
+use std::{collections::HashSet, f32::consts::PI};
+
+use bevy::prelude::*;
+
+use crate::{
+    accessibility::announced, augmentation::Augmentation, library::GestureSet, recognizer::Candidate,
+    templates::Template, theme::Theme, ui_strings::UiStrings, GestureRecognized, UI_PANEL_WIDTH,
+};
+
+// the gesture set the demo adds and matches against
+pub const SET: &str = "keypad";
+// drawings it isn't at least this sure of type nothing, unless there's a
+// `--min-confidence`. Scribbles and other shapes come out at about 0, most
+// digits written a little crooked still make it
+pub const MIN_CONFIDENCE: f32 = 0.05;
+// every glyph is one hand-made template and this many distorted copies of it,
+// so the confidences have a spread of templates to go by
+const VARIANTS: usize = 6;
+const SEED: u64 = 10;
+// the source of the demo's templates, they aren't saved with the user's
+const SOURCE: &str = "keypad";
+
+// what recognizing each of the gestures does to the text
+#[derive(Clone, Copy)]
+enum Key {
+    Type(char),
+    Backspace,
+}
+
+const KEYS: [(&str, Key); 16] = [
+    ("0", Key::Type('0')),
+    ("1", Key::Type('1')),
+    ("2", Key::Type('2')),
+    ("3", Key::Type('3')),
+    ("4", Key::Type('4')),
+    ("5", Key::Type('5')),
+    ("6", Key::Type('6')),
+    ("7", Key::Type('7')),
+    ("8", Key::Type('8')),
+    ("9", Key::Type('9')),
+    ("plus", Key::Type('+')),
+    ("minus", Key::Type('-')),
+    ("times", Key::Type('*')),
+    ("divide", Key::Type('/')),
+    ("equals", Key::Type('=')),
+    // a '<' pointing back at the text
+    ("backspace", Key::Backspace),
+];
+
+// points along an ellipse, angles in degrees with y going down like on the
+// board (90 is the bottom), from `from` to `to` either way round
+fn arc(center: Vec2, radius: Vec2, from: f32, to: f32) -> Vec<Vec2> {
+    let steps = ((to - from).abs() / 10.0).ceil().max(1.0) as usize;
+    (0..=steps)
+        .map(|i| {
+            let angle = (from + (to - from) * i as f32 / steps as f32) * PI / 180.0;
+            center + radius * Vec2::new(angle.cos(), angle.sin())
+        })
+        .collect()
+}
+
+fn line(points: &[(f32, f32)]) -> Vec<Vec2> {
+    points.iter().map(|&(x, y)| Vec2::new(x, y)).collect()
+}
+
+// the strokes of every glyph the way they're usually written, in a box about
+// 0.6 wide and 1 high
+fn glyph(name: &str) -> Vec<Vec<Vec2>> {
+    match name {
+        "0" => vec![arc(Vec2::new(0.3, 0.5), Vec2::new(0.3, 0.5), -90.0, -450.0)],
+        "1" => vec![line(&[(0.15, 0.2), (0.35, 0.0), (0.35, 1.0)])],
+        "2" => vec![[
+            arc(Vec2::new(0.3, 0.28), Vec2::new(0.28, 0.28), 200.0, 390.0),
+            line(&[(0.0, 1.0), (0.6, 1.0)]),
+        ]
+        .concat()],
+        "3" => vec![[
+            arc(Vec2::new(0.3, 0.25), Vec2::new(0.27, 0.25), 200.0, 450.0),
+            arc(Vec2::new(0.3, 0.75), Vec2::new(0.3, 0.25), 270.0, 520.0),
+        ]
+        .concat()],
+        "4" => vec![line(&[(0.45, 0.0), (0.0, 0.65), (0.6, 0.65)]), line(&[(0.45, 0.3), (0.45, 1.0)])],
+        "5" => vec![[
+            line(&[(0.55, 0.0), (0.1, 0.0), (0.08, 0.45)]),
+            arc(Vec2::new(0.3, 0.7), Vec2::new(0.28, 0.3), 225.0, 510.0),
+        ]
+        .concat()],
+        "6" => vec![[
+            line(&[(0.5, 0.0), (0.25, 0.15)]),
+            arc(Vec2::new(0.3, 0.72), Vec2::new(0.24, 0.26), 180.0, -180.0),
+        ]
+        .concat()],
+        "7" => vec![line(&[(0.0, 0.0), (0.6, 0.0), (0.2, 1.0)])],
+        "8" => vec![(0..=36)
+            .map(|i| {
+                let t = i as f32 * 10.0 * PI / 180.0;
+                Vec2::new(0.3 + 0.28 * (2.0 * t).sin(), 0.5 - 0.5 * t.cos())
+            })
+            .collect()],
+        "9" => vec![[
+            arc(Vec2::new(0.3, 0.27), Vec2::new(0.27, 0.27), 0.0, -360.0),
+            line(&[(0.55, 1.0)]),
+        ]
+        .concat()],
+        "plus" => vec![line(&[(0.3, 0.0), (0.3, 0.6)]), line(&[(0.0, 0.3), (0.6, 0.3)])],
+        "minus" => vec![line(&[(0.0, 0.0), (0.6, 0.0)])],
+        "times" => vec![line(&[(0.0, 0.0), (0.6, 0.6)]), line(&[(0.6, 0.0), (0.0, 0.6)])],
+        "divide" => vec![line(&[(0.5, 0.0), (0.1, 1.0)])],
+        "equals" => vec![line(&[(0.0, 0.0), (0.6, 0.0)]), line(&[(0.0, 0.3), (0.6, 0.3)])],
+        "backspace" => vec![line(&[(0.5, 0.0), (0.0, 0.3), (0.5, 0.6)])],
+        _ => unreachable!("every key has a glyph"),
+    }
+}
+
+// the digits and symbols of the demo, along with their augmented copies
+pub fn templates(n: usize, preserve_corners: bool) -> GestureSet {
+    let augmentation = Augmentation {
+        variants: VARIANTS,
+        seed: SEED,
+    };
+    KEYS.iter()
+        .map(|(name, _)| {
+            let strokes = glyph(name);
+            let template = Template::new(Candidate::new(&strokes, n, preserve_corners).points, SOURCE, strokes.len());
+            let mut templates = HashSet::from([template.clone()]);
+            templates.extend(augmentation.variants(name, &template, n, preserve_corners).into_iter().map(
+                |mut variant| {
+                    variant.source = SOURCE.to_string();
+                    variant
+                },
+            ));
+            (name.to_string(), templates)
+        })
+        .collect()
+}
+
+// `--keypad` turns the app into a handwriting keypad: the digits and
+// + - * / = written on the canvas are typed into a line of text over it, and
+// a '<' deletes the last character
+#[derive(Resource, Default)]
+pub struct KeypadText(String);
+
+#[derive(Component)]
+pub struct KeypadLine;
+
+pub fn spawn_keypad_line(mut commands: Commands, strings: Res<UiStrings>, theme: Res<Theme>) {
+    commands.spawn((
+        Text::new(describe("", &strings)),
+        TextFont {
+            font_size: 40.0,
+            ..default()
+        },
+        // written on the board, like the drawings
+        TextColor(theme.brush),
+        // across the top of the canvas
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(10.0),
+            left: Val::Px(UI_PANEL_WIDTH),
+            right: Val::Px(UI_PANEL_WIDTH),
+            justify_content: JustifyContent::Center,
+            ..default()
+        },
+        KeypadLine,
+        announced(),
+    ));
+}
+
+fn describe(text: &str, strings: &UiStrings) -> String {
+    if text.is_empty() {
+        strings.keypad_hint.clone()
+    } else {
+        format!("{}_", text)
+    }
+}
+
+// types what's recognized on the main canvas, drawings that aren't confident
+// enough never get here
+pub fn type_recognized(
+    mut recognized: EventReader<GestureRecognized>,
+    mut text: ResMut<KeypadText>,
+    mut line: Single<&mut Text, With<KeypadLine>>,
+    strings: Res<UiStrings>,
+) {
+    let mut changed = false;
+    for event in recognized.read().filter(|event| event.canvas == 0 && event.set == SET) {
+        let Some((_, key)) = KEYS.iter().find(|(name, _)| *name == event.name) else {
+            continue;
+        };
+        match key {
+            Key::Type(c) => text.0.push(*c),
+            Key::Backspace => {
+                text.0.pop();
+            }
+        }
+        changed = true;
+    }
+    if changed {
+        line.0 = describe(&text.0, &strings);
+    }
+}
@@ -0,0 +1,71 @@
+use bevy::{math::Vec2, prelude::Resource};
+use clap::ValueEnum;
+use serde::Deserialize;
+
+use crate::{
+    library::GestureLibrary,
+    recognizer::Match,
+    ui_strings::{fill, UiStrings},
+};
+
+// added to the distance per point and per miss in `LearningMode::Weight`, about
+// as much again as a drawing of the same gesture is typically off
+pub const MISS_PENALTY: f32 = 0.002;
+// `LearningMode::Prune` removes a template after this many misses
+const PRUNE_AFTER_MISSES: u32 = 3;
+
+// what 'Add'ing a misrecognized drawing under the right name does to the
+// template it was wrongly matched with. Its misses are counted either way
+#[derive(Clone, Copy, PartialEq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LearningMode {
+    Off,
+    // every miss puts the template a bit further away, see `recognizer::penalty`
+    Weight,
+    // a template that keeps causing wrong matches is removed, unless it's its
+    // gesture's last one
+    Prune,
+}
+
+pub struct MatchedTemplate {
+    pub set: String,
+    pub name: String,
+    pub cloud: Vec<Vec2>,
+}
+
+// the template the last drawing was recognized as, None if it wasn't or it
+// has been dealt with
+#[derive(Resource, Default)]
+pub struct LastMatch(pub Option<MatchedTemplate>);
+
+impl LastMatch {
+    pub fn remember(&mut self, result: Option<&Match>) {
+        self.0 = result.map(|result| MatchedTemplate {
+            set: result.set.clone(),
+            name: result.name.clone(),
+            cloud: result.cloud.clone(),
+        });
+    }
+}
+
+// the last drawing was added as `added_as`. If it had been recognized as
+// something else that's a miss for the template it matched, returns what
+// became of that template
+pub fn learn_from_correction(
+    library: &mut GestureLibrary,
+    last: &mut LastMatch,
+    added_as: &str,
+    mode: LearningMode,
+    strings: &UiStrings,
+) -> Option<String> {
+    let matched = last.0.take().filter(|matched| matched.name != added_as)?;
+    let misses = library.record_miss(&matched.set, &matched.name, &matched.cloud)?;
+    if mode == LearningMode::Prune
+        && misses >= PRUNE_AFTER_MISSES
+        && library.remove_template(&matched.set, &matched.name, &matched.cloud)
+    {
+        return Some(fill(&strings.removed_template, &[("name", &matched.name)]));
+    }
+    let missed = if misses == 1 { &strings.template_missed_once } else { &strings.template_missed };
+    Some(fill(missed, &[("name", &matched.name), ("misses", &misses)]))
+}
@@ -0,0 +1,343 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use bevy::{math::Vec2, prelude::Resource};
+
+use crate::{
+    compaction::{self, CompactionConfig},
+    recognizer::{RecognitionConfig, N_RESAMPLED_POINTS},
+    storage::SavedTemplates,
+    templates::Template,
+};
+
+// templates that were drawn in the app (or merged from ones that were), the
+// rest come back on their own every run
+const USER_SOURCES: [&str; 4] = ["user", "training", "merged", "augmented"];
+
+pub type GestureSet = HashMap<String, HashSet<Template>>;
+
+pub fn is_user_source(source: &str) -> bool {
+    USER_SOURCES.contains(&source)
+}
+
+// named gesture sets ("letters", "shapes", ...), recognition only looks at the
+// active ones
+#[derive(Resource)]
+pub struct GestureLibrary {
+    sets: BTreeMap<String, GestureSet>,
+    // never empty, new gestures are added to the first one
+    active: Vec<String>,
+    // number of points every template's cloud is resampled to
+    resolution: usize,
+    // see `RecognitionConfig::preserve_corners`
+    preserve_corners: bool,
+    // the templates that aren't the user's and were removed for their misses,
+    // by set and gesture
+    removed: SavedTemplates,
+}
+
+fn with_resolution(set: GestureSet, n: usize, preserve_corners: bool) -> GestureSet {
+    set.into_iter()
+        .map(|(name, templates)| {
+            let templates = templates
+                .into_iter()
+                .map(|template| template.with_resolution(n, preserve_corners))
+                .collect();
+            (name, templates)
+        })
+        .collect()
+}
+
+impl GestureLibrary {
+    pub fn new(name: impl Into<String>, set: GestureSet) -> Self {
+        let name = name.into();
+        Self {
+            sets: BTreeMap::from([(name.clone(), with_resolution(set, N_RESAMPLED_POINTS, false))]),
+            active: vec![name],
+            resolution: N_RESAMPLED_POINTS,
+            preserve_corners: false,
+            removed: SavedTemplates::new(),
+        }
+    }
+
+    // replaces the set if one with the same name already exists
+    pub fn insert_set(&mut self, name: impl Into<String>, set: GestureSet) {
+        self.sets.insert(name.into(), with_resolution(set, self.resolution, self.preserve_corners));
+    }
+
+    pub fn set_resampling(&mut self, n: usize, preserve_corners: bool) {
+        if n == self.resolution && preserve_corners == self.preserve_corners {
+            return;
+        }
+        self.resolution = n;
+        self.preserve_corners = preserve_corners;
+        self.sets = std::mem::take(&mut self.sets)
+            .into_iter()
+            .map(|(name, set)| (name, with_resolution(set, n, preserve_corners)))
+            .collect();
+    }
+
+    pub fn active(&self) -> &[String] {
+        &self.active
+    }
+
+    pub fn active_sets(&self) -> impl Iterator<Item = (&str, &GestureSet)> {
+        self.active
+            .iter()
+            .filter_map(|name| self.sets.get_key_value(name))
+            .map(|(name, set)| (name.as_str(), set))
+    }
+
+    // returns false (and changes nothing) if any of the sets doesn't exist
+    pub fn set_active<S: AsRef<str>>(&mut self, names: &[S]) -> bool {
+        if names.is_empty() || !names.iter().all(|name| self.sets.contains_key(name.as_ref())) {
+            return false;
+        }
+        self.active = names.iter().map(|name| name.as_ref().to_string()).collect();
+        true
+    }
+
+    // one set at a time in name order, then all of them together, then back to the first
+    pub fn cycle_active(&mut self) {
+        let names: Vec<String> = self.sets.keys().cloned().collect();
+        self.active = if self.active.len() > 1 {
+            vec![names[0].clone()]
+        } else {
+            let current = names.iter().position(|name| *name == self.active[0]).unwrap_or(0);
+            match names.get(current + 1) {
+                Some(next) => vec![next.clone()],
+                None if names.len() > 1 => names,
+                None => vec![names[0].clone()],
+            }
+        };
+    }
+
+    // the gesture with the fewest samples in the set new gestures are added to
+    pub fn least_trained(&self) -> Option<&str> {
+        self.sets
+            .get(&self.active[0])?
+            .iter()
+            .min_by(|(a_name, a), (b_name, b)| a.len().cmp(&b.len()).then(a_name.cmp(b_name)))
+            .map(|(name, _)| name.as_str())
+    }
+
+    // merges the near-identical templates of every gesture in the active sets
+    // and trims the ones with too many, returns how many templates are gone
+    pub fn compact(&mut self, compaction: &CompactionConfig, average: bool, config: &RecognitionConfig) -> usize {
+        let mut removed = 0;
+        for set_name in self.active.clone() {
+            for templates in self.sets.get_mut(&set_name).into_iter().flat_map(|set| set.values_mut()) {
+                let before = templates.len();
+                *templates = compaction::compact(templates, compaction.threshold, compaction.max_templates, average, config)
+                    .into_iter()
+                    .map(|template| template.with_resolution(self.resolution, self.preserve_corners))
+                    .collect();
+                removed += before - templates.len();
+            }
+        }
+        removed
+    }
+
+    // only merges as much as it takes to bring a gesture of the set new
+    // gestures are added to back down to `max_templates`
+    pub fn limit_templates(&mut self, gesture_name: &str, compaction: &CompactionConfig, config: &RecognitionConfig) {
+        let Some(templates) = self.sets.get_mut(&self.active[0]).and_then(|set| set.get_mut(gesture_name)) else {
+            return;
+        };
+        if templates.len() > compaction.max_templates {
+            *templates = compaction::compact(templates, 0.0, compaction.max_templates, false, config);
+        }
+    }
+
+    // how many templates a gesture has in the set new gestures are added to
+    pub fn template_count(&self, gesture_name: &str) -> usize {
+        self.sets
+            .get(&self.active[0])
+            .and_then(|set| set.get(gesture_name))
+            .map_or(0, |templates| templates.len())
+    }
+
+    pub fn add_template(&mut self, gesture_name: &str, template: Template) -> &str {
+        let set_name = &self.active[0];
+        self.sets
+            .entry(set_name.clone())
+            .or_default()
+            .entry(gesture_name.to_string())
+            .or_default()
+            .insert(template.with_resolution(self.resolution, self.preserve_corners));
+        set_name
+    }
+
+    // counts a miss for the template of the gesture with that cloud, returns
+    // its misses so far. None if it's gone by now
+    pub fn record_miss(&mut self, set_name: &str, gesture_name: &str, cloud: &[Vec2]) -> Option<u32> {
+        let templates = self.sets.get_mut(set_name)?.get_mut(gesture_name)?;
+        let found = templates.iter().find(|template| template.cloud == cloud)?.clone();
+        // templates are hashed by their points, so it has to be taken out to change
+        let mut template = templates.take(&found)?;
+        template.misses += 1;
+        let misses = template.misses;
+        templates.insert(template);
+        Some(misses)
+    }
+
+    // false if it's its gesture's last template (or gone already), those are kept
+    pub fn remove_template(&mut self, set_name: &str, gesture_name: &str, cloud: &[Vec2]) -> bool {
+        let Some(templates) = self.sets.get_mut(set_name).and_then(|set| set.get_mut(gesture_name)) else {
+            return false;
+        };
+        if templates.len() < 2 {
+            return false;
+        }
+        let Some(found) = templates.iter().find(|template| template.cloud == cloud).cloned() else {
+            return false;
+        };
+        templates.remove(&found);
+        // the others come back on their own next time, unless it's saved that they're gone
+        if !is_user_source(&found.source) {
+            let mut removed = found;
+            removed.removed = true;
+            self.removed
+                .entry(set_name.to_string())
+                .or_default()
+                .entry(gesture_name.to_string())
+                .or_default()
+                .push(removed);
+        }
+        true
+    }
+
+    // the templates from `USER_SOURCES` in every set, and what learning did
+    // to the others: the ones with misses and the ones that were removed
+    pub fn user_templates(&self) -> SavedTemplates {
+        let mut saved = SavedTemplates::new();
+        for (set_name, set) in &self.sets {
+            for (name, templates) in set {
+                let templates = templates
+                    .iter()
+                    .filter(|template| is_user_source(&template.source) || template.misses > 0)
+                    .cloned();
+                saved.entry(set_name.clone()).or_default().entry(name.clone()).or_default().extend(templates);
+            }
+        }
+        for (set_name, gestures) in &self.removed {
+            for (name, templates) in gestures {
+                saved.entry(set_name.clone()).or_default().entry(name.clone()).or_default().extend(templates.iter().cloned());
+            }
+        }
+        for gestures in saved.values_mut() {
+            for templates in gestures.values_mut() {
+                templates.sort_by_key(|template| template.created);
+            }
+            gestures.retain(|_, templates| !templates.is_empty());
+        }
+        saved.retain(|_, gestures| !gestures.is_empty());
+        saved
+    }
+
+    // adds saved templates to their sets, making the sets that don't exist.
+    // The saved templates that aren't the user's only update (or remove) the
+    // ones that are there, they don't come back if those are gone
+    pub fn add_saved(&mut self, saved: SavedTemplates) {
+        for (set_name, gestures) in saved {
+            for (name, templates) in gestures {
+                for template in templates {
+                    let template = template.with_resolution(self.resolution, self.preserve_corners);
+                    if is_user_source(&template.source) {
+                        self.sets.entry(set_name.clone()).or_default().entry(name.clone()).or_default().insert(template);
+                        continue;
+                    }
+                    let Some(existing) = self.sets.get_mut(&set_name).and_then(|set| set.get_mut(&name)) else {
+                        continue;
+                    };
+                    if !existing.contains(&template) {
+                        continue;
+                    }
+                    if template.removed {
+                        existing.remove(&template);
+                        self.removed
+                            .entry(set_name.clone())
+                            .or_default()
+                            .entry(name.clone())
+                            .or_default()
+                            .push(template);
+                    } else {
+                        // equal by their points, this brings the misses along
+                        existing.replace(template);
+                    }
+                }
+            }
+        }
+    }
+
+    // swaps the templates of a set that `replaced` picks for the ones in
+    // `set`, gestures left without templates are gone
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn replace_templates(&mut self, set_name: &str, replaced: impl Fn(&Template) -> bool, set: GestureSet) {
+        let gestures = self.sets.entry(set_name.to_string()).or_default();
+        for templates in gestures.values_mut() {
+            templates.retain(|template| !replaced(template));
+        }
+        for (name, templates) in with_resolution(set, self.resolution, self.preserve_corners) {
+            gestures.entry(name).or_default().extend(templates);
+        }
+        gestures.retain(|_, templates| !templates.is_empty());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // lines at different slopes, they're still different clouds when normalized
+    fn line(slope: f32, source: &str) -> Template {
+        Template::new((0..8).map(|i| i as f32 / 7.0 - 0.5).map(|x| Vec2::new(x, x * slope)).collect(), source, 1)
+    }
+
+    fn library() -> GestureLibrary {
+        let set = GestureSet::from([(
+            "line".to_string(),
+            HashSet::from([line(0.0, "default"), line(0.5, "default"), line(1.0, "user")]),
+        )]);
+        GestureLibrary::new("default", set)
+    }
+
+    // the library after saving it and loading it again into a fresh one
+    fn round_trip(library: &GestureLibrary) -> GestureLibrary {
+        let text = toml::to_string(&library.user_templates()).unwrap();
+        let mut loaded = self::library();
+        loaded.set_resampling(library.resolution, library.preserve_corners);
+        loaded.add_saved(toml::from_str(&text).unwrap());
+        loaded
+    }
+
+    fn cloud(library: &GestureLibrary, slope: f32) -> Vec<Vec2> {
+        line(slope, "default").with_resolution(library.resolution, library.preserve_corners).cloud
+    }
+
+    #[test]
+    fn misses_survive_a_save_and_load() {
+        let mut library = library();
+        for slope in [0.0, 1.0] {
+            let cloud = cloud(&library, slope);
+            assert_eq!(library.record_miss("default", "line", &cloud), Some(1));
+        }
+        let mut loaded = round_trip(&library);
+        assert_eq!(loaded.template_count("line"), 3);
+        for slope in [0.0, 1.0] {
+            let cloud = cloud(&loaded, slope);
+            assert_eq!(loaded.record_miss("default", "line", &cloud), Some(2));
+        }
+    }
+
+    #[test]
+    fn removed_built_in_templates_stay_removed() {
+        let mut library = library();
+        let cloud = cloud(&library, 0.5);
+        assert!(library.remove_template("default", "line", &cloud));
+        let mut loaded = round_trip(&library);
+        assert_eq!(loaded.template_count("line"), 2);
+        assert_eq!(loaded.record_miss("default", "line", &cloud), None);
+        // and saving it again keeps the note that it's gone
+        assert_eq!(round_trip(&loaded).template_count("line"), 2);
+    }
+}
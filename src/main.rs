@@ -1,26 +1,206 @@
+#![allow(clippy::too_many_arguments, clippy::type_complexity)]
+
+mod accessibility;
+mod augmentation;
+mod bench;
+mod bindings;
+mod calibration;
+mod canvases;
+mod compaction;
+mod compare;
+mod config_file;
+mod corners;
+mod dataset;
+mod diagnostics;
+mod dtw;
+#[cfg(not(target_arch = "wasm32"))]
+mod export;
+mod gamepad;
+mod gesture_input;
+#[cfg(feature = "gpu")]
+mod gpu;
+mod history;
+mod keypad;
+mod learning;
+mod library;
+#[cfg(not(target_arch = "wasm32"))]
+mod network;
+mod options;
+mod point_grid;
+mod recognizer;
+mod replay;
+mod saved_session;
+mod script;
+mod session_log;
+mod smoothing;
+mod storage;
+mod svg_templates;
+#[cfg(not(target_arch = "wasm32"))]
+mod template_watch;
 mod templates;
-
-use core::f32;
-use std::collections::{HashMap, HashSet};
+mod theme;
+mod training;
+mod ui_strings;
+mod zones;
 
 use bevy::{
+    app::ScheduleRunnerPlugin,
     asset::RenderAssetUsages,
     dev_tools::fps_overlay::{FpsOverlayConfig, FpsOverlayPlugin},
-    input::mouse::AccumulatedMouseMotion,
+    ecs::system::SystemParam,
+    input::{
+        mouse::{AccumulatedMouseMotion, MouseScrollUnit, MouseWheel},
+        touch::ForceTouch,
+        InputSystem,
+    },
+    log::{LogPlugin, DEFAULT_FILTER},
     prelude::*,
-    render::render_resource::{Extent3d, TextureDimension, TextureFormat},
+    render::{
+        render_resource::{Extent3d, TextureDimension, TextureFormat},
+        settings::WgpuSettings,
+        RenderPlugin,
+    },
+    time::TimeUpdateStrategy,
+    window::{PrimaryWindow, WindowResolution},
+    winit::WinitPlugin,
 };
 use bevy_simple_text_input::{TextInput, TextInputPlugin, TextInputSubmitEvent, TextInputTextFont};
-use chrono::Utc;
-use templates::Template;
+use web_time::Instant;
+use accessibility::{announced, AccessibleName};
+use augmentation::Augmentation;
+use bindings::{Action, InputBindings};
+use calibration::Calibration;
+use compaction::CompactionConfig;
+use compare::CompareView;
+use gesture_input::{CandidateComplete, StrokeBegan, StrokeEnded, StrokePoint};
+use history::{RecognitionHistory, THUMBNAIL_SIZE};
+use learning::LastMatch;
+use library::GestureLibrary;
+use recognizer::{recognize_top, Candidate, Match, RecognitionConfig, WidthSource, RESOLUTIONS};
+use replay::Replay;
+use session_log::SessionLog;
+use smoothing::{OneEuroFilter, OneEuroParams};
+use storage::TemplateStorage;
+use zones::HotZones;
+use templates::{split_strokes, Template};
+use theme::Theme;
+use training::{ActiveTraining, Training};
+use ui_strings::{fill, UiStrings};
 
 const BRUSH_THICKNESS: u32 = 3;
-const BRUSH_COLOR: Color = Color::linear_rgb(255.0, 255.0, 255.0);
-const BOARD_COLOR: Color = Color::linear_rgb(0.0, 0.0, 0.0);
-const N_RESAMPLED_POINTS: usize = 32;
+// the buttons sit in a panel on either side of the canvas
+const UI_PANEL_WIDTH: f32 = 160.0;
+// the history list, in the right panel below the result text
+const HISTORY_PANEL_TOP: f32 = 60.0;
+const HISTORY_PANEL_HEIGHT: f32 = 300.0;
+const HISTORY_SCROLL_LINE_HEIGHT: f32 = 20.0;
+// the recognition latency, under the FPS in the top left corner
+const LATENCY_OVERLAY_TOP: f32 = 24.0;
+// the score chart, in the left panel below the overlay, one bar per class
+const SCORE_PANEL_TOP: f32 = 72.0;
+const SCORE_PANEL_CLASSES: usize = 5;
+const SCORE_BAR_HEIGHT: f32 = 8.0;
+// how far the board can be zoomed out and in, and by how much a notch of the
+// scroll wheel does it
+const MIN_ZOOM: f32 = 0.5;
+const MAX_ZOOM: f32 = 8.0;
+const ZOOM_STEP: f32 = 1.1;
+
+// drawing this fast, in board pixels per second, is a width of 1 for
+// `--width-source=speed`. Twice as fast is half as wide
+const WIDTH_REFERENCE_SPEED: f32 = 500.0;
+
+// how much pressure can thin out or thicken the brush
+const MIN_PRESSURE_SCALE: f32 = 0.3;
+const MAX_PRESSURE_SCALE: f32 = 2.0;
+
+#[derive(Resource)]
+struct DrawingBoard {
+    image: Handle<Image>,
+    // the part of the image that has been painted on since it was last
+    // cleared, clearing only goes over that
+    painted: Option<URect>,
+}
+
+#[derive(Component)]
+struct BoardSprite;
+
+// the part of the window that can be drawn on, in window coordinates. The
+// board covers exactly this area and everything drawn on it (strokes, replays,
+// the match overlay) is in board coordinates, relative to its top left corner
+#[derive(Resource)]
+struct CanvasRect(Rect);
 
+impl CanvasRect {
+    fn for_window(window_size: Vec2) -> Self {
+        let min = Vec2::new(UI_PANEL_WIDTH, 0.0);
+        let max = Vec2::new(window_size.x - UI_PANEL_WIDTH, window_size.y);
+        Self(Rect::from_corners(min, max.max(min + 1.0)))
+    }
+
+    // where the board sprite goes
+    fn world_center(&self, window_size: Vec2) -> Vec2 {
+        window_to_world(window_size, self.0.center())
+    }
+}
+
+// window coordinates start at the top left with y going down, the camera is
+// centered on the window with y going up
+fn window_to_world(window_size: Vec2, window_pos: Vec2) -> Vec2 {
+    Vec2::new(window_pos.x - window_size.x / 2.0, window_size.y / 2.0 - window_pos.y)
+}
+
+// how the board is shown in the canvas: scaled by `zoom` around its center,
+// which is `pan` pixels away from the canvas's. Only the view changes, board
+// coordinates (and so the drawings) stay the same
 #[derive(Resource)]
-struct DrawingBoard(Handle<Image>);
+struct BoardView {
+    zoom: f32,
+    pan: Vec2,
+}
+
+impl Default for BoardView {
+    fn default() -> Self {
+        Self {
+            zoom: 1.0,
+            pan: Vec2::ZERO,
+        }
+    }
+}
+
+impl BoardView {
+    // None for positions outside of the canvas or the board
+    fn to_board(&self, canvas: &CanvasRect, window_pos: Vec2) -> Option<Vec2> {
+        if !canvas.0.contains(window_pos) {
+            return None;
+        }
+        let size = canvas.0.size();
+        let board_pos = (window_pos - canvas.0.center() - self.pan) / self.zoom + size / 2.0;
+        Rect::from_corners(Vec2::ZERO, size).contains(board_pos).then_some(board_pos)
+    }
+
+    fn to_window(&self, canvas: &CanvasRect, board_pos: Vec2) -> Vec2 {
+        canvas.0.center() + self.pan + (board_pos - canvas.0.size() / 2.0) * self.zoom
+    }
+
+    // where gizmos draw a point of the board
+    fn to_world(&self, canvas: &CanvasRect, window_size: Vec2, board_pos: Vec2) -> Vec2 {
+        window_to_world(window_size, self.to_window(canvas, board_pos))
+    }
+
+    fn sprite_transform(&self, canvas: &CanvasRect, window_size: Vec2) -> Transform {
+        let center = canvas.world_center(window_size) + Vec2::new(self.pan.x, -self.pan.y);
+        Transform::from_translation(center.extend(0.0)).with_scale(Vec2::splat(self.zoom).extend(1.0))
+    }
+
+    // zooms by `factor` keeping the board under `window_pos` where it is
+    fn zoom_at(&mut self, canvas: &CanvasRect, window_pos: Vec2, factor: f32) {
+        let zoom = (self.zoom * factor).clamp(MIN_ZOOM, MAX_ZOOM);
+        let board_pos = (window_pos - canvas.0.center() - self.pan) / self.zoom + canvas.0.size() / 2.0;
+        self.pan = window_pos - canvas.0.center() - (board_pos - canvas.0.size() / 2.0) * zoom;
+        self.zoom = zoom;
+    }
+}
 
 #[derive(Component)]
 struct ResultText;
@@ -28,12 +208,30 @@ struct ResultText;
 #[derive(Resource)]
 struct BrushEnabled(bool);
 
+// whether a recognized drawing is replaced by its template on the board
+#[derive(Resource)]
+struct Beautify(bool);
+
+// what happens to the pen's points before they're added to the drawing
+#[derive(Resource, Default)]
+struct BrushConfig {
+    // None takes them as they come
+    smoothing: Option<OneEuroParams>,
+}
+
 #[derive(Resource)]
 struct IsTyping(bool);
 
 #[derive(Resource)]
 struct OverAButton(bool);
 
+// what the name typed into the text box is for
+#[derive(Resource, PartialEq)]
+enum NameInputPurpose {
+    AddGesture,
+    Training,
+}
+
 #[derive(Component)]
 struct ToggleBrushButton;
 
@@ -43,191 +241,528 @@ struct AddGestureButton;
 #[derive(Component)]
 struct EndDrawingButton;
 
+#[derive(Component)]
+struct SwitchSetButton;
+
+#[derive(Component)]
+struct ResolutionButton;
+
+#[derive(Component)]
+struct ReplayButton;
+
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Component)]
+struct ExportButton;
+
+#[derive(Component)]
+struct TrainButton;
+
+#[derive(Component)]
+struct CompactButton;
+
+#[derive(Component)]
+struct HistoryPanel;
+
+// the id of the history entry it brings back
+#[derive(Component)]
+struct HistoryEntryButton(u64);
+
+#[derive(Component)]
+struct ScorePanel;
+
+#[derive(Component)]
+struct LatencyOverlay;
+
+// "name (set)" and confidence of the closest classes of the last recognition,
+// closest first
+#[derive(Resource, Default)]
+struct ClassScores(Vec<(String, f32)>);
+
+// what the pen (or finger) was doing at a point, the mouse and keyboard
+// always draw at full pressure and without tilt
+#[derive(Clone, Copy, PartialEq)]
+struct PenSample {
+    // 1.0 is an average press
+    pressure: f32,
+    // altitude in radians, 0 is flat on the surface and PI / 2 perpendicular to it
+    tilt: Option<f32>,
+}
+
+impl Default for PenSample {
+    fn default() -> Self {
+        Self {
+            pressure: 1.0,
+            tilt: None,
+        }
+    }
+}
+
+impl PenSample {
+    fn from_force(force: Option<ForceTouch>) -> Self {
+        match force {
+            Some(ForceTouch::Calibrated { force, altitude_angle, .. }) => Self {
+                pressure: force as f32,
+                tilt: altitude_angle.map(|angle| angle as f32),
+            },
+            // no telling what a normalized 1.0 corresponds to, so it's taken as is
+            Some(ForceTouch::Normalized(force)) => Self {
+                pressure: force as f32,
+                tilt: None,
+            },
+            None => Self::default(),
+        }
+    }
+}
+
 #[derive(PartialEq)]
 enum DrawMoment {
     Idle,
     Ended,
     Paused,
-    Began(Vec2, bool), // the bool is to check if it previously it was paused or not
-    Drawing(Vec2),
+    Began(Vec2, bool, PenSample), // the bool is to check if it previously it was paused or not
+    Drawing(Vec2, PenSample),
 }
 
-#[derive(Resource)]
+// a component on the windows of `canvases`, which have a state of their own
+#[derive(Resource, Component)]
 struct DrawState(DrawMoment);
 
 #[derive(Resource)]
-struct StrokeTemplates(HashMap<String, HashSet<Template>>);
+struct ResampledPoints(Candidate);
+
+// the strokes of the current (or last) drawing as they were drawn, in window
+// coordinates, with the Time::elapsed_secs() and pen input of every point.
+// Points are smoothed on the way in, so replays and templates made from the
+// drawing get the same strokes that were painted
+#[derive(Resource, Component, Default, Clone)]
+struct CandidateVectors {
+    strokes: Vec<Vec<Vec2>>,
+    times: Vec<Vec<f32>>,
+    pen: Vec<Vec<PenSample>>,
+    // every stroke's own, None if it isn't smoothed
+    filters: Vec<Option<OneEuroFilter>>,
+}
 
-#[derive(Resource)]
-struct ResampledPoints(Vec<Vec2>);
+impl CandidateVectors {
+    fn begin_stroke(&mut self, brush: &BrushConfig) {
+        self.strokes.push(vec![]);
+        self.times.push(vec![]);
+        self.pen.push(vec![]);
+        self.filters.push(brush.smoothing.map(OneEuroFilter::new));
+    }
+
+    // adds to the stroke that was begun last
+    fn push(&mut self, point: Vec2, time: f32, pen: PenSample) -> Option<Vec2> {
+        let stroke = self.strokes.len().checked_sub(1)?;
+        self.push_to(stroke, point, time, pen)
+    }
+
+    // the point after smoothing, None (and nothing added) for strokes that
+    // don't exist (anymore, after an undo)
+    fn push_to(&mut self, stroke: usize, point: Vec2, time: f32, pen: PenSample) -> Option<Vec2> {
+        if stroke >= self.strokes.len() {
+            return None;
+        }
+        let point = match self.filters.get_mut(stroke) {
+            Some(Some(filter)) => filter.filter(point, time),
+            _ => point,
+        };
+        self.strokes[stroke].push(point);
+        self.times[stroke].push(time);
+        self.pen[stroke].push(pen);
+        Some(point)
+    }
 
-fn resample(candidate_vectors: &Vec<Vec<Vec2>>, total_length: f32) -> Vec<Vec2> {
-    let mut resampled_points: Vec<Vec2> = Vec::with_capacity(N_RESAMPLED_POINTS);
-    let increment = total_length / N_RESAMPLED_POINTS as f32;
+    // false if there was nothing to take off
+    fn pop_stroke(&mut self) -> bool {
+        self.times.pop();
+        self.pen.pop();
+        self.filters.pop();
+        self.strokes.pop().is_some()
+    }
 
-    for candidate_points in candidate_vectors.iter() {
-        if candidate_points.len() > 1 {
-            resampled_points.push(candidate_points[0]);
+    fn last_point(&self) -> Option<Vec2> {
+        self.strokes.last().and_then(|stroke| stroke.last()).copied()
+    }
 
-            let mut accumulated_distance = 0.0;
-            let mut previous_point = candidate_points[0];
+    fn pressures(&self) -> Vec<Vec<f32>> {
+        self.pen
+            .iter()
+            .map(|samples| samples.iter().map(|sample| sample.pressure).collect())
+            .collect()
+    }
 
-            for i in 1..candidate_points.len() {
-                let current_point = candidate_points[i];
-                let mut segment_distance = previous_point.distance(current_point);
+    // every point's width for the recognizer, see `WidthSource`
+    fn widths(&self, source: WidthSource) -> Vec<Vec<f32>> {
+        match source {
+            WidthSource::Pressure => self.pressures(),
+            WidthSource::Speed => self
+                .strokes
+                .iter()
+                .zip(&self.times)
+                .map(|(stroke, times)| {
+                    (0..stroke.len())
+                        .map(|i| {
+                            // over the points on either side
+                            let (a, b) = (i.saturating_sub(1), (i + 1).min(stroke.len() - 1));
+                            let elapsed = times[b] - times[a];
+                            if elapsed > 0.0 {
+                                WIDTH_REFERENCE_SPEED * elapsed / stroke[a].distance(stroke[b])
+                            } else {
+                                1.0
+                            }
+                        })
+                        .collect()
+                })
+                .collect(),
+        }
+    }
 
-                while segment_distance + accumulated_distance >= increment
-                    && resampled_points.len() < N_RESAMPLED_POINTS
-                {
-                    let alpha = (increment - accumulated_distance) / segment_distance;
-                    let dv = previous_point.lerp(current_point, alpha);
+    // the drawing, resampled and normalized, with the widths of its points if
+    // the recognizer looks at them
+    fn candidate(&self, config: &RecognitionConfig) -> Candidate {
+        let candidate = Candidate::new(&self.strokes, config.n_points, config.preserve_corners);
+        if config.width_weight > 0.0 {
+            candidate.with_widths(&self.strokes, &self.widths(config.width_source))
+        } else {
+            candidate
+        }
+    }
+}
 
-                    resampled_points.push(dv);
+// the recognized template's points, in window coordinates
+#[derive(Resource, Default)]
+struct MatchOverlay {
+    points: Vec<Vec2>,
+    stroke_count: usize,
+}
 
-                    previous_point = dv;
-                    accumulated_distance = 0.0;
-                    segment_distance = dv.distance(current_point);
-                }
+#[derive(Resource, Default)]
+struct ActiveReplay(Option<Replay>);
+
+// sent whenever a drawing matched a template
+#[derive(Event)]
+struct GestureRecognized {
+    // 0 for the main canvas, see `canvases::ExtraCanvas::index` for the others
+    canvas: usize,
+    set: String,
+    name: String,
+    // 0 to 1, see `Calibration::confidence`
+    confidence: f32,
+    latency_ms: f64,
+    // not every template was matched in time, see `Match::truncated`
+    truncated: bool,
+}
 
-                accumulated_distance += segment_distance;
-                previous_point = current_point;
+// back to an empty board of `board_size`, a board that keeps its size only
+// gets the part that was painted on cleared
+fn reset_board(board_size: Vec2, board: &mut Image, painted: &mut Option<URect>, theme: &Theme) {
+    let color = theme.board.to_srgba().to_u8_array();
+    if board.size_f32() != board_size {
+        board.resize(Extent3d {
+            width: board_size.x as u32,
+            height: board_size.y as u32,
+            depth_or_array_layers: 1,
+        });
+        for pixel in board.data.chunks_exact_mut(4) {
+            pixel.copy_from_slice(&color);
+        }
+    } else if let Some(area) = *painted {
+        let width = board.width() as usize;
+        for y in area.min.y as usize..area.max.y as usize {
+            let row = (y * width + area.min.x as usize) * 4..(y * width + area.max.x as usize) * 4;
+            for pixel in board.data[row].chunks_exact_mut(4) {
+                pixel.copy_from_slice(&color);
             }
         }
     }
+    *painted = None;
+}
+
+fn main() {
+    // every option can also go in stroke_recognizer.toml (or `--config=<file>`),
+    // the command line wins
+    let options = config_file::options().unwrap_or_else(|e| {
+        eprintln!("Couldn't load the config file: {}", e);
+        std::process::exit(1);
+    });
+
+    let dataset_dir = options.dataset.clone();
+    let dataset = dataset_dir.as_ref().map(|dir| {
+        dataset::load_dir(dir).unwrap_or_else(|e| {
+            eprintln!("Couldn't load the dataset at {}: {}", dir, e);
+            std::process::exit(1);
+        })
+    });
+
+    let mut recognition_config = RecognitionConfig::default();
+    if let Some(n) = options.points {
+        recognition_config.n_points = n;
+    }
 
-    while resampled_points.len() > N_RESAMPLED_POINTS {
-        resampled_points.pop();
+    // `--matcher=dtw` compares the points in drawing order instead of as clouds
+    if let Some(matcher) = options.matcher {
+        recognition_config.matcher = matcher;
     }
-    
-    resampled_points
-}
 
-fn get_centroid(points: &Vec<Vec2>) -> Vec2 {
-    let mut c_x = 0.0;
-    let mut c_y = 0.0;
-    for point in points.iter() {
-        c_x += point.x;
-        c_y += point.y;
+    // `--top-k=0` turns the Protractor pre-filter off
+    if let Some(k) = options.top_k {
+        recognition_config.protractor_top_k = (k > 0).then_some(k);
     }
-    c_x /= points.len() as f32;
-    c_y /= points.len() as f32;
-    Vec2::new(c_x, c_y)
-}
 
-fn scale_and_translate(points: &mut Vec<Vec2>) {
-    // bounding box
-    let (mut min_x, mut min_y, mut max_x, mut max_y) = (f32::MAX, f32::MAX, f32::MIN, f32::MIN);
-    for point in points.iter() {
-        min_x = min_x.min(point.x);
-        min_y = min_y.min(point.y);
-        max_x = max_x.max(point.x);
-        max_y = max_y.max(point.y);
+    // tells a small circle from a big one, see `size_penalty`
+    if let Some(weight) = options.size_weight {
+        recognition_config.size_weight = weight;
     }
 
-    // scale
-    let scale = f32::max(max_x - min_x, max_y - min_y);
-    for point in points.iter_mut() {
-        point.x = (point.x - min_x) / scale;
-        point.y = (point.y - min_y) / scale;
+    // tells a clockwise circle from a counter-clockwise one, see `direction_penalty`
+    if let Some(weight) = options.direction_weight {
+        recognition_config.direction_weight = weight;
     }
 
-    // translate to origin
-    let centroid = get_centroid(&points);
-    for point in points.iter_mut() {
-        point.x -= centroid.x;
-        point.y -= centroid.y;
+    // tells an emphatic stroke from a light one, see `width_penalty`.
+    // `--width-source=speed` goes by how slowly instead of how hard it was drawn
+    if let Some(weight) = options.width_weight {
+        recognition_config.width_weight = weight;
+    }
+    if let Some(source) = options.width_source {
+        recognition_config.width_source = source;
     }
-}
 
-fn get_weights() -> [f32; N_RESAMPLED_POINTS] {
-    let mut weights = [0.0; N_RESAMPLED_POINTS];
-    let n = N_RESAMPLED_POINTS as f32;
-    for i in 0..N_RESAMPLED_POINTS {
-        weights[i] = 1.0 - i as f32 / n;
+    // `--learning=weight` or `prune` acts on the templates behind the
+    // misrecognitions that get corrected with 'Add'
+    if let Some(mode) = options.learning {
+        recognition_config.learning = mode;
     }
-    weights
-}
-fn greedy_5_eval_nearest(
-    candidate_index: usize,
-    template: &mut Vec<Vec2>,
-    candidate: &Vec<Vec2>,
-    weights: [f32; N_RESAMPLED_POINTS],
-) -> f32 {
-    let mut nearest_dist = f32::MAX;
-    let mut nearest_point_index = 0;
-    template.iter().enumerate().for_each(|(j, t_point)| {
-        let weight = weights[j];
-        let d = weight * candidate[candidate_index].distance_squared(*t_point);
-        if d < nearest_dist {
-            nearest_dist = d;
-            nearest_point_index = j;
-        }
+
+    // `--min-confidence=0.4` reports weaker matches as not recognized
+    if let Some(confidence) = options.min_confidence {
+        recognition_config.min_confidence = confidence;
+    }
+    // `--latency-budget=8` stops matching after 8 milliseconds with the best
+    // match so far, see `RecognitionConfig::latency_budget`
+    if let Some(ms) = options.latency_budget {
+        recognition_config.latency_budget = Some(std::time::Duration::from_secs_f64(ms / 1000.0));
+    }
+    // `--keypad` types the digits and symbols written on the canvas, see `keypad`
+    let keypad = options.keypad.unwrap_or(false);
+    if keypad && options.min_confidence.is_none() {
+        recognition_config.min_confidence = keypad::MIN_CONFIDENCE;
+    }
+
+    // resampling keeps a point on every corner the stroke turns at
+    recognition_config.preserve_corners = options.corners.unwrap_or(false);
+
+    // `--smoothing` filters out the jitter of touchscreens and cheap mice,
+    // `--smoothing=<min cutoff>,<beta>` tunes it, see `OneEuroParams`
+    let brush = BrushConfig {
+        smoothing: options.smoothing.map(Option::unwrap_or_default),
+    };
+
+    let mut compaction = CompactionConfig::default();
+    if let Some(threshold) = options.merge_threshold {
+        compaction.threshold = threshold;
+    }
+    if let Some(max) = options.max_templates {
+        compaction.max_templates = max;
+    }
+
+    let zones = options.zones.clone().unwrap_or_default();
+    let extra_canvases = options.canvases.clone().unwrap_or_default();
+
+    // there are no sockets to listen on in the browser
+    #[cfg(not(target_arch = "wasm32"))]
+    let server = options.serve.as_ref().map(|address| {
+        network::NetworkServer::bind(address).unwrap_or_else(|e| {
+            eprintln!("Couldn't listen on {}", e);
+            std::process::exit(1);
+        })
     });
-    template.swap_remove(nearest_point_index);
-    nearest_dist
-}
-// O(n^(2 + epsilon))
-fn greedy_5(templates: Res<StrokeTemplates>, resampled_points: &Vec<Vec2>, epsilon: f32) -> String {
-    let mut least_shape_distance = f32::MAX;
-    let mut nearest_shape_name = "not recognized";
-
-    let n_starting_points = (N_RESAMPLED_POINTS as f32).powf(epsilon).ceil() as usize;
-    let weights = get_weights();
-    for (name, stroke) in templates.0.iter() {
-        for stroke in stroke.iter() {
-            let mut least_distance: f32 = f32::MAX;
-           
-            for starting_point in 0..n_starting_points {
-                let mut total_distance_1: f32 = 0.0; // matching candidate with template
-                let mut template_p_clone = stroke.0.clone();
-                
-                let mut total_distance_2: f32 = 0.0; // matching template with candidate
-                let mut resampled_p_clone = resampled_points.clone();
-
-                for i in starting_point..N_RESAMPLED_POINTS {
-                    total_distance_1 += greedy_5_eval_nearest(i, &mut template_p_clone, resampled_points, weights);
-                    total_distance_2 += greedy_5_eval_nearest(i, &mut resampled_p_clone, &stroke.0, weights);
-                }
 
-                for i in 0..starting_point {
-                    total_distance_1 += greedy_5_eval_nearest(i, &mut template_p_clone, resampled_points, weights);
-                    total_distance_2 += greedy_5_eval_nearest(i, &mut resampled_p_clone, &stroke.0, weights);
-                }
+    let mut augmentation = Augmentation::default();
+    if let Some(variants) = options.augment {
+        augmentation.variants = variants;
+    }
+    if let Some(seed) = options.augment_seed {
+        augmentation.seed = seed;
+    }
 
-                let min = f32::min(total_distance_1, total_distance_2);
-                least_distance = least_distance.min(min);
+    // the SVG files in templates/ (or `--template-dir=<dir>`) join the built-in
+    // templates, the directory is optional but one that was asked for has to load
+    let template_dir = options.template_dir.clone();
+    let mut default_templates = templates::stroke_templates();
+    let svg_dir = template_dir.clone().unwrap_or_else(|| svg_templates::DEFAULT_DIR.to_string());
+    if template_dir.is_some() || std::path::Path::new(&svg_dir).is_dir() {
+        match svg_templates::load_dir(&svg_dir) {
+            Ok(set) => {
+                for (name, templates) in set {
+                    default_templates.entry(name).or_default().extend(templates);
+                }
             }
-
-            if least_distance < least_shape_distance {
-                least_shape_distance = least_distance;
-                nearest_shape_name = name;
+            Err(e) => {
+                eprintln!("Couldn't load the SVG templates: {}", e);
+                std::process::exit(1);
             }
         }
     }
 
-    nearest_shape_name.to_string()
-}
+    if options.bench.unwrap_or(false) {
+        let config = bench::BenchConfig::from_options(&options);
+        match &dataset {
+            Some(gestures) => bench::run_dataset(gestures, &config, &recognition_config),
+            None if keypad => bench::run(
+                keypad::templates(recognition_config.n_points, recognition_config.preserve_corners),
+                &config,
+                &recognition_config,
+            ),
+            None => bench::run(default_templates, &config, &recognition_config),
+        }
+        return;
+    }
 
-fn reset_board(window_size: Vec2, board: &mut Image, resize: bool) {
-    if resize {
-        board.resize(Extent3d {
-            width: window_size.x as u32,
-            height: window_size.y as u32,
-            depth_or_array_layers: 1,
-        });
+    // bindings.toml is optional, but a file that was asked for has to load
+    let bindings = match &options.bindings {
+        Some(file) => InputBindings::load(file),
+        None => InputBindings::load_default(),
+    }
+    .unwrap_or_else(|e| {
+        eprintln!("Couldn't load the input bindings: {}", e);
+        std::process::exit(1);
+    });
+
+    // English unless there's a `--locale=<code>`, see `ui_strings`
+    let strings = match &options.locale {
+        Some(locale) => UiStrings::load(locale).unwrap_or_else(|e| {
+            eprintln!("Couldn't load the UI strings: {}", e);
+            std::process::exit(1);
+        }),
+        None => UiStrings::default(),
+    };
+    let theme = if options.high_contrast.unwrap_or(false) {
+        Theme::high_contrast()
+    } else {
+        Theme::default()
+    };
+
+    let mut library = GestureLibrary::new("default", default_templates);
+    if let (Some(dir), Some(gestures)) = (&dataset_dir, &dataset) {
+        let set_name = std::path::Path::new(dir)
+            .file_name()
+            .map_or(dir.clone(), |name| name.to_string_lossy().to_string());
+        library.insert_set(set_name.clone(), dataset::into_templates(gestures, dir));
+        library.set_active(&[set_name]);
+    }
+    // before the saved templates, so what learning did to its templates sticks
+    if keypad {
+        library.insert_set(
+            keypad::SET,
+            keypad::templates(recognition_config.n_points, recognition_config.preserve_corners),
+        );
+        library.set_active(&[keypad::SET]);
+    }
+    // the gestures added in earlier runs, `--templates=<file>` keeps them
+    // somewhere other than user_templates.toml
+    let template_storage = TemplateStorage::for_platform(options.templates.clone());
+    match template_storage.0.load() {
+        Ok(saved) => library.add_saved(saved),
+        // saving would overwrite whatever is wrong with it
+        Err(e) => {
+            eprintln!("Couldn't load the saved templates: {}", e);
+            std::process::exit(1);
+        }
+    }
+    // `--sets=letters,shapes` matches against several sets at once
+    if let Some(sets) = &options.sets {
+        if !library.set_active(sets) {
+            eprintln!("Unknown gesture set in {}", sets.join(","));
+            std::process::exit(1);
+        }
+    }
+
+    // `--diagnose` reports the templates of the active sets that are likely
+    // to be mistaken for another gesture's, like F6 does in the app
+    if options.diagnose.unwrap_or(false) {
+        library.set_resampling(recognition_config.n_points, recognition_config.preserve_corners);
+        println!("{}", diagnostics::diagnose(&library, &recognition_config).report());
+        return;
+    }
+
+    // `--window=1600x900`, in logical pixels
+    let mut window = Window {
+        // in the browser the canvas takes up whatever element it's in
+        fit_canvas_to_parent: true,
+        ..default()
+    };
+    if let Some(size) = options.window {
+        window.resolution = WindowResolution::new(size.width, size.height);
     }
 
-    for x in 0..(window_size.x as u32) {
-        for y in 0..(window_size.y as u32) {
-            board.set_color_at(x, y, BOARD_COLOR).unwrap_or(());
+    // `--record` records the drawings from the start, `--record=<file>`
+    // somewhere other than session.jsonl
+    let mut session = SessionLog::new(
+        options
+            .record
+            .clone()
+            .flatten()
+            .unwrap_or_else(|| session_log::DEFAULT_FILE.to_string()),
+    );
+    if options.record.is_some() {
+        if let Err(e) = session.start() {
+            eprintln!("Couldn't record the session: {}", e);
+            std::process::exit(1);
         }
     }
-}
 
-fn main() {
-    App::new()
-        .add_plugins((
-            DefaultPlugins,
+    // `--resume` restores the saved session on startup, `--session=<file>`
+    // saves and restores it somewhere other than saved_session.json
+    let session_file = saved_session::SessionFile {
+        path: options
+            .session
+            .clone()
+            .unwrap_or_else(|| saved_session::DEFAULT_FILE.to_string()),
+        resume: options.resume.unwrap_or(false),
+    };
+
+    // `--script=<file>` replays a recorded session without a window and checks
+    // that every drawing is still recognized the same, see `script`
+    let script = options.script.as_ref().map(|path| {
+        script::ScriptedInput::load(path, &bindings).unwrap_or_else(|e| {
+            eprintln!("Couldn't load the script: {}", e);
+            std::process::exit(1);
+        })
+    });
+    let headless = script.is_some();
+
+    let mut plugins = DefaultPlugins.set(WindowPlugin {
+        primary_window: Some(window),
+        ..default()
+    });
+    // nothing to open the window with or to render to, the app runs frame
+    // after frame as fast as it can
+    if headless {
+        plugins = plugins
+            .disable::<WinitPlugin>()
+            .set(RenderPlugin {
+                render_creation: WgpuSettings {
+                    backends: None,
+                    ..default()
+                }
+                .into(),
+                ..default()
+            })
+            // without a backend there's no render app, but the camera plugin
+            // RenderPlugin adds on its own still asks for one to extract
+            // `ManualTextureViews` into and logs an error that it isn't there
+            .set(LogPlugin {
+                filter: format!("{},bevy_render::extract_resource=off", DEFAULT_FILTER),
+                ..default()
+            })
+            .add(ScheduleRunnerPlugin::run_loop(std::time::Duration::ZERO));
+    }
+
+    let mut app = App::new();
+    app.add_plugins((
+            plugins,
             TextInputPlugin,
             FpsOverlayPlugin {
                 config: FpsOverlayConfig {
@@ -235,30 +770,211 @@ fn main() {
                         font_size: 20.0,
                         ..default()
                     },
-                    text_color: Color::linear_rgb(0.0, 255.0, 0.0),
+                    text_color: theme.text,
                     enabled: true,
                 },
             },
         ))
-        .add_systems(Startup, (setup_window, spawn))
+        .add_systems(Startup, (setup_window, spawn, canvases::spawn_extra_canvases))
         .add_systems(
             Update,
             (
-                draw_state_handler,
-                toggle_brush,
-                handle_adding_gestures,
-                draw,
-                textbox_input_listener,
+                // input and buttons first so the drawing systems know if a
+                // click was meant for a button
+                (
+                    handle_resize,
+                    zoom_and_pan,
+                    draw_state_handler,
+                    gamepad::gamepad_input,
+                    toggle_brush,
+                    toggle_overlay,
+                    toggle_recording,
+                    diagnose_templates,
+                    compare::toggle_compare,
+                    theme::toggle_contrast,
+                    switch_gesture_set,
+                    switch_resolution,
+                    apply_resolution.run_if(resource_changed::<RecognitionConfig>),
+                    calibrate.run_if(resource_changed::<GestureLibrary>),
+                    handle_adding_gestures,
+                    start_training,
+                    start_replay,
+                    compact_templates,
+                    select_history_entry,
+                )
+                    .chain(),
+                (
+                    // before drawing, so a restored drawing can be added to
+                    (saved_session::save_session, saved_session::restore_session),
+                    draw,
+                    canvases::draw_on_extra_canvases,
+                    gesture_input::send_touches,
+                    gesture_input::apply_gesture_input,
+                    undo_stroke,
+                    recognize_drawing,
+                    show_recognition,
+                    play_replay,
+                    textbox_input_listener,
+                    draw_match_overlay,
+                    compare::draw_compare,
+                    gamepad::draw_gamepad_cursor,
+                    zones::draw_zones,
+                    update_history_panel.run_if(resource_changed::<RecognitionHistory>),
+                    update_score_panel.run_if(resource_changed::<ClassScores>),
+                    update_latency_overlay.run_if(resource_changed::<RecognitionHistory>),
+                    scroll_history,
+                    storage::save_templates.run_if(resource_changed::<GestureLibrary>),
+                )
+                    .chain(),
+                // after everything that spawns or changes text and colors
+                (theme::apply_theme, accessibility::name_buttons, accessibility::announce),
             )
                 .chain(),
         )
         .insert_resource(BrushEnabled(true))
+        .insert_resource(Beautify(options.beautify.unwrap_or(false)))
+        .insert_resource(brush)
         .insert_resource(IsTyping(false))
         .insert_resource(OverAButton(false))
+        .insert_resource(NameInputPurpose::AddGesture)
+        .init_resource::<ActiveTraining>()
         .insert_resource(DrawState(DrawMoment::Idle))
-        .insert_resource(StrokeTemplates(templates::stroke_templates()))
-        .insert_resource(ResampledPoints(Vec::new()))
-        .run();
+        .insert_resource(library)
+        .insert_resource(template_storage)
+        .insert_resource(bindings)
+        .insert_resource(strings)
+        .insert_resource(theme)
+        .insert_resource(ClearColor(theme.background))
+        .insert_resource(recognition_config)
+        .insert_resource(compaction)
+        .insert_resource(augmentation)
+        .insert_resource(zones)
+        .insert_resource(extra_canvases)
+        .insert_resource(ResampledPoints(Candidate::default()))
+        .init_resource::<CandidateVectors>()
+        .init_resource::<MatchOverlay>()
+        .init_resource::<BoardView>()
+        .init_resource::<CompareView>()
+        .init_resource::<gamepad::GamepadCursor>()
+        .init_resource::<ActiveReplay>()
+        .init_resource::<RecognitionHistory>()
+        .init_resource::<ClassScores>()
+        .insert_resource(session)
+        .insert_resource(session_file)
+        .init_resource::<LastMatch>()
+        .init_resource::<Calibration>()
+        .add_event::<GestureRecognized>()
+        .add_event::<StrokeBegan>()
+        .add_event::<StrokePoint>()
+        .add_event::<StrokeEnded>()
+        .add_event::<CandidateComplete>();
+    // there's no directory to export to in the browser
+    #[cfg(not(target_arch = "wasm32"))]
+    app.add_systems(Update, export_image.after(start_replay).before(compact_templates));
+    // there are no files to watch in the browser
+    #[cfg(not(target_arch = "wasm32"))]
+    app.insert_resource(template_watch::TemplateWatch::new(svg_dir))
+        .add_systems(Update, template_watch::reload_templates);
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Some(server) = server {
+        app.insert_resource(server)
+            .add_systems(Update, network::serve_network.after(recognize_drawing));
+    }
+    if keypad {
+        app.init_resource::<keypad::KeypadText>()
+            .add_systems(Startup, keypad::spawn_keypad_line)
+            .add_systems(Update, keypad::type_recognized);
+    }
+    if let Some(script) = script {
+        app.insert_resource(script)
+            .insert_resource(TimeUpdateStrategy::ManualDuration(script::FRAME_TIME))
+            .add_systems(PreUpdate, script::run_script.before(InputSystem));
+    }
+    // there's no render device to match on without a window, an empty
+    // matcher leaves everything to the CPU
+    #[cfg(feature = "gpu")]
+    if headless {
+        app.init_resource::<gpu::GpuMatcher>();
+    } else {
+        app.add_plugins(gpu::GpuMatchPlugin);
+    }
+    // a script that didn't go as recorded fails the run
+    if app.run().is_error() {
+        std::process::exit(1);
+    }
+}
+
+// shows or hides the FPS and the latency together
+fn toggle_overlay(
+    mut fps: ResMut<FpsOverlayConfig>,
+    mut latency: Single<&mut Visibility, With<LatencyOverlay>>,
+    bindings: Res<InputBindings>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    buttons: Res<ButtonInput<MouseButton>>,
+    typing: Res<IsTyping>,
+) {
+    if typing.0 || !bindings.just_pressed(Action::ToggleOverlay, &keyboard, &buttons) {
+        return;
+    }
+    fps.enabled = !fps.enabled;
+    **latency = if fps.enabled { Visibility::Inherited } else { Visibility::Hidden };
+}
+
+// how many of the conflicts and outliers fit under the result, the rest are
+// in the log
+const DIAGNOSTICS_SHOWN: usize = 4;
+
+// cross-matches the templates of the active sets, see `diagnostics`
+fn diagnose_templates(
+    library: Res<GestureLibrary>,
+    config: Res<RecognitionConfig>,
+    mut result_text: Single<&mut Text, With<ResultText>>,
+    bindings: Res<InputBindings>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    buttons: Res<ButtonInput<MouseButton>>,
+    typing: Res<IsTyping>,
+    strings: Res<UiStrings>,
+) {
+    if typing.0 || !bindings.just_pressed(Action::Diagnose, &keyboard, &buttons) {
+        return;
+    }
+    let diagnostics = diagnostics::diagnose(&library, &config);
+    info!("{}", diagnostics.report());
+    let lines = diagnostics.lines(&strings);
+    let mut text = diagnostics.summary(&strings);
+    for line in lines.iter().take(DIAGNOSTICS_SHOWN) {
+        text.push_str("\n\n");
+        text.push_str(line);
+    }
+    if lines.len() > DIAGNOSTICS_SHOWN {
+        text.push_str("\n\n");
+        text.push_str(&fill(&strings.diagnostics_more, &[("count", &(lines.len() - DIAGNOSTICS_SHOWN))]));
+    }
+    result_text.0 = text;
+}
+
+// starts or stops appending the drawings to the session log
+fn toggle_recording(
+    mut session: ResMut<SessionLog>,
+    mut result_text: Single<&mut Text, With<ResultText>>,
+    bindings: Res<InputBindings>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    buttons: Res<ButtonInput<MouseButton>>,
+    typing: Res<IsTyping>,
+    strings: Res<UiStrings>,
+) {
+    if typing.0 || !bindings.just_pressed(Action::ToggleRecording, &keyboard, &buttons) {
+        return;
+    }
+    result_text.0 = if session.is_recording() {
+        session.stop();
+        fill(&strings.stopped_recording, &[("path", &session.path())])
+    } else {
+        match session.start() {
+            Ok(()) => fill(&strings.recording, &[("path", &session.path())]),
+            Err(e) => fill(&strings.recording_failed, &[("error", &e)]),
+        }
+    };
 }
 
 fn toggle_brush(
@@ -269,286 +985,1194 @@ fn toggle_brush(
         (Changed<Interaction>, With<ToggleBrushButton>),
     >,
     mut text: Single<&mut Text, With<ToggleBrushButton>>,
+    bindings: Res<InputBindings>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    buttons: Res<ButtonInput<MouseButton>>,
+    typing: Res<IsTyping>,
+    strings: Res<UiStrings>,
+    theme: Res<Theme>,
 ) {
+    let brush_label = |enabled: bool| if enabled { strings.brush_on.clone() } else { strings.brush_off.clone() };
+    if !typing.0 && bindings.just_pressed(Action::ToggleBrush, &keyboard, &buttons) {
+        brush_enabled.0 = !brush_enabled.0;
+        text.0 = brush_label(brush_enabled.0);
+    }
+
     for (interaction, mut border_color) in &mut interaction_query {
         match *interaction {
             Interaction::Pressed => {
                 over_button.0 = true;
                 brush_enabled.0 = !brush_enabled.0;
-                border_color.0 = bevy::color::palettes::css::LIGHT_GREEN.into();
-                text.0 = if brush_enabled.0 {
-                    format!("ON")
-                } else {
-                    format!("OFF")
-                };
+                border_color.0 = theme.button_pressed;
+                text.0 = brush_label(brush_enabled.0);
             }
             _ => {
-                text.0 = format!("Toggle Brush");
+                text.0 = strings.toggle_brush.clone();
                 over_button.0 = false;
-                border_color.0 = Color::WHITE;
+                border_color.0 = theme.button_border;
             }
         }
     }
 }
 
-fn handle_adding_gestures(
-    mut commands: Commands,
-    mut typing: ResMut<IsTyping>,
+fn active_sets_label(library: &GestureLibrary, strings: &UiStrings) -> String {
+    if library.active().len() > 1 {
+        strings.all_sets.clone()
+    } else {
+        fill(&strings.set, &[("set", &library.active()[0])])
+    }
+}
+
+fn switch_gesture_set(
     mut over_button: ResMut<OverAButton>,
+    mut library: ResMut<GestureLibrary>,
     mut interaction_query: Query<
         (&Interaction, &mut BorderColor),
-        (Changed<Interaction>, With<AddGestureButton>),
+        (Changed<Interaction>, With<SwitchSetButton>),
     >,
-    result_text: Single<&Text, With<ResultText>>,
+    mut text: Single<&mut Text, With<SwitchSetButton>>,
+    strings: Res<UiStrings>,
+    theme: Res<Theme>,
 ) {
     for (interaction, mut border_color) in &mut interaction_query {
         match *interaction {
             Interaction::Pressed => {
                 over_button.0 = true;
-                border_color.0 = bevy::color::palettes::css::LIGHT_GREEN.into();
-                if !result_text.0.is_empty() && !typing.0 {
-                    typing.0 = true;
-                    commands
-                        .spawn(Node {
-                            width: Val::Percent(100.0),
-                            height: Val::Percent(100.0),
-                            align_items: AlignItems::Center,
-                            justify_content: JustifyContent::Center,
-                            bottom: Val::Px(300.0),
-                            ..default()
-                        })
-                        .with_children(|parent| {
-                            parent.spawn((
-                                Node {
-                                    width: Val::Px(200.0),
-                                    border: UiRect::all(Val::Px(5.0)),
-                                    padding: UiRect::all(Val::Px(5.0)),
-                                    ..default()
-                                },
-                                BorderColor(BRUSH_COLOR),
-                                TextInput,
-                                TextInputTextFont(TextFont {
-                                    font_size: 34.,
-                                    ..default()
-                                }),
-                            ));
-                        });
-                }
+                library.cycle_active();
+                border_color.0 = theme.button_pressed;
+                text.0 = active_sets_label(&library, &strings);
             }
             _ => {
                 over_button.0 = false;
-                border_color.0 = Color::WHITE;
+                border_color.0 = theme.button_border;
             }
         }
     }
 }
 
-fn textbox_input_listener(
-    mut events: EventReader<TextInputSubmitEvent>,
-    mut typing: ResMut<IsTyping>,
-    mut commands: Commands,
-    resampled_points: Res<ResampledPoints>,
-    mut custom_templates: ResMut<StrokeTemplates>,
-    mut result_text: Single<&mut Text, With<ResultText>>,
-) {
-    for event in events.read() {
-        let text = &event.value;
-
-        if resampled_points.0.len() == N_RESAMPLED_POINTS {
-            if let Some(set) = custom_templates.0.get_mut(text) {
-                set.insert(Template(resampled_points.0.clone()));
-            } else {
-                custom_templates.0.insert(
-                    text.clone(),
-                    HashSet::from([Template(resampled_points.0.clone())]),
-                );
-            }
-            result_text.0 = format!("{} gesture added!", text);
-        } else {
-            result_text.0 = format!(
-                "Gesture drawn has too little resampled points (< {})",
-                N_RESAMPLED_POINTS
-            );
-        }
-
-        typing.0 = false;
-        commands.entity(event.entity).despawn();
-    }
+fn resolution_label(config: &RecognitionConfig, strings: &UiStrings) -> String {
+    fill(&strings.points, &[("points", &config.n_points)])
 }
 
-fn draw_state_handler(
-    buttons: Res<ButtonInput<MouseButton>>,
-    keyboard: Res<ButtonInput<KeyCode>>,
-    touches: Res<Touches>,
-    mouse_move_delta: Res<AccumulatedMouseMotion>,
-    mut draw_state: ResMut<DrawState>,
-    window: Single<&Window>,
+fn switch_resolution(
     mut over_button: ResMut<OverAButton>,
+    mut config: ResMut<RecognitionConfig>,
     mut interaction_query: Query<
         (&Interaction, &mut BorderColor),
-        (Changed<Interaction>, With<EndDrawingButton>),
+        (Changed<Interaction>, With<ResolutionButton>),
     >,
+    mut text: Single<&mut Text, With<ResolutionButton>>,
+    strings: Res<UiStrings>,
+    theme: Res<Theme>,
 ) {
-    if buttons.just_pressed(MouseButton::Left) || keyboard.just_pressed(KeyCode::Space) {
-        if let Some(x) = window.cursor_position() {
-            draw_state.0 = DrawMoment::Began(x, draw_state.0 == DrawMoment::Paused);
+    for (interaction, mut border_color) in &mut interaction_query {
+        match *interaction {
+            Interaction::Pressed => {
+                over_button.0 = true;
+                let next = RESOLUTIONS
+                    .iter()
+                    .position(|&n| n == config.n_points)
+                    .map_or(0, |i| (i + 1) % RESOLUTIONS.len());
+                config.n_points = RESOLUTIONS[next];
+                border_color.0 = theme.button_pressed;
+                text.0 = resolution_label(&config, &strings);
+            }
+            _ => {
+                over_button.0 = false;
+                border_color.0 = theme.button_border;
+            }
         }
-    } else if buttons.pressed(MouseButton::Left) && mouse_move_delta.delta != Vec2::ZERO
-        || keyboard.pressed(KeyCode::Space) && mouse_move_delta.delta != Vec2::ZERO
-    {
-        if let Some(x) = window.cursor_position() {
-            draw_state.0 = DrawMoment::Drawing(x);
+    }
+}
+
+fn apply_resolution(config: Res<RecognitionConfig>, mut library: ResMut<GestureLibrary>) {
+    library.set_resampling(config.n_points, config.preserve_corners);
+}
+
+fn calibrate(
+    library: Res<GestureLibrary>,
+    config: Res<RecognitionConfig>,
+    mut calibration: ResMut<Calibration>,
+) {
+    *calibration = Calibration::new(&library, &config);
+}
+
+fn spawn_name_input(commands: &mut Commands, theme: &Theme) {
+    commands
+        .spawn(Node {
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            align_items: AlignItems::Center,
+            justify_content: JustifyContent::Center,
+            bottom: Val::Px(300.0),
+            ..default()
+        })
+        .with_children(|parent| {
+            parent.spawn((
+                Node {
+                    width: Val::Px(200.0),
+                    border: UiRect::all(Val::Px(5.0)),
+                    padding: UiRect::all(Val::Px(5.0)),
+                    ..default()
+                },
+                BorderColor(theme.button_border),
+                TextInput,
+                TextInputTextFont(TextFont {
+                    font_size: 34.,
+                    ..default()
+                }),
+            ));
+        });
+}
+
+fn handle_adding_gestures(
+    mut commands: Commands,
+    mut typing: ResMut<IsTyping>,
+    mut purpose: ResMut<NameInputPurpose>,
+    mut over_button: ResMut<OverAButton>,
+    mut interaction_query: Query<
+        (&Interaction, &mut BorderColor),
+        (Changed<Interaction>, With<AddGestureButton>),
+    >,
+    result_text: Single<&Text, With<ResultText>>,
+    bindings: Res<InputBindings>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    buttons: Res<ButtonInput<MouseButton>>,
+    theme: Res<Theme>,
+) {
+    let mut open_input = bindings.just_pressed(Action::AddGesture, &keyboard, &buttons);
+    for (interaction, mut border_color) in &mut interaction_query {
+        match *interaction {
+            Interaction::Pressed => {
+                over_button.0 = true;
+                border_color.0 = theme.button_pressed;
+                open_input = true;
+            }
+            _ => {
+                over_button.0 = false;
+                border_color.0 = theme.button_border;
+            }
+        }
+    }
+
+    if open_input && !result_text.0.is_empty() && !typing.0 {
+        typing.0 = true;
+        *purpose = NameInputPurpose::AddGesture;
+        spawn_name_input(&mut commands, &theme);
+    }
+}
+
+// 'Train' asks for the gesture with the fewest samples, shift-click to type
+// the name of the gesture instead, click again while training to stop
+fn start_training(
+    mut commands: Commands,
+    mut typing: ResMut<IsTyping>,
+    mut purpose: ResMut<NameInputPurpose>,
+    mut over_button: ResMut<OverAButton>,
+    mut interaction_query: Query<
+        (&Interaction, &mut BorderColor),
+        (Changed<Interaction>, With<TrainButton>),
+    >,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    library: Res<GestureLibrary>,
+    mut training: ResMut<ActiveTraining>,
+    mut result_text: Single<&mut Text, With<ResultText>>,
+    strings: Res<UiStrings>,
+    theme: Res<Theme>,
+) {
+    for (interaction, mut border_color) in &mut interaction_query {
+        match *interaction {
+            Interaction::Pressed => {
+                over_button.0 = true;
+                border_color.0 = theme.button_pressed;
+
+                if let Some(stopped) = training.0.take() {
+                    result_text.0 = fill(&strings.stopped_training, &[("name", &stopped.name)]);
+                } else if keyboard.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]) {
+                    if !typing.0 {
+                        typing.0 = true;
+                        *purpose = NameInputPurpose::Training;
+                        spawn_name_input(&mut commands, &theme);
+                    }
+                } else if let Some(name) = library.least_trained() {
+                    let next = Training::new(name);
+                    result_text.0 = next.prompt(&strings);
+                    training.0 = Some(next);
+                } else {
+                    result_text.0 = strings.nothing_to_train.clone();
+                }
+            }
+            _ => {
+                over_button.0 = false;
+                border_color.0 = theme.button_border;
+            }
+        }
+    }
+}
+
+fn textbox_input_listener(
+    mut events: EventReader<TextInputSubmitEvent>,
+    mut typing: ResMut<IsTyping>,
+    mut commands: Commands,
+    resampled_points: Res<ResampledPoints>,
+    mut library: ResMut<GestureLibrary>,
+    mut result_text: Single<&mut Text, With<ResultText>>,
+    purpose: Res<NameInputPurpose>,
+    mut training: ResMut<ActiveTraining>,
+    compaction: Res<CompactionConfig>,
+    augmentation: Res<Augmentation>,
+    config: Res<RecognitionConfig>,
+    mut session: ResMut<SessionLog>,
+    mut last_match: ResMut<LastMatch>,
+    strings: Res<UiStrings>,
+) {
+    for event in events.read() {
+        let text = &event.value;
+
+        if *purpose == NameInputPurpose::Training {
+            let next = Training::new(text.as_str());
+            result_text.0 = next.prompt(&strings);
+            training.0 = Some(next);
+        } else if !resampled_points.0.points.is_empty() {
+            let template = Template::from_candidate(&resampled_points.0, "user");
+            // a new gesture gets its augmented variants along with its first sample
+            let variants = if library.template_count(text) == 0 {
+                augmentation.variants(text, &template, config.n_points, config.preserve_corners)
+            } else {
+                Vec::new()
+            };
+            let set = library.add_template(text, template).to_string();
+            let augmented = variants.len();
+            for variant in variants {
+                library.add_template(text, variant);
+            }
+            library.limit_templates(text, &compaction, &config);
+            session.record_correction(text);
+            result_text.0 = fill(&strings.gesture_added, &[("name", text), ("set", &set)]);
+            if augmented > 0 {
+                result_text.0 = format!("{}\n{}", result_text.0, fill(&strings.augmented, &[("count", &augmented)]));
+            }
+            if let Some(learned) =
+                learning::learn_from_correction(&mut library, &mut last_match, text, config.learning, &strings)
+            {
+                result_text.0 = format!("{}\n{}", result_text.0, learned);
+            }
+        } else {
+            result_text.0 = strings.draw_before_adding.clone();
+        }
+
+        typing.0 = false;
+        commands.entity(event.entity).despawn();
+    }
+}
+
+fn draw_state_handler(
+    buttons: Res<ButtonInput<MouseButton>>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    touches: Res<Touches>,
+    bindings: Res<InputBindings>,
+    mouse_move_delta: Res<AccumulatedMouseMotion>,
+    mut draw_state: ResMut<DrawState>,
+    mut complete: EventWriter<CandidateComplete>,
+    window: Single<&Window, With<PrimaryWindow>>,
+    other_windows: Query<&Window, Without<PrimaryWindow>>,
+    canvas: Res<CanvasRect>,
+    view: Res<BoardView>,
+    mut over_button: ResMut<OverAButton>,
+    mut interaction_query: Query<
+        (&Interaction, &mut BorderColor),
+        (Changed<Interaction>, With<EndDrawingButton>),
+    >,
+    theme: Res<Theme>,
+) {
+    let cursor_position = window.cursor_position().and_then(|x| view.to_board(&canvas, x));
+    // the buttons were for one of the extra canvases
+    let elsewhere = other_windows.iter().any(|other| other.cursor_position().is_some());
+    if bindings.just_pressed(Action::Draw, &keyboard, &buttons) {
+        if let Some(x) = cursor_position {
+            draw_state.0 = DrawMoment::Began(x, draw_state.0 == DrawMoment::Paused, PenSample::default());
+        }
+    } else if bindings.pressed(Action::Draw, &keyboard, &buttons) && mouse_move_delta.delta != Vec2::ZERO
+    {
+        if let Some(x) = cursor_position {
+            draw_state.0 = DrawMoment::Drawing(x, PenSample::default());
         }
     } else {
         if draw_state.0 != DrawMoment::Paused {
             draw_state.0 = DrawMoment::Idle;
         }
+    }
+
+    // touches are drawn through `gesture_input`, every finger gets its own stroke
+    if (bindings.just_released(Action::Draw, &keyboard, &buttons) || touches.any_just_released())
+        && !over_button.0
+        && !elsewhere
+    {
+        draw_state.0 = DrawMoment::Paused;
+    }
 
-        for touch in touches.iter() {
-            if touches.just_pressed(touch.id()) {
-                draw_state.0 =
-                    DrawMoment::Began(touch.position(), draw_state.0 == DrawMoment::Paused);
-            } else if touch.delta() != Vec2::ZERO {
-                draw_state.0 = DrawMoment::Drawing(touch.position());
+    for (interaction, mut border_color) in &mut interaction_query {
+        match *interaction {
+            Interaction::Pressed => {
+                over_button.0 = true;
+                border_color.0 = theme.button_pressed;
+                complete.send(CandidateComplete);
             }
-            break;
+            _ => {over_button.0 = false; border_color.0 = theme.button_border;}
         }
     }
 
-    if buttons.just_released(MouseButton::Left)
-        || keyboard.just_released(KeyCode::Space)
-        || touches.any_just_released()
-    {
-        if !over_button.0 {
-            draw_state.0 = DrawMoment::Paused;
+    if bindings.just_released(Action::Recognize, &keyboard, &buttons) && !elsewhere {
+        complete.send(CandidateComplete);
+    }
+}
+
+// paints a dot as wide as the brush (or a single pixel without it) straight
+// into the image's RGBA bytes, that's what the board's format is
+fn fill_pixel(
+    board: &mut Image,
+    painted: &mut Option<URect>,
+    vec: Vec2,
+    first_pixel: bool,
+    brush_enabled: bool,
+    pressure: f32,
+    theme: &Theme,
+) {
+    let thickness = if first_pixel {
+        BRUSH_THICKNESS * 2
+    } else {
+        BRUSH_THICKNESS
+    };
+    let thickness =
+        (thickness as f32 * pressure.clamp(MIN_PRESSURE_SCALE, MAX_PRESSURE_SCALE)).round() as i32;
+    let radius = if brush_enabled { thickness } else { 0 };
+
+    // cut off at the edges of the board
+    let center = vec.floor().as_ivec2();
+    let size = board.size().as_ivec2();
+    let min = (center - radius).max(IVec2::ZERO);
+    let max = (center + radius).min(size - 1);
+    if min.x > max.x || min.y > max.y {
+        return;
+    }
+    let color = theme.brush.to_srgba().to_u8_array();
+    for y in min.y..=max.y {
+        for x in min.x..=max.x {
+            // half a pixel more, like the edge of a round brush would cover
+            if 4 * (IVec2::new(x, y) - center).length_squared() <= (2 * radius + 1).pow(2) {
+                let i = (y * size.x + x) as usize * 4;
+                board.data[i..i + 4].copy_from_slice(&color);
+            }
+        }
+    }
+    let area = URect::from_corners(min.as_uvec2(), max.as_uvec2() + 1);
+    *painted = Some(painted.map_or(area, |painted| painted.union(area)));
+}
+
+// wipes the previous drawing and whatever was shown on top of it
+fn start_drawing(
+    board: &mut Image,
+    painted: &mut Option<URect>,
+    board_size: Vec2,
+    candidate_vectors: &mut CandidateVectors,
+    overlay: &mut MatchOverlay,
+    theme: &Theme,
+) {
+    *candidate_vectors = CandidateVectors::default();
+    overlay.points.clear();
+    reset_board(board_size, board, painted, theme);
+}
+
+// fills the gaps between two points when the cursor moved fast
+fn paint_segment(
+    board: &mut Image,
+    painted: &mut Option<URect>,
+    from: Vec2,
+    to: Vec2,
+    brush_enabled: bool,
+    pressure: f32,
+    theme: &Theme,
+) {
+    let delta = from.distance(to);
+
+    if delta > 6.0 {
+        let num_steps = (delta / BRUSH_THICKNESS as f32).ceil() as u32;
+        for step in 0..=num_steps {
+            let alpha = step as f32 / num_steps as f32;
+            let dv = from.lerp(to, alpha);
+            fill_pixel(board, painted, dv, false, brush_enabled, pressure, theme);
+        }
+    } else {
+        fill_pixel(board, painted, to, false, brush_enabled, pressure, theme);
+    }
+}
+
+// paints a whole drawing at once, like it was drawn point by point
+fn paint_strokes(
+    board: &mut Image,
+    painted: &mut Option<URect>,
+    candidate_vectors: &CandidateVectors,
+    brush_enabled: bool,
+    theme: &Theme,
+) {
+    for (stroke, pen) in candidate_vectors.strokes.iter().zip(candidate_vectors.pen.iter()) {
+        for (i, (point, sample)) in stroke.iter().zip(pen).enumerate() {
+            match i.checked_sub(1) {
+                Some(previous) => paint_segment(board, painted, stroke[previous], *point, brush_enabled, sample.pressure, theme),
+                None => fill_pixel(board, painted, *point, true, brush_enabled, sample.pressure, theme),
+            }
+        }
+    }
+}
+
+// strokes that weren't drawn, at full pressure
+fn paint_lines(board: &mut Image, painted: &mut Option<URect>, strokes: &[Vec<Vec2>], brush_enabled: bool, theme: &Theme) {
+    for stroke in strokes {
+        for (i, point) in stroke.iter().enumerate() {
+            match i.checked_sub(1) {
+                Some(previous) => paint_segment(board, painted, stroke[previous], *point, brush_enabled, 1.0, theme),
+                None => fill_pixel(board, painted, *point, true, brush_enabled, 1.0, theme),
+            }
+        }
+    }
+}
+
+// keeping every point at the same distance from the center of the canvas keeps
+// the drawing where it was on screen
+fn handle_resize(
+    mut resized: EventReader<bevy::window::WindowResized>,
+    window: Single<&Window, With<PrimaryWindow>>,
+    mut canvas: ResMut<CanvasRect>,
+    mut board_sprite: Single<&mut Transform, With<BoardSprite>>,
+    view: Res<BoardView>,
+    mut drawingboard: ResMut<DrawingBoard>,
+    mut images: ResMut<Assets<Image>>,
+    mut candidate_vectors: ResMut<CandidateVectors>,
+    mut overlay: ResMut<MatchOverlay>,
+    mut replay: ResMut<ActiveReplay>,
+    brush_enabled: Res<BrushEnabled>,
+    theme: Res<Theme>,
+) {
+    if resized.read().last().is_none() {
+        return;
+    }
+    *canvas = CanvasRect::for_window(window.size());
+    **board_sprite = view.sprite_transform(&canvas, window.size());
+
+    let board = images.get_mut(&drawingboard.image).expect("Board not found!!");
+    let old_size = board.size_f32();
+    let new_size = canvas.0.size();
+    if old_size == new_size {
+        return;
+    }
+
+    let shift = (new_size - old_size) / 2.0;
+    for point in candidate_vectors.strokes.iter_mut().flatten().chain(overlay.points.iter_mut()) {
+        *point += shift;
+    }
+    for filter in candidate_vectors.filters.iter_mut().flatten() {
+        filter.shift(shift);
+    }
+
+    // the repaint below shows the whole drawing, there's nothing left to replay
+    replay.0 = None;
+    reset_board(new_size, board, &mut drawingboard.painted, &theme);
+    paint_strokes(board, &mut drawingboard.painted, &candidate_vectors, brush_enabled.0, &theme);
+}
+
+fn draw(
+    mut result_text: Single<&mut Text, With<ResultText>>,
+    mut drawingboard: ResMut<DrawingBoard>,
+    mut images: ResMut<Assets<Image>>,
+
+    canvas: Res<CanvasRect>,
+
+    mut candidate_vectors: ResMut<CandidateVectors>,
+    mut replay: ResMut<ActiveReplay>,
+    time: Res<Time>,
+    is_typing: Res<IsTyping>,
+    over_button: Res<OverAButton>,
+    mut overlay: ResMut<MatchOverlay>,
+
+    mut draw_state: ResMut<DrawState>,
+    brush_enabled: Res<BrushEnabled>,
+    brush: Res<BrushConfig>,
+    theme: Res<Theme>,
+) {
+    if is_typing.0 {
+        draw_state.0 = DrawMoment::Idle;
+        return;
+    }
+
+    if let DrawMoment::Began(mouse_pos, paused, pen) = draw_state.0 {
+        if over_button.0 { return }
+        result_text.0 = "".to_string();
+        let board = images.get_mut(&drawingboard.image).expect("Board not found!!");
+
+        // a replay would keep painting over the new stroke
+        replay.0 = None;
+        if !paused {
+            start_drawing(board, &mut drawingboard.painted, canvas.0.size(), &mut candidate_vectors, &mut overlay, &theme);
+        }
+        candidate_vectors.begin_stroke(&brush);
+
+        if let Some(point) = candidate_vectors.push(mouse_pos, time.elapsed_secs(), pen) {
+            fill_pixel(board, &mut drawingboard.painted, point, true, brush_enabled.0, pen.pressure, &theme);
+        }
+    } else if let DrawMoment::Drawing(mouse_pos, pen) = draw_state.0 {
+        if over_button.0 { return }
+        let board = images.get_mut(&drawingboard.image).expect("Board not found!!");
+        // taken from the stroke rather than kept around so it follows the
+        // drawing when the window is resized
+        let previous_pos = candidate_vectors.last_point().unwrap_or(mouse_pos);
+        if let Some(point) = candidate_vectors.push(mouse_pos, time.elapsed_secs(), pen) {
+            paint_segment(board, &mut drawingboard.painted, previous_pos, point, brush_enabled.0, pen.pressure, &theme);
+        }
+    }
+}
+
+// takes the last stroke off the drawing
+fn undo_stroke(
+    bindings: Res<InputBindings>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    buttons: Res<ButtonInput<MouseButton>>,
+    typing: Res<IsTyping>,
+    mut candidate_vectors: ResMut<CandidateVectors>,
+    mut overlay: ResMut<MatchOverlay>,
+    mut replay: ResMut<ActiveReplay>,
+    mut drawingboard: ResMut<DrawingBoard>,
+    mut images: ResMut<Assets<Image>>,
+    canvas: Res<CanvasRect>,
+    brush_enabled: Res<BrushEnabled>,
+    theme: Res<Theme>,
+) {
+    if typing.0 || !bindings.just_pressed(Action::Undo, &keyboard, &buttons) {
+        return;
+    }
+    if candidate_vectors.pop_stroke() {
+        // the match was for the drawing with that stroke still in it
+        overlay.points.clear();
+        replay.0 = None;
+        let board = images.get_mut(&drawingboard.image).expect("Board not found!!");
+        reset_board(canvas.0.size(), board, &mut drawingboard.painted, &theme);
+        paint_strokes(board, &mut drawingboard.painted, &candidate_vectors, brush_enabled.0, &theme);
+    }
+}
+
+// what a recognition updates, together so `recognize_drawing` stays under the
+// number of parameters a system can have
+#[derive(SystemParam)]
+struct RecognitionOutputs<'w> {
+    overlay: ResMut<'w, MatchOverlay>,
+    history: ResMut<'w, RecognitionHistory>,
+    images: ResMut<'w, Assets<Image>>,
+    scores: ResMut<'w, ClassScores>,
+    calibration: Res<'w, Calibration>,
+    recognized: EventWriter<'w, GestureRecognized>,
+    session: ResMut<'w, SessionLog>,
+    last_match: ResMut<'w, LastMatch>,
+    final_resampled_points: ResMut<'w, ResampledPoints>,
+    drawingboard: ResMut<'w, DrawingBoard>,
+    brush_enabled: Res<'w, BrushEnabled>,
+    beautify: Res<'w, Beautify>,
+    compare: ResMut<'w, CompareView>,
+    strings: Res<'w, UiStrings>,
+    theme: Res<'w, Theme>,
+}
+
+// recognizes the drawing once it's finished, with the right mouse button or 'Recognize'
+fn recognize_drawing(
+    mut draw_state: ResMut<DrawState>,
+    candidate_vectors: Res<CandidateVectors>,
+    mut outputs: RecognitionOutputs,
+    mut result_text: Single<&mut Text, With<ResultText>>,
+    mut library: ResMut<GestureLibrary>,
+    mut training: ResMut<ActiveTraining>,
+    config: Res<RecognitionConfig>,
+    compaction: Res<CompactionConfig>,
+    canvas: Res<CanvasRect>,
+    zones: Res<HotZones>,
+    #[cfg(feature = "gpu")] mut gpu: ResMut<gpu::GpuMatcher>,
+) {
+    // a drawing from an earlier frame that the GPU is done with
+    #[cfg(feature = "gpu")]
+    if let Some((pending, results)) = gpu.take_finished(&library, &config, SCORE_PANEL_CLASSES) {
+        report_recognition(
+            results,
+            pending.candidate,
+            pending.drawing,
+            latency_since(pending.start_time),
+            &mut outputs,
+            &mut result_text,
+            config.min_confidence,
+        );
+    }
+
+    if draw_state.0 != DrawMoment::Ended {
+        return;
+    }
+    draw_state.0 = DrawMoment::Idle;
+    if candidate_vectors.strokes.is_empty() || candidate_vectors.strokes[0].is_empty() {
+        return;
+    }
+    let start_time = Instant::now();
+
+    let candidate =
+        candidate_vectors.candidate(&config).on_canvas(canvas.0.size());
+    // the hot zone the drawing is centered in decides what it can be
+    let centroid = candidate.denormalize(Vec2::ZERO);
+    let candidate = candidate.restrict(zones.classes_at(centroid, canvas.0.size()));
+
+    // while training, drawings become templates instead of being recognized
+    if let Some(active) = training.0.as_mut() {
+        let template = Template::from_candidate(&candidate, "training");
+        library.add_template(&active.name, template);
+        library.limit_templates(&active.name, &compaction, &config);
+        active.add_sample(candidate.points.clone());
+
+        result_text.0 = if active.is_done() {
+            let (mean, max) = active.spread(&config);
+            let done = fill(
+                &outputs.strings.trained,
+                &[("name", &active.name), ("mean", &format!("{:.3}", mean)), ("max", &format!("{:.3}", max))],
+            );
+            training.0 = None;
+            done
+        } else {
+            active.prompt(&outputs.strings)
+        };
+        outputs.session.skip_drawing();
+        outputs.last_match.0 = None;
+        outputs.final_resampled_points.0 = candidate;
+        return;
+    }
+
+    // big libraries are matched on the GPU, the result comes back a few frames later
+    #[cfg(feature = "gpu")]
+    if gpu.handles(&candidate, &config) {
+        gpu.request(gpu::PendingMatch {
+            candidate,
+            drawing: candidate_vectors.clone(),
+            start_time,
+        });
+        return;
+    }
+
+    let results = recognize_top(&library, &candidate, &config, SCORE_PANEL_CLASSES);
+    report_recognition(
+        results,
+        candidate,
+        candidate_vectors.clone(),
+        latency_since(start_time),
+        &mut outputs,
+        &mut result_text,
+        config.min_confidence,
+    );
+}
+
+fn latency_since(start_time: Instant) -> f64 {
+    start_time.elapsed().as_secs_f64() * 1000.0
+}
+
+// shows the best match (or that there wasn't one) and how close the runners-up
+// came, and adds the drawing to the history (and the session log)
+fn report_recognition(
+    results: Vec<Match>,
+    candidate: Candidate,
+    drawing: CandidateVectors,
+    latency_ms: f64,
+    outputs: &mut RecognitionOutputs,
+    result_text: &mut Text,
+    min_confidence: f32,
+) {
+    let scored: Vec<(Match, f32)> = results
+        .into_iter()
+        .map(|result| {
+            let confidence = outputs.calibration.confidence(&result);
+            (result, confidence)
+        })
+        .collect();
+    outputs.scores.0 = scored
+        .iter()
+        .map(|(result, confidence)| {
+            let name = fill(&outputs.strings.gesture_in_set, &[("name", &result.name), ("set", &result.set)]);
+            (name, *confidence)
+        })
+        .collect();
+
+    // the chart still shows how close a rejected one came
+    let best = scored.first().filter(|(_, confidence)| *confidence >= min_confidence);
+    outputs.session.record_drawing(&drawing, &scored, best, latency_ms);
+    outputs.last_match.remember(best.map(|(result, _)| result));
+    let (name, confidence) = match best {
+        Some((result, confidence)) => {
+            outputs.overlay.points = result.cloud.iter().map(|p| candidate.denormalize(*p)).collect();
+            outputs.overlay.stroke_count = result.stroke_count;
+            let name = fill(&outputs.strings.gesture_in_set, &[("name", &result.name), ("set", &result.set)]);
+            outputs.compare.set_template(name.clone(), result.cloud.clone());
+            if outputs.beautify.0 {
+                let fit = candidate.fit(&result.cloud);
+                let strokes: Vec<Vec<Vec2>> = split_strokes(&result.cloud, result.stroke_count)
+                    .into_iter()
+                    .map(|stroke| stroke.into_iter().map(&fit).collect())
+                    .collect();
+                let board = outputs.images.get_mut(&outputs.drawingboard.image).expect("Board not found!!");
+                reset_board(board.size_f32(), board, &mut outputs.drawingboard.painted, &outputs.theme);
+                paint_lines(board, &mut outputs.drawingboard.painted, &strokes, outputs.brush_enabled.0, &outputs.theme);
+            }
+            outputs.recognized.send(GestureRecognized {
+                canvas: 0,
+                set: result.set.clone(),
+                name: result.name.clone(),
+                confidence: *confidence,
+                latency_ms,
+                truncated: result.truncated,
+            });
+            (name, Some(*confidence))
+        }
+        None => {
+            result_text.0 = fill(&outputs.strings.not_recognized, &[("latency", &format!("{:.3}", latency_ms))]);
+            if scored.first().is_some_and(|(result, _)| result.truncated) {
+                result_text.0 = format!("{}\n{}", result_text.0, outputs.strings.out_of_time);
+            }
+            outputs.compare.set_template(String::new(), Vec::new());
+            (outputs.strings.nothing_matched.clone(), None)
+        }
+    };
+
+    let thumbnail = outputs.images.add(history::thumbnail(&candidate.points, &outputs.theme));
+    outputs.history.push(drawing, name, confidence, latency_ms, thumbnail);
+    outputs.final_resampled_points.0 = candidate;
+}
+
+fn show_recognition(
+    mut recognized: EventReader<GestureRecognized>,
+    mut result_text: Single<&mut Text, With<ResultText>>,
+    strings: Res<UiStrings>,
+) {
+    // the extra canvases show theirs in their window's title
+    for event in recognized.read().filter(|event| event.canvas == 0) {
+        result_text.0 = fill(
+            &strings.recognized,
+            &[
+                ("name", &event.name),
+                ("set", &event.set),
+                ("confidence", &format!("{:.0}", event.confidence * 100.0)),
+                ("latency", &format!("{:.3}", event.latency_ms)),
+            ],
+        );
+        // under the result of a recognition that ran out of its latency budget
+        if event.truncated {
+            result_text.0 = format!("{}\n{}", result_text.0, strings.out_of_time);
         }
     }
+}
 
+// 'Replay' redraws the last drawing at the speed it was drawn, shift-clicking
+// it draws the template it was recognized as instead
+fn start_replay(
+    mut over_button: ResMut<OverAButton>,
+    mut interaction_query: Query<
+        (&Interaction, &mut BorderColor),
+        (Changed<Interaction>, With<ReplayButton>),
+    >,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    candidate_vectors: Res<CandidateVectors>,
+    overlay: Res<MatchOverlay>,
+    mut replay: ResMut<ActiveReplay>,
+    mut drawingboard: ResMut<DrawingBoard>,
+    mut images: ResMut<Assets<Image>>,
+    canvas: Res<CanvasRect>,
+    mut result_text: Single<&mut Text, With<ResultText>>,
+    strings: Res<UiStrings>,
+    theme: Res<Theme>,
+) {
     for (interaction, mut border_color) in &mut interaction_query {
         match *interaction {
             Interaction::Pressed => {
                 over_button.0 = true;
-                border_color.0 = bevy::color::palettes::css::LIGHT_GREEN.into();
+                border_color.0 = theme.button_pressed;
+
+                let next = if keyboard.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]) {
+                    (!overlay.points.is_empty())
+                        .then(|| Replay::constant_speed(split_strokes(&overlay.points, overlay.stroke_count)))
+                } else {
+                    candidate_vectors.strokes.iter().any(|stroke| !stroke.is_empty()).then(|| {
+                        Replay::recorded(
+                            candidate_vectors.strokes.clone(),
+                            candidate_vectors.times.clone(),
+                            candidate_vectors.pressures(),
+                        )
+                    })
+                };
+                match next {
+                    Some(next) => {
+                        let board = images.get_mut(&drawingboard.image).expect("Board not found!!");
+                        reset_board(canvas.0.size(), board, &mut drawingboard.painted, &theme);
+                        replay.0 = Some(next);
+                    }
+                    None => result_text.0 = strings.nothing_to_replay.clone(),
+                }
+            }
+            _ => {
+                over_button.0 = false;
+                border_color.0 = theme.button_border;
+            }
+        }
+    }
+}
+
+// saves the board (and the strokes as an SVG) into the working directory
+#[cfg(not(target_arch = "wasm32"))]
+fn export_image(
+    mut over_button: ResMut<OverAButton>,
+    mut interaction_query: Query<
+        (&Interaction, &mut BorderColor),
+        (Changed<Interaction>, With<ExportButton>),
+    >,
+    drawingboard: Res<DrawingBoard>,
+    images: Res<Assets<Image>>,
+    candidate_vectors: Res<CandidateVectors>,
+    mut result_text: Single<&mut Text, With<ResultText>>,
+    strings: Res<UiStrings>,
+    theme: Res<Theme>,
+) {
+    for (interaction, mut border_color) in &mut interaction_query {
+        match *interaction {
+            Interaction::Pressed => {
+                over_button.0 = true;
+                border_color.0 = theme.button_pressed;
+
+                let board = images.get(&drawingboard.image).expect("Board not found!!");
+                result_text.0 = match export::export(board, &candidate_vectors.strokes, &theme, ".") {
+                    Ok(paths) => {
+                        let names: Vec<String> = paths.iter().map(|path| path.display().to_string()).collect();
+                        fill(&strings.exported, &[("files", &names.join(", "))])
+                    }
+                    Err(e) => fill(&strings.export_failed, &[("error", &e)]),
+                };
+            }
+            _ => {
+                over_button.0 = false;
+                border_color.0 = theme.button_border;
+            }
+        }
+    }
+}
+
+// merges near-identical templates in the active sets, shift-click averages
+// them instead of keeping one
+fn compact_templates(
+    mut over_button: ResMut<OverAButton>,
+    mut interaction_query: Query<
+        (&Interaction, &mut BorderColor),
+        (Changed<Interaction>, With<CompactButton>),
+    >,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut library: ResMut<GestureLibrary>,
+    compaction: Res<CompactionConfig>,
+    config: Res<RecognitionConfig>,
+    mut result_text: Single<&mut Text, With<ResultText>>,
+    strings: Res<UiStrings>,
+    theme: Res<Theme>,
+) {
+    for (interaction, mut border_color) in &mut interaction_query {
+        match *interaction {
+            Interaction::Pressed => {
+                over_button.0 = true;
+                border_color.0 = theme.button_pressed;
+
+                let average = keyboard.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+                let removed = library.compact(&compaction, average, &config);
+                result_text.0 = match removed {
+                    0 => strings.nothing_to_compact.clone(),
+                    1 => strings.merged_one.clone(),
+                    removed => fill(&strings.merged, &[("count", &removed)]),
+                };
+            }
+            _ => {
+                over_button.0 = false;
+                border_color.0 = theme.button_border;
+            }
+        }
+    }
+}
+
+fn play_replay(
+    time: Res<Time>,
+    mut replay: ResMut<ActiveReplay>,
+    mut drawingboard: ResMut<DrawingBoard>,
+    mut images: ResMut<Assets<Image>>,
+    brush_enabled: Res<BrushEnabled>,
+    theme: Res<Theme>,
+) {
+    let Some(active) = replay.0.as_mut() else {
+        return;
+    };
+
+    let due = active.advance(time.delta_secs());
+    if !due.is_empty() {
+        let board = images.get_mut(&drawingboard.image).expect("Board not found!!");
+        for (previous, point, pressure) in due {
+            match previous {
+                Some(previous) => paint_segment(board, &mut drawingboard.painted, previous, point, brush_enabled.0, pressure, &theme),
+                None => fill_pixel(board, &mut drawingboard.painted, point, true, brush_enabled.0, pressure, &theme),
+            }
+        }
+    }
+
+    if active.finished() {
+        replay.0 = None;
+    }
+}
+
+// brings an earlier drawing back onto the board and recognizes it again, so it
+// can be added under another name or checked against the templates as they are now
+fn select_history_entry(
+    mut over_button: ResMut<OverAButton>,
+    mut interaction_query: Query<
+        (&Interaction, &HistoryEntryButton, &mut BorderColor),
+        Changed<Interaction>,
+    >,
+    history: Res<RecognitionHistory>,
+    mut candidate_vectors: ResMut<CandidateVectors>,
+    mut draw_state: ResMut<DrawState>,
+    mut replay: ResMut<ActiveReplay>,
+    mut drawingboard: ResMut<DrawingBoard>,
+    mut images: ResMut<Assets<Image>>,
+    canvas: Res<CanvasRect>,
+    brush_enabled: Res<BrushEnabled>,
+    theme: Res<Theme>,
+) {
+    for (interaction, entry_button, mut border_color) in &mut interaction_query {
+        match *interaction {
+            Interaction::Pressed => {
+                over_button.0 = true;
+                border_color.0 = theme.button_pressed;
+
+                let Some(entry) = history.get(entry_button.0) else {
+                    continue;
+                };
+                *candidate_vectors = entry.drawing.clone();
+                replay.0 = None;
+                let board = images.get_mut(&drawingboard.image).expect("Board not found!!");
+                reset_board(canvas.0.size(), board, &mut drawingboard.painted, &theme);
+                paint_strokes(board, &mut drawingboard.painted, &candidate_vectors, brush_enabled.0, &theme);
                 draw_state.0 = DrawMoment::Ended;
             }
-            _ => {over_button.0 = false; border_color.0 = Color::WHITE;}
+            _ => {
+                over_button.0 = false;
+                border_color.0 = theme.button_border;
+            }
+        }
+    }
+}
+
+fn update_history_panel(
+    mut commands: Commands,
+    panel: Single<Entity, With<HistoryPanel>>,
+    history: Res<RecognitionHistory>,
+    theme: Res<Theme>,
+    strings: Res<UiStrings>,
+) {
+    commands.entity(*panel).despawn_descendants();
+    commands.entity(*panel).with_children(|parent| {
+        for entry in history.entries() {
+            let latency = format!("{:.2}", entry.latency_ms);
+            let label = match entry.confidence {
+                Some(confidence) => fill(
+                    &strings.history_entry,
+                    &[
+                        ("name", &entry.name),
+                        ("confidence", &format!("{:.0}", confidence * 100.0)),
+                        ("latency", &latency),
+                    ],
+                ),
+                None => fill(&strings.history_entry_not_recognized, &[("name", &entry.name), ("latency", &latency)]),
+            };
+            parent
+                .spawn((
+                    Button,
+                    Node {
+                        width: Val::Px(UI_PANEL_WIDTH - 10.0),
+                        border: UiRect::all(Val::Px(2.0)),
+                        padding: UiRect::all(Val::Px(3.0)),
+                        margin: UiRect::bottom(Val::Px(4.0)),
+                        column_gap: Val::Px(6.0),
+                        align_items: AlignItems::Center,
+                        flex_shrink: 0.0,
+                        ..default()
+                    },
+                    BorderColor(theme.button_border),
+                    BackgroundColor(theme.button_background),
+                    HistoryEntryButton(entry.id),
+                ))
+                .with_children(|parent| {
+                    parent.spawn((
+                        ImageNode::new(entry.thumbnail.clone()),
+                        Node {
+                            width: Val::Px(THUMBNAIL_SIZE as f32),
+                            height: Val::Px(THUMBNAIL_SIZE as f32),
+                            flex_shrink: 0.0,
+                            ..default()
+                        },
+                    ));
+                    parent.spawn((
+                        Text::new(label),
+                        TextFont {
+                            font_size: 13.0,
+                            ..default()
+                        },
+                        TextColor(theme.button_text),
+                    ));
+                });
+        }
+    });
+}
+
+// a bar per class of the last recognition, as long as it's confident in it
+fn update_score_panel(
+    mut commands: Commands,
+    panel: Single<Entity, With<ScorePanel>>,
+    scores: Res<ClassScores>,
+    theme: Res<Theme>,
+    strings: Res<UiStrings>,
+) {
+    commands.entity(*panel).despawn_descendants();
+    commands.entity(*panel).with_children(|parent| {
+        for (i, (name, confidence)) in scores.0.iter().enumerate() {
+            // the winner stands out from the runners-up
+            let color = if i == 0 { theme.text } else { theme.text_dim };
+            parent.spawn((
+                Text::new(fill(
+                    &strings.score,
+                    &[("name", name), ("confidence", &format!("{:.0}", confidence * 100.0))],
+                )),
+                TextFont {
+                    font_size: 13.0,
+                    ..default()
+                },
+                TextColor(theme.button_text),
+            ));
+            parent.spawn((
+                Node {
+                    width: Val::Percent(confidence * 100.0),
+                    height: Val::Px(SCORE_BAR_HEIGHT),
+                    margin: UiRect::bottom(Val::Px(4.0)),
+                    ..default()
+                },
+                BackgroundColor(color),
+            ));
         }
+    });
+}
+
+fn update_latency_overlay(
+    history: Res<RecognitionHistory>,
+    mut overlay: Single<&mut Text, With<LatencyOverlay>>,
+    strings: Res<UiStrings>,
+) {
+    if let Some(stats) = history.latency_stats() {
+        let ms = |ms: f64| format!("{:.3}", ms);
+        overlay.0 = fill(
+            &strings.latency,
+            &[
+                ("count", &stats.count),
+                ("min", &ms(stats.min)),
+                ("mean", &ms(stats.mean)),
+                ("p95", &ms(stats.p95)),
+            ],
+        );
     }
+}
 
-    if buttons.just_released(MouseButton::Right) {
-        draw_state.0 = DrawMoment::Ended
+// the scroll wheel zooms in and out around the cursor, dragging with the
+// middle mouse button moves the board around
+fn zoom_and_pan(
+    mut wheel: EventReader<MouseWheel>,
+    buttons: Res<ButtonInput<MouseButton>>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    bindings: Res<InputBindings>,
+    mouse_move_delta: Res<AccumulatedMouseMotion>,
+    window: Single<&Window, With<PrimaryWindow>>,
+    canvas: Res<CanvasRect>,
+    mut view: ResMut<BoardView>,
+    mut board_sprite: Single<&mut Transform, With<BoardSprite>>,
+) {
+    let cursor = window.cursor_position().filter(|cursor| canvas.0.contains(*cursor));
+    let mut changed = false;
+    for event in wheel.read() {
+        let Some(cursor) = cursor else {
+            continue;
+        };
+        let notches = match event.unit {
+            MouseScrollUnit::Line => event.y,
+            // touchpads scroll in pixels, a line's worth is a notch
+            MouseScrollUnit::Pixel => event.y / HISTORY_SCROLL_LINE_HEIGHT,
+        };
+        view.zoom_at(&canvas, cursor, ZOOM_STEP.powf(notches));
+        changed = true;
+    }
+    if bindings.pressed(Action::Pan, &keyboard, &buttons) && mouse_move_delta.delta != Vec2::ZERO {
+        view.pan += mouse_move_delta.delta;
+        changed = true;
+    }
+    if changed {
+        **board_sprite = view.sprite_transform(&canvas, window.size());
     }
 }
 
-fn fill_pixel(board: &mut Image, vec: Vec2, first_pixel: bool, brush_enabled: bool) {
-    let thickness = if first_pixel {
-        BRUSH_THICKNESS * 2
-    } else {
-        BRUSH_THICKNESS
-    };
-    if brush_enabled {
-        for theta in 0..=360 {
-            for delta_r in 0..=thickness {
-                let x = vec.x + (delta_r as f32) * ops::cos((theta as f32).to_radians());
-                let y = vec.y + (delta_r as f32) * ops::sin((theta as f32).to_radians());
-                board
-                    .set_color_at(x as u32, y as u32, BRUSH_COLOR)
-                    .unwrap_or(()); // most likely the error would be an out_of_bounds so it i think im okay to ignore
-            }
+fn scroll_history(
+    mut wheel: EventReader<MouseWheel>,
+    window: Single<&Window, With<PrimaryWindow>>,
+    mut panel: Single<&mut ScrollPosition, With<HistoryPanel>>,
+) {
+    let over_panel = window.cursor_position().is_some_and(|cursor| {
+        cursor.x >= window.width() - UI_PANEL_WIDTH
+            && (HISTORY_PANEL_TOP..HISTORY_PANEL_TOP + HISTORY_PANEL_HEIGHT).contains(&cursor.y)
+    });
+    for event in wheel.read() {
+        if !over_panel {
+            continue;
         }
-    } else {
-        board
-            .set_color_at(vec.x as u32, vec.y as u32, BRUSH_COLOR)
-            .unwrap_or(()); // most likely the error would be an out_of_bounds so it i think im okay to ignore
+        let lines = match event.unit {
+            MouseScrollUnit::Line => event.y * HISTORY_SCROLL_LINE_HEIGHT,
+            MouseScrollUnit::Pixel => event.y,
+        };
+        // clamped to the content by the UI layout
+        panel.offset_y = (panel.offset_y - lines).max(0.0);
     }
 }
 
-fn draw(
-    mut result_text: Single<&mut Text, With<ResultText>>,
-    drawingboard: Res<DrawingBoard>,
-    mut images: ResMut<Assets<Image>>,
-
-    window: Single<&Window>,
-
-    mut previous_pos: Local<Vec2>,
-    mut stroke_index: Local<usize>,
-    mut candidate_vectors: Local<Vec<Vec<Vec2>>>,
-    mut total_length: Local<f32>,
-    is_typing: Res<IsTyping>,
-    over_button: Res<OverAButton>,
-    mut final_resampled_points: ResMut<ResampledPoints>,
-
-    mut draw_state: ResMut<DrawState>,
-    brush_enabled: Res<BrushEnabled>,
-
-    templates: Res<StrokeTemplates>,
+fn draw_match_overlay(
+    mut gizmos: Gizmos,
+    overlay: Res<MatchOverlay>,
+    canvas: Res<CanvasRect>,
+    view: Res<BoardView>,
+    window: Single<&Window, With<PrimaryWindow>>,
+    beautify: Res<Beautify>,
+    compare: Res<CompareView>,
+    theme: Res<Theme>,
 ) {
-    if is_typing.0 {
-        draw_state.0 = DrawMoment::Idle;
+    // the template is already drawn, or the board isn't there
+    if beautify.0 || compare.enabled {
         return;
     }
-
-    if let DrawMoment::Began(mouse_pos, paused) = draw_state.0 {
-        if over_button.0 { return }
-        result_text.0 = "".to_string();
-        let board = images.get_mut(&drawingboard.0).expect("Board not found!!");
-
-        if !paused {
-            candidate_vectors.clear();
-            candidate_vectors.push(vec![]);
-            *total_length = 0.0;
-            reset_board(window.size(), board, true);
-        } else {
-            *stroke_index += 1;
-            candidate_vectors.push(vec![]);
-        }
-
-        fill_pixel(board, mouse_pos, true, brush_enabled.0);
-        *previous_pos = mouse_pos;
-        candidate_vectors[*stroke_index].push(mouse_pos);
-    } else if draw_state.0 == DrawMoment::Ended {
-        if candidate_vectors.is_empty() || candidate_vectors[0].is_empty() { draw_state.0 = DrawMoment::Idle; return;}
-        let start_time = Utc::now();
-
-        let mut resampled_points = resample(&candidate_vectors, *total_length);
-        scale_and_translate(&mut resampled_points);
-        let name = greedy_5(templates, &resampled_points, 0.5);
-
-        let end_time = Utc::now();
-        let elapsed_time = end_time.signed_duration_since(start_time);
-        result_text.0 = format!(
-            "{}\n{}.{} milliseconds",
-            name,
-            elapsed_time.num_milliseconds(),
-            elapsed_time.num_microseconds().get_or_insert_default()
-        );
-
-        final_resampled_points.0 = resampled_points;
-        draw_state.0 = DrawMoment::Idle;
-        *stroke_index = 0;
-    } else if let DrawMoment::Drawing(mouse_pos) = draw_state.0 {
-        if over_button.0 { return }
-        let board = images.get_mut(&drawingboard.0).expect("Board not found!!");
-        let delta = previous_pos.distance(mouse_pos);
-
-        if delta > 6.0 {
-            let num_steps = (delta / BRUSH_THICKNESS as f32).ceil() as u32;
-            for step in 0..=num_steps {
-                let alpha = step as f32 / num_steps as f32;
-                let dv = previous_pos.lerp(mouse_pos, alpha);
-                fill_pixel(board, dv, false, brush_enabled.0);
-            }
-        } else {
-            fill_pixel(board, mouse_pos, false, brush_enabled.0);
-        }
-
-        candidate_vectors[*stroke_index].push(mouse_pos);
-        *previous_pos = mouse_pos;
-        *total_length += delta;
+    for point in overlay.points.iter() {
+        let world = view.to_world(&canvas, window.size(), *point);
+        gizmos.circle_2d(Isometry2d::from_translation(world), 4.0, theme.overlay);
     }
 }
 
-fn spawn(window: Single<&Window>, mut commands: Commands, mut images: ResMut<Assets<Image>>) {
+fn spawn(
+    window: Single<&Window, With<PrimaryWindow>>,
+    mut commands: Commands,
+    mut images: ResMut<Assets<Image>>,
+    library: Res<GestureLibrary>,
+    config: Res<RecognitionConfig>,
+    strings: Res<UiStrings>,
+    theme: Res<Theme>,
+) {
     commands.spawn(Camera2d);
     commands.spawn((
         Text::new(""),
@@ -556,26 +2180,75 @@ fn spawn(window: Single<&Window>, mut commands: Commands, mut images: ResMut<Ass
             font_size: 20.0,
             ..default()
         },
-        TextColor(Color::linear_rgb(0.0, 255.0, 0.0)),
+        TextColor(theme.text),
         Node {
             position_type: PositionType::Absolute,
             right: Val::Px(0.0),
+            max_width: Val::Px(UI_PANEL_WIDTH),
             ..default()
         },
         ResultText,
+        announced(),
+    ));
+
+    commands.spawn((
+        Node {
+            position_type: PositionType::Absolute,
+            right: Val::Px(0.0),
+            top: Val::Px(HISTORY_PANEL_TOP),
+            width: Val::Px(UI_PANEL_WIDTH),
+            height: Val::Px(HISTORY_PANEL_HEIGHT),
+            flex_direction: FlexDirection::Column,
+            overflow: Overflow::scroll_y(),
+            ..default()
+        },
+        ScrollPosition::default(),
+        HistoryPanel,
+    ));
+
+    commands.spawn((
+        Node {
+            position_type: PositionType::Absolute,
+            left: Val::Px(0.0),
+            top: Val::Px(SCORE_PANEL_TOP),
+            width: Val::Px(UI_PANEL_WIDTH - 10.0),
+            flex_direction: FlexDirection::Column,
+            padding: UiRect::all(Val::Px(5.0)),
+            ..default()
+        },
+        ScorePanel,
+    ));
+
+    commands.spawn((
+        Text::new(""),
+        TextFont {
+            font_size: 12.0,
+            ..default()
+        },
+        TextColor(theme.text),
+        Node {
+            position_type: PositionType::Absolute,
+            left: Val::Px(0.0),
+            top: Val::Px(LATENCY_OVERLAY_TOP),
+            max_width: Val::Px(UI_PANEL_WIDTH),
+            ..default()
+        },
+        LatencyOverlay,
     ));
 
     commands.spawn((
-        Text::new("Misrecognized? 'Add' stroke as a gesture\n\n\n'Toggle Brush' for performance"),
+        Text::new(strings.hint.clone()),
         TextFont {
-            font_size: 20.0,
+            font_size: 16.0,
             ..default()
         },
-        TextColor(Color::linear_rgb(0.0, 255.0, 0.0)),
+        TextColor(theme.text),
+        // above the buttons of the left panel
         Node {
             position_type: PositionType::Absolute,
-            bottom: Val::Px(30.0),
-            left: Val::Px(150.0),
+            bottom: Val::Px(400.0),
+            left: Val::Px(0.0),
+            width: Val::Px(UI_PANEL_WIDTH),
             ..default()
         },
     ));
@@ -600,18 +2273,18 @@ fn spawn(window: Single<&Window>, mut commands: Commands, mut images: ResMut<Ass
                         align_items: AlignItems::Center,
                         ..default()
                     },
-                    BorderColor(Color::WHITE),
+                    BorderColor(theme.button_border),
                     BorderRadius::MAX,
-                    BackgroundColor(Color::srgb(0.15, 0.15, 0.15)),
+                    BackgroundColor(theme.button_background),
                     AddGestureButton,
                 ))
                 .with_child((
-                    Text::new("Add"),
+                    Text::new(strings.add.clone()),
                     TextFont {
                         font_size: 17.0,
                         ..default()
                     },
-                    TextColor(Color::srgb(0.9, 0.9, 0.9)),
+                    TextColor(theme.button_text),
                 ));
         });
 
@@ -634,18 +2307,19 @@ fn spawn(window: Single<&Window>, mut commands: Commands, mut images: ResMut<Ass
                         align_items: AlignItems::Center,
                         ..default()
                     },
-                    BorderColor(Color::WHITE),
+                    BorderColor(theme.button_border),
                     BorderRadius::MAX,
-                    BackgroundColor(Color::srgb(0.15, 0.15, 0.15)),
+                    BackgroundColor(theme.button_background),
                     ToggleBrushButton,
+                    AccessibleName(strings.toggle_brush.clone()),
                 ))
                 .with_child((
-                    Text::new("Toggle Brush"),
+                    Text::new(strings.toggle_brush.clone()),
                     TextFont {
                         font_size: 17.0,
                         ..default()
                     },
-                    TextColor(Color::srgb(0.9, 0.9, 0.9)),
+                    TextColor(theme.button_text),
                     ToggleBrushButton
                 ));
         });
@@ -670,39 +2344,267 @@ fn spawn(window: Single<&Window>, mut commands: Commands, mut images: ResMut<Ass
                         align_items: AlignItems::Center,
                         ..default()
                     },
-                    BorderColor(Color::WHITE),
+                    BorderColor(theme.button_border),
                     BorderRadius::MAX,
-                    BackgroundColor(Color::srgb(0.15, 0.15, 0.15)),
+                    BackgroundColor(theme.button_background),
                     EndDrawingButton,
                 ))
                 .with_child((
-                    Text::new("Recognize"),
+                    Text::new(strings.recognize.clone()),
+                    TextFont {
+                        font_size: 17.0,
+                        ..default()
+                    },
+                    TextColor(theme.button_text),
+                ));
+        });
+
+    commands
+        .spawn(Node {
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            align_items: AlignItems::End,
+            justify_content: JustifyContent::End,
+            bottom: Val::Px(80.0),
+            ..default()
+        })
+        .with_children(|parent| {
+            parent
+                .spawn((
+                    Button,
+                    Node {
+                        width: Val::Px(140.0),
+                        height: Val::Px(65.0),
+                        border: UiRect::all(Val::Px(3.0)),
+                        justify_content: JustifyContent::Center,
+                        align_items: AlignItems::Center,
+                        ..default()
+                    },
+                    BorderColor(theme.button_border),
+                    BorderRadius::MAX,
+                    BackgroundColor(theme.button_background),
+                    SwitchSetButton,
+                    AccessibleName(strings.gesture_set.clone()),
+                ))
+                .with_child((
+                    Text::new(active_sets_label(&library, &strings)),
+                    TextFont {
+                        font_size: 17.0,
+                        ..default()
+                    },
+                    TextColor(theme.button_text),
+                    SwitchSetButton,
+                ));
+        });
+
+    commands
+        .spawn(Node {
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            align_items: AlignItems::End,
+            bottom: Val::Px(160.0),
+            ..default()
+        })
+        .with_children(|parent| {
+            parent
+                .spawn((
+                    Button,
+                    Node {
+                        width: Val::Px(140.0),
+                        height: Val::Px(65.0),
+                        border: UiRect::all(Val::Px(3.0)),
+                        justify_content: JustifyContent::Center,
+                        align_items: AlignItems::Center,
+                        ..default()
+                    },
+                    BorderColor(theme.button_border),
+                    BorderRadius::MAX,
+                    BackgroundColor(theme.button_background),
+                    ResolutionButton,
+                    AccessibleName(strings.resolution.clone()),
+                ))
+                .with_child((
+                    Text::new(resolution_label(&config, &strings)),
+                    TextFont {
+                        font_size: 17.0,
+                        ..default()
+                    },
+                    TextColor(theme.button_text),
+                    ResolutionButton,
+                ));
+        });
+
+    commands
+        .spawn(Node {
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            align_items: AlignItems::End,
+            justify_content: JustifyContent::End,
+            bottom: Val::Px(160.0),
+            ..default()
+        })
+        .with_children(|parent| {
+            parent
+                .spawn((
+                    Button,
+                    Node {
+                        width: Val::Px(140.0),
+                        height: Val::Px(65.0),
+                        border: UiRect::all(Val::Px(3.0)),
+                        justify_content: JustifyContent::Center,
+                        align_items: AlignItems::Center,
+                        ..default()
+                    },
+                    BorderColor(theme.button_border),
+                    BorderRadius::MAX,
+                    BackgroundColor(theme.button_background),
+                    ReplayButton,
+                ))
+                .with_child((
+                    Text::new(strings.replay.clone()),
+                    TextFont {
+                        font_size: 17.0,
+                        ..default()
+                    },
+                    TextColor(theme.button_text),
+                ));
+        });
+
+    commands
+        .spawn(Node {
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            align_items: AlignItems::End,
+            justify_content: JustifyContent::End,
+            bottom: Val::Px(240.0),
+            ..default()
+        })
+        .with_children(|parent| {
+            parent
+                .spawn((
+                    Button,
+                    Node {
+                        width: Val::Px(140.0),
+                        height: Val::Px(65.0),
+                        border: UiRect::all(Val::Px(3.0)),
+                        justify_content: JustifyContent::Center,
+                        align_items: AlignItems::Center,
+                        ..default()
+                    },
+                    BorderColor(theme.button_border),
+                    BorderRadius::MAX,
+                    BackgroundColor(theme.button_background),
+                    CompactButton,
+                ))
+                .with_child((
+                    Text::new(strings.compact.clone()),
+                    TextFont {
+                        font_size: 17.0,
+                        ..default()
+                    },
+                    TextColor(theme.button_text),
+                ));
+        });
+
+    // there's no directory to export to in the browser
+    #[cfg(not(target_arch = "wasm32"))]
+    commands
+        .spawn(Node {
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            align_items: AlignItems::End,
+            bottom: Val::Px(240.0),
+            ..default()
+        })
+        .with_children(|parent| {
+            parent
+                .spawn((
+                    Button,
+                    Node {
+                        width: Val::Px(140.0),
+                        height: Val::Px(65.0),
+                        border: UiRect::all(Val::Px(3.0)),
+                        justify_content: JustifyContent::Center,
+                        align_items: AlignItems::Center,
+                        ..default()
+                    },
+                    BorderColor(theme.button_border),
+                    BorderRadius::MAX,
+                    BackgroundColor(theme.button_background),
+                    ExportButton,
+                ))
+                .with_child((
+                    Text::new(strings.export.clone()),
                     TextFont {
                         font_size: 17.0,
                         ..default()
                     },
-                    TextColor(Color::srgb(0.9, 0.9, 0.9)),
+                    TextColor(theme.button_text),
                 ));
         });
 
+    commands
+        .spawn(Node {
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            align_items: AlignItems::End,
+            bottom: Val::Px(320.0),
+            ..default()
+        })
+        .with_children(|parent| {
+            parent
+                .spawn((
+                    Button,
+                    Node {
+                        width: Val::Px(140.0),
+                        height: Val::Px(65.0),
+                        border: UiRect::all(Val::Px(3.0)),
+                        justify_content: JustifyContent::Center,
+                        align_items: AlignItems::Center,
+                        ..default()
+                    },
+                    BorderColor(theme.button_border),
+                    BorderRadius::MAX,
+                    BackgroundColor(theme.button_background),
+                    TrainButton,
+                ))
+                .with_child((
+                    Text::new(strings.train.clone()),
+                    TextFont {
+                        font_size: 17.0,
+                        ..default()
+                    },
+                    TextColor(theme.button_text),
+                ));
+        });
+
+    let canvas = CanvasRect::for_window(window.size());
     let image = Image::new_fill(
         Extent3d {
-            width: window.size().x as u32,
-            height: window.size().y as u32,
+            width: canvas.0.width() as u32,
+            height: canvas.0.height() as u32,
             depth_or_array_layers: 1,
         },
         TextureDimension::D2,
-        &(BOARD_COLOR.to_srgba().to_u8_array()),
+        &(theme.board.to_srgba().to_u8_array()),
         TextureFormat::Rgba8UnormSrgb,
         RenderAssetUsages::MAIN_WORLD | RenderAssetUsages::RENDER_WORLD,
     );
 
     let handle = images.add(image);
-    commands.spawn(Sprite::from_image(handle.clone()));
-    commands.insert_resource(DrawingBoard(handle));
+    commands.spawn((
+        Sprite::from_image(handle.clone()),
+        Transform::from_translation(canvas.world_center(window.size()).extend(0.0)),
+        BoardSprite,
+    ));
+    commands.insert_resource(DrawingBoard {
+        image: handle,
+        painted: None,
+    });
+    commands.insert_resource(canvas);
 }
 
-fn setup_window(mut window: Single<&mut Window>) {
-    window.title = String::from("Stroke Recognizer");
+fn setup_window(mut window: Single<&mut Window, With<PrimaryWindow>>, strings: Res<UiStrings>) {
+    window.title = strings.window_title.clone();
     window.position = WindowPosition::Centered(MonitorSelection::Current);
 }
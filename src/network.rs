@@ -0,0 +1,317 @@
+use std::{
+    collections::HashSet,
+    io::{ErrorKind, Read, Write},
+    net::{TcpListener, TcpStream},
+};
+
+use bevy::{ecs::event::EventCursor, prelude::*};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    gesture_input::{CandidateComplete, StrokeBegan, StrokeEnded, StrokePoint},
+    CandidateVectors, CanvasRect, GestureRecognized, PenSample,
+};
+
+// a client that sends more than this between two frames, or doesn't read what
+// it's sent, is dropped
+const MAX_BUFFERED_BYTES: usize = 1 << 20;
+
+// remote stroke ids are the client's own, made unique among the clients and
+// apart from touch ids by the client's number and the top bit
+const REMOTE_STROKE: u64 = 1 << 63;
+
+#[derive(Serialize, Deserialize)]
+struct RemotePoint {
+    x: f32,
+    y: f32,
+}
+
+// what clients send, a line of JSON each. Positions are in pixels from the
+// top left corner of the main canvas, like the `gesture_input` events
+#[derive(Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum Incoming {
+    StrokeBegan {
+        stroke: u32,
+        x: f32,
+        y: f32,
+        #[serde(default)]
+        pressure: Option<f32>,
+    },
+    StrokePoint {
+        stroke: u32,
+        x: f32,
+        y: f32,
+        #[serde(default)]
+        pressure: Option<f32>,
+    },
+    StrokeEnded {
+        stroke: u32,
+    },
+    Recognize,
+}
+
+// what every client is sent, a line of JSON each
+#[derive(Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum Outgoing<'a> {
+    // a drawing on the main canvas going off to be recognized, from any client
+    // or from the app itself
+    Candidate {
+        strokes: Vec<Vec<RemotePoint>>,
+    },
+    // a recognition on any of the canvases, see `GestureRecognized`
+    Recognized {
+        canvas: usize,
+        set: &'a str,
+        name: &'a str,
+        confidence: f32,
+        latency_ms: f64,
+        // the matching ran out of its `--latency-budget`
+        truncated: bool,
+    },
+    // only to the client whose line it was
+    Error {
+        message: String,
+    },
+}
+
+struct Client {
+    stream: TcpStream,
+    number: u64,
+    received: Vec<u8>,
+    unsent: Vec<u8>,
+    // the client's strokes that haven't ended yet, ended for it if it leaves
+    open_strokes: HashSet<u32>,
+    closed: bool,
+}
+
+impl Client {
+    fn stroke_id(&self, stroke: u32) -> u64 {
+        REMOTE_STROKE | (self.number << 32) | stroke as u64
+    }
+
+    fn queue(&mut self, message: &Outgoing) {
+        let mut line = serde_json::to_vec(message).expect("messages are always valid JSON");
+        line.push(b'\n');
+        self.unsent.extend(line);
+    }
+
+    // whatever the socket takes without blocking, the rest waits for the next
+    // frame
+    fn flush(&mut self) {
+        while !self.unsent.is_empty() {
+            match self.stream.write(&self.unsent) {
+                Ok(0) => {
+                    self.closed = true;
+                    return;
+                }
+                Ok(n) => {
+                    self.unsent.drain(..n);
+                }
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(_) => {
+                    self.closed = true;
+                    return;
+                }
+            }
+        }
+        if self.unsent.len() > MAX_BUFFERED_BYTES {
+            self.closed = true;
+        }
+    }
+
+    // the complete lines that came in since the last frame
+    fn receive(&mut self) -> Vec<String> {
+        let mut chunk = [0; 4096];
+        loop {
+            match self.stream.read(&mut chunk) {
+                Ok(0) => {
+                    self.closed = true;
+                    break;
+                }
+                Ok(n) => {
+                    self.received.extend_from_slice(&chunk[..n]);
+                    // one that never sends a newline would grow it for as long as it keeps sending
+                    if self.received.len() > MAX_BUFFERED_BYTES {
+                        self.closed = true;
+                        break;
+                    }
+                }
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(_) => {
+                    self.closed = true;
+                    break;
+                }
+            }
+        }
+        let mut lines = Vec::new();
+        while let Some(end) = self.received.iter().position(|&byte| byte == b'\n') {
+            let line: Vec<u8> = self.received.drain(..=end).collect();
+            lines.push(String::from_utf8_lossy(&line).trim().to_string());
+        }
+        lines
+    }
+}
+
+// `--serve=<address>` (e.g. `--serve=127.0.0.1:7878`) listens for TCP clients
+// that are sent the drawings and recognitions as they happen and can draw on
+// the main canvas themselves. Everything is non-blocking and polled once a
+// frame, there's no thread to look after. Plain TCP only, browsers can't
+// connect since there's no WebSocket handshake
+#[derive(Resource)]
+pub struct NetworkServer {
+    listener: TcpListener,
+    clients: Vec<Client>,
+    next_number: u64,
+}
+
+impl NetworkServer {
+    pub fn bind(address: &str) -> Result<Self, String> {
+        let listener = TcpListener::bind(address).map_err(|e| format!("{}: {}", address, e))?;
+        listener.set_nonblocking(true).map_err(|e| format!("{}: {}", address, e))?;
+        Ok(Self {
+            listener,
+            clients: Vec::new(),
+            next_number: 0,
+        })
+    }
+
+    fn accept(&mut self) {
+        loop {
+            match self.listener.accept() {
+                Ok((stream, address)) => {
+                    if stream.set_nonblocking(true).is_err() {
+                        continue;
+                    }
+                    // the lines are small and should go out right away
+                    let _ = stream.set_nodelay(true);
+                    info!("Gesture client connected from {}", address);
+                    self.clients.push(Client {
+                        stream,
+                        number: self.next_number,
+                        received: Vec::new(),
+                        unsent: Vec::new(),
+                        open_strokes: HashSet::new(),
+                        closed: false,
+                    });
+                    // 31 bits of it go in the stroke ids
+                    self.next_number = (self.next_number + 1) & (u32::MAX >> 1) as u64;
+                }
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(e) => {
+                    warn!("Couldn't accept a gesture client: {}", e);
+                    break;
+                }
+            }
+        }
+    }
+
+    fn broadcast(&mut self, message: &Outgoing) {
+        for client in &mut self.clients {
+            client.queue(message);
+        }
+    }
+}
+
+// takes in the clients' strokes, sends out the main canvas's drawings as
+// they're recognized and the results from every canvas. Runs after the
+// recognition so the drawing is the one `CandidateComplete` is for
+pub fn serve_network(
+    mut server: ResMut<NetworkServer>,
+    // read and sent, an `EventReader` and `EventWriter` of the same event
+    // can't be in one system
+    mut complete: ResMut<Events<CandidateComplete>>,
+    mut completed: Local<EventCursor<CandidateComplete>>,
+    mut recognized: EventReader<GestureRecognized>,
+    candidate_vectors: Res<CandidateVectors>,
+    canvas: Res<CanvasRect>,
+    mut began: EventWriter<StrokeBegan>,
+    mut points: EventWriter<StrokePoint>,
+    mut ended: EventWriter<StrokeEnded>,
+) {
+    server.accept();
+    let drawing_complete = completed.read(&complete).count() > 0;
+
+    // far off positions would paint very long lines, they're kept on the canvas
+    let size = canvas.0.size();
+    let position = |x: f32, y: f32| Vec2::new(x, y).clamp(Vec2::ZERO, size);
+    let pen = |pressure: Option<f32>| PenSample {
+        pressure: pressure.unwrap_or(1.0),
+        tilt: None,
+    };
+    for client in &mut server.clients {
+        for line in client.receive() {
+            if line.is_empty() {
+                continue;
+            }
+            match serde_json::from_str(&line) {
+                Ok(Incoming::StrokeBegan { stroke, x, y, pressure }) => {
+                    client.open_strokes.insert(stroke);
+                    began.send(StrokeBegan {
+                        stroke: client.stroke_id(stroke),
+                        position: position(x, y),
+                        pen: pen(pressure),
+                    });
+                }
+                Ok(Incoming::StrokePoint { stroke, x, y, pressure }) => {
+                    points.send(StrokePoint {
+                        stroke: client.stroke_id(stroke),
+                        position: position(x, y),
+                        pen: pen(pressure),
+                    });
+                }
+                Ok(Incoming::StrokeEnded { stroke }) => {
+                    client.open_strokes.remove(&stroke);
+                    ended.send(StrokeEnded {
+                        stroke: client.stroke_id(stroke),
+                    });
+                }
+                Ok(Incoming::Recognize) => {
+                    complete.send(CandidateComplete);
+                }
+                Err(e) => client.queue(&Outgoing::Error {
+                    message: format!("{}: {}", line, e),
+                }),
+            }
+        }
+    }
+
+    // the completions sent above are read next frame, once the drawing
+    // they're for has been recognized
+    if drawing_complete && candidate_vectors.strokes.iter().any(|stroke| !stroke.is_empty()) {
+        let strokes = candidate_vectors
+            .strokes
+            .iter()
+            .map(|stroke| stroke.iter().map(|point| RemotePoint { x: point.x, y: point.y }).collect())
+            .collect();
+        server.broadcast(&Outgoing::Candidate { strokes });
+    }
+    for event in recognized.read() {
+        server.broadcast(&Outgoing::Recognized {
+            canvas: event.canvas,
+            set: &event.set,
+            name: &event.name,
+            confidence: event.confidence,
+            latency_ms: event.latency_ms,
+            truncated: event.truncated,
+        });
+    }
+
+    for client in &mut server.clients {
+        client.flush();
+        if client.closed {
+            for &stroke in &client.open_strokes {
+                ended.send(StrokeEnded {
+                    stroke: client.stroke_id(stroke),
+                });
+            }
+            if let Ok(address) = client.stream.peer_addr() {
+                info!("Gesture client {} disconnected", address);
+            }
+        }
+    }
+    server.clients.retain(|client| !client.closed);
+}
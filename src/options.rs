@@ -0,0 +1,414 @@
+use std::{fmt::Display, str::FromStr};
+
+use clap::Parser;
+use serde::{Deserialize, Deserializer};
+
+use crate::{
+    canvases::ExtraCanvases,
+    learning::LearningMode,
+    recognizer::{Matcher, WidthSource},
+    smoothing::OneEuroParams,
+    zones::HotZones,
+};
+
+// the startup options, from the command line and from the config file (see
+// `config_file`) with the option's name as the key. Everything is optional so
+// the two can be merged, the defaults are filled in where they're used. The
+// flags take `=false` so the command line can turn off what the file turns on
+#[derive(Parser, Deserialize, Default)]
+#[command(about = "Draw a gesture and have it recognized with the $P recognizer")]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Options {
+    /// Read the options from this file instead of stroke_recognizer.toml
+    #[arg(long)]
+    #[serde(skip)]
+    pub config: Option<String>,
+
+    /// Recognize against a directory of $1/$N/$P XML gestures
+    #[arg(long)]
+    pub dataset: Option<String>,
+    /// Points every drawing is resampled to
+    #[arg(long, value_parser = checked(more_than_one))]
+    pub points: Option<usize>,
+    /// How the points are compared with a template's
+    #[arg(long)]
+    pub matcher: Option<Matcher>,
+    /// Templates the Protractor pre-filter keeps, 0 turns it off
+    #[arg(long)]
+    pub top_k: Option<usize>,
+    /// Tells a small drawing from a big one
+    #[arg(long, value_parser = checked(not_negative))]
+    pub size_weight: Option<f32>,
+    /// Tells a clockwise drawing from a counter-clockwise one
+    #[arg(long, value_parser = checked(not_negative))]
+    pub direction_weight: Option<f32>,
+    /// Tells an emphatic stroke from a light one
+    #[arg(long, value_parser = checked(not_negative))]
+    pub width_weight: Option<f32>,
+    /// Where the width of a stroke comes from
+    #[arg(long)]
+    pub width_source: Option<WidthSource>,
+    /// What correcting a misrecognition does to the template it was matched with
+    #[arg(long)]
+    pub learning: Option<LearningMode>,
+    /// Matches that are less sure than this aren't recognized
+    #[arg(long, value_parser = checked(fraction))]
+    pub min_confidence: Option<f32>,
+    /// Milliseconds matching may take before it stops with the best match so far
+    #[arg(long, value_parser = checked::<f64>(positive))]
+    pub latency_budget: Option<f64>,
+    /// Type the digits and symbols written on the canvas
+    #[arg(long, num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    pub keypad: Option<bool>,
+    /// Keep a point on every corner a stroke turns at when resampling
+    #[arg(long, num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    pub corners: Option<bool>,
+    /// Filter out jitter, optionally with <min cutoff>,<beta>
+    #[arg(long, value_name = "MIN_CUTOFF,BETA", require_equals = true)]
+    #[serde(deserialize_with = "flag_or_value")]
+    pub smoothing: Option<Option<OneEuroParams>>,
+    /// How close two templates of a gesture are to be merged when compacting
+    #[arg(long, value_parser = checked(not_negative))]
+    pub merge_threshold: Option<f32>,
+    /// Templates a gesture is compacted down to
+    #[arg(long, value_parser = checked::<usize>(positive))]
+    pub max_templates: Option<usize>,
+    /// Parts of the canvas that only recognize some gestures: left,top,right,bottom:a,b;...
+    #[arg(long, value_parser = HotZones::parse)]
+    pub zones: Option<HotZones>,
+    /// Extra canvases and the gestures each recognizes: a,b;*;...
+    #[arg(long, value_parser = ExtraCanvases::parse)]
+    pub canvases: Option<ExtraCanvases>,
+    /// Listen for strokes sent over TCP on this address
+    #[cfg(not(target_arch = "wasm32"))]
+    #[arg(long)]
+    pub serve: Option<String>,
+    /// Augmented copies made of every template
+    #[arg(long)]
+    pub augment: Option<usize>,
+    /// Seed of the augmented copies
+    #[arg(long)]
+    pub augment_seed: Option<u64>,
+    /// Load the SVG templates from this directory instead of templates/
+    #[arg(long)]
+    pub template_dir: Option<String>,
+    /// Benchmark the recognizer on synthesized drawings and exit
+    #[arg(long, num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    pub bench: Option<bool>,
+    /// Synthesized drawings per template a benchmark recognizes
+    #[arg(long)]
+    pub samples: Option<usize>,
+    /// Seed of the benchmark's drawings
+    #[arg(long)]
+    pub seed: Option<u64>,
+    /// Samples per class and subject of a dataset the benchmark uses as templates
+    #[arg(long)]
+    pub train: Option<usize>,
+    /// Load the input bindings from this file instead of bindings.toml
+    #[arg(long)]
+    pub bindings: Option<String>,
+    /// Language of the UI, see the locales/ directory
+    #[arg(long)]
+    pub locale: Option<String>,
+    /// Start with the high contrast theme
+    #[arg(long, num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    pub high_contrast: Option<bool>,
+    /// Save the added gestures in this file instead of user_templates.toml
+    #[arg(long)]
+    pub templates: Option<String>,
+    /// Gesture sets to match against
+    #[arg(long, value_delimiter = ',')]
+    pub sets: Option<Vec<String>>,
+    /// Report the templates likely to be mistaken for another gesture's and exit
+    #[arg(long, num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    pub diagnose: Option<bool>,
+    /// Size of the window in logical pixels
+    #[arg(long, value_name = "WIDTHxHEIGHT", value_parser = checked(fits_the_panels))]
+    pub window: Option<WindowSize>,
+    /// Record the drawings from the start, optionally somewhere other than session.jsonl
+    #[arg(long, value_name = "FILE", require_equals = true)]
+    #[serde(deserialize_with = "flag_or_value")]
+    pub record: Option<Option<String>>,
+    /// Save and restore the session in this file instead of saved_session.json
+    #[arg(long)]
+    pub session: Option<String>,
+    /// Restore the saved session on startup
+    #[arg(long, num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    pub resume: Option<bool>,
+    /// Replay a recorded session without a window and check it's recognized the same
+    #[arg(long)]
+    pub script: Option<String>,
+    /// Straighten recognized lines and round off circles
+    #[arg(long, num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    pub beautify: Option<bool>,
+}
+
+impl Options {
+    // every option that's set here, and the rest from `other`
+    pub fn or(self, other: Self) -> Self {
+        Self {
+            config: self.config.or(other.config),
+            dataset: self.dataset.or(other.dataset),
+            points: self.points.or(other.points),
+            matcher: self.matcher.or(other.matcher),
+            top_k: self.top_k.or(other.top_k),
+            size_weight: self.size_weight.or(other.size_weight),
+            direction_weight: self.direction_weight.or(other.direction_weight),
+            width_weight: self.width_weight.or(other.width_weight),
+            width_source: self.width_source.or(other.width_source),
+            learning: self.learning.or(other.learning),
+            min_confidence: self.min_confidence.or(other.min_confidence),
+            latency_budget: self.latency_budget.or(other.latency_budget),
+            keypad: self.keypad.or(other.keypad),
+            corners: self.corners.or(other.corners),
+            smoothing: self.smoothing.or(other.smoothing),
+            merge_threshold: self.merge_threshold.or(other.merge_threshold),
+            max_templates: self.max_templates.or(other.max_templates),
+            zones: self.zones.or(other.zones),
+            canvases: self.canvases.or(other.canvases),
+            #[cfg(not(target_arch = "wasm32"))]
+            serve: self.serve.or(other.serve),
+            augment: self.augment.or(other.augment),
+            augment_seed: self.augment_seed.or(other.augment_seed),
+            template_dir: self.template_dir.or(other.template_dir),
+            bench: self.bench.or(other.bench),
+            samples: self.samples.or(other.samples),
+            seed: self.seed.or(other.seed),
+            train: self.train.or(other.train),
+            bindings: self.bindings.or(other.bindings),
+            locale: self.locale.or(other.locale),
+            high_contrast: self.high_contrast.or(other.high_contrast),
+            templates: self.templates.or(other.templates),
+            sets: self.sets.or(other.sets),
+            diagnose: self.diagnose.or(other.diagnose),
+            window: self.window.or(other.window),
+            record: self.record.or(other.record),
+            session: self.session.or(other.session),
+            resume: self.resume.or(other.resume),
+            script: self.script.or(other.script),
+            beautify: self.beautify.or(other.beautify),
+        }
+    }
+
+    // the value parsers only see the command line, this holds what was read
+    // from a file to the same rules
+    pub fn check(&self) -> Result<(), String> {
+        check("points", &self.points, more_than_one)?;
+        check("size-weight", &self.size_weight, not_negative)?;
+        check("direction-weight", &self.direction_weight, not_negative)?;
+        check("width-weight", &self.width_weight, not_negative)?;
+        check("min-confidence", &self.min_confidence, fraction)?;
+        check("latency-budget", &self.latency_budget, positive)?;
+        check("merge-threshold", &self.merge_threshold, not_negative)?;
+        check("max-templates", &self.max_templates, positive)?;
+        check("window", &self.window, fits_the_panels)
+    }
+}
+
+// `--window=1600x900`, `window = "1600x900"` in the config file
+#[derive(Clone, Copy, Deserialize)]
+#[serde(try_from = "String")]
+pub struct WindowSize {
+    pub width: f32,
+    pub height: f32,
+}
+
+impl FromStr for WindowSize {
+    type Err = String;
+
+    fn from_str(size: &str) -> Result<Self, String> {
+        match size.split_once('x').map(|(width, height)| (width.parse(), height.parse())) {
+            Some((Ok(width), Ok(height))) => Ok(Self { width, height }),
+            _ => Err(format!("{} isn't <width>x<height>", size)),
+        }
+    }
+}
+
+impl TryFrom<String> for WindowSize {
+    type Error = String;
+
+    fn try_from(size: String) -> Result<Self, String> {
+        size.parse()
+    }
+}
+
+// `--smoothing=1,0.007`, `smoothing = [1.0, 0.007]` in the config file
+impl FromStr for OneEuroParams {
+    type Err = String;
+
+    fn from_str(values: &str) -> Result<Self, String> {
+        match values.split_once(',').map(|(cutoff, beta)| (cutoff.parse(), beta.parse())) {
+            Some((Ok(min_cutoff), Ok(beta))) => [min_cutoff, beta].try_into(),
+            _ => Err(format!("{} isn't <min cutoff>,<beta>", values)),
+        }
+    }
+}
+
+impl TryFrom<[f32; 2]> for OneEuroParams {
+    type Error = String;
+
+    fn try_from([min_cutoff, beta]: [f32; 2]) -> Result<Self, String> {
+        if min_cutoff > 0.0 && beta >= 0.0 {
+            Ok(Self {
+                min_cutoff,
+                beta,
+                ..Self::default()
+            })
+        } else {
+            Err("the min cutoff is above 0 and beta isn't below it".to_string())
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for OneEuroParams {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        <[f32; 2]>::deserialize(deserializer)?.try_into().map_err(serde::de::Error::custom)
+    }
+}
+
+// `zones = "0,0,0.5,1:circle"` and `canvases = "a,b;*"` in the config file
+// take what the command line does
+impl<'de> Deserialize<'de> for HotZones {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Self::parse(&String::deserialize(deserializer)?).map_err(serde::de::Error::custom)
+    }
+}
+
+impl<'de> Deserialize<'de> for ExtraCanvases {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Self::parse(&String::deserialize(deserializer)?).map_err(serde::de::Error::custom)
+    }
+}
+
+// an option that also works without a value: `record = true` in the config
+// file is `--record`, `record = "run.jsonl"` is `--record=run.jsonl` and
+// false leaves it out
+fn flag_or_value<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum FlagOrValue<T> {
+        Flag(bool),
+        Value(T),
+    }
+    Ok(match FlagOrValue::deserialize(deserializer)? {
+        FlagOrValue::Flag(true) => Some(None),
+        FlagOrValue::Flag(false) => None,
+        FlagOrValue::Value(value) => Some(Some(value)),
+    })
+}
+
+// the rules the values follow, wherever they come from
+fn more_than_one(n: &usize) -> Result<(), &'static str> {
+    if *n > 1 {
+        Ok(())
+    } else {
+        Err("it's at least 2")
+    }
+}
+
+fn not_negative(x: &f32) -> Result<(), &'static str> {
+    if *x >= 0.0 {
+        Ok(())
+    } else {
+        Err("it can't be negative")
+    }
+}
+
+fn fraction(x: &f32) -> Result<(), &'static str> {
+    if (0.0..=1.0).contains(x) {
+        Ok(())
+    } else {
+        Err("it's between 0 and 1")
+    }
+}
+
+fn positive<T: Default + PartialOrd>(x: &T) -> Result<(), &'static str> {
+    if *x > T::default() {
+        Ok(())
+    } else {
+        Err("it's above 0")
+    }
+}
+
+fn fits_the_panels(size: &WindowSize) -> Result<(), &'static str> {
+    if size.width > 2.0 * crate::UI_PANEL_WIDTH && size.height > 0.0 {
+        Ok(())
+    } else {
+        Err("it's wider than the two side panels")
+    }
+}
+
+// a command line value parser that parses the value and holds it to `rule`
+fn checked<T>(rule: fn(&T) -> Result<(), &'static str>) -> impl Fn(&str) -> Result<T, String> + Clone + Send + Sync
+where
+    T: FromStr + Clone + Send + Sync + 'static,
+    T::Err: Display,
+{
+    move |value| {
+        let value = value.parse().map_err(|e: T::Err| e.to_string())?;
+        rule(&value)?;
+        Ok(value)
+    }
+}
+
+fn check<T>(name: &str, value: &Option<T>, rule: fn(&T) -> Result<(), &'static str>) -> Result<(), String> {
+    value.as_ref().map_or(Ok(()), rule).map_err(|e| format!("{}: {}", name, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_command_line_wins_over_the_file() {
+        let command_line = Options::try_parse_from(["stroke_recognizer", "--points=64", "--record"]).unwrap();
+        let file: Options = toml::from_str("points = 48\nmatcher = \"dtw\"\nrecord = \"run.jsonl\"").unwrap();
+        let options = command_line.or(file);
+        assert_eq!(options.points, Some(64));
+        assert!(options.matcher == Some(Matcher::Dtw));
+        assert_eq!(options.record, Some(None));
+    }
+
+    #[test]
+    fn the_command_line_turns_off_a_flag_the_file_turns_on() {
+        let command_line = Options::try_parse_from(["stroke_recognizer", "--corners=false", "--keypad"]).unwrap();
+        let file: Options = toml::from_str("corners = true
+keypad = false
+beautify = true").unwrap();
+        let options = command_line.or(file);
+        assert_eq!(options.corners, Some(false));
+        assert_eq!(options.keypad, Some(true));
+        assert_eq!(options.beautify, Some(true));
+    }
+
+    #[test]
+    fn unknown_or_invalid_options_are_rejected() {
+        assert!(Options::try_parse_from(["stroke_recognizer", "--pionts=64"]).is_err());
+        assert!(Options::try_parse_from(["stroke_recognizer", "--points=1"]).is_err());
+        assert!(Options::try_parse_from(["stroke_recognizer", "--matcher=cluod"]).is_err());
+        assert!(toml::from_str::<Options>("pionts = 64").is_err());
+        assert!(toml::from_str::<Options>("config = \"other.toml\"").is_err());
+        assert!(toml::from_str::<Options>("points = 1").unwrap().check().is_err());
+        assert!(Options::try_parse_from(["stroke_recognizer", "--zones=0,0,1:circle"]).is_err());
+        assert!(Options::try_parse_from(["stroke_recognizer", "--canvases=a;;b"]).is_err());
+        assert!(toml::from_str::<Options>("zones = \"0,0,0.5,1\"").is_err());
+        assert!(toml::from_str::<Options>("canvases = \"*,a\"").is_err());
+    }
+
+    #[test]
+    fn optional_values_work_with_and_without_one() {
+        let options = Options::try_parse_from(["stroke_recognizer", "--smoothing=2,0.5", "--sets=a,b"]).unwrap();
+        let params = options.smoothing.flatten().unwrap();
+        assert_eq!((params.min_cutoff, params.beta), (2.0, 0.5));
+        assert_eq!(options.sets, Some(vec!["a".to_string(), "b".to_string()]));
+
+        let options: Options = toml::from_str("smoothing = true\nrecord = false\nwindow = \"800x600\"").unwrap();
+        assert!(matches!(options.smoothing, Some(None)));
+        assert!(options.record.is_none());
+        assert_eq!(options.window.map(|size| (size.width, size.height)), Some((800.0, 600.0)));
+    }
+}
@@ -0,0 +1,167 @@
+use bevy::math::Vec2;
+
+// roughly how many points end up in a cell
+const POINTS_PER_CELL: f32 = 2.0;
+
+// uniform grid over a point cloud for nearest neighbour queries. Taking a
+// point out only moves it behind the end of its cell's list, so `reset` puts
+// everything back without reallocating and the grid can be reused
+pub struct PointGrid<'a> {
+    points: &'a [Vec2],
+    // the points left in cell c are indices[cell_starts[c]..cell_starts[c] + cell_lens[c]]
+    cell_starts: Vec<usize>,
+    cell_lens: Vec<usize>,
+    indices: Vec<usize>,
+    origin: Vec2,
+    cell_size: f32,
+    width: usize,
+    height: usize,
+}
+
+impl<'a> PointGrid<'a> {
+    // `bounds` has to contain every point that will be looked up, not just `points`
+    pub fn new(points: &'a [Vec2], bounds: (Vec2, Vec2)) -> Self {
+        let (min, max) = bounds;
+        let size = (max - min).max(Vec2::splat(f32::EPSILON));
+        let cells = (points.len() as f32 / POINTS_PER_CELL).max(1.0);
+        let cell_size = (size.x * size.y / cells).sqrt().max(size.max_element() / cells);
+        let width = ((size.x / cell_size).ceil() as usize).max(1);
+        let height = ((size.y / cell_size).ceil() as usize).max(1);
+
+        let mut grid = Self {
+            points,
+            cell_starts: vec![0; width * height + 1],
+            cell_lens: vec![0; width * height],
+            indices: vec![0; points.len()],
+            origin: min,
+            cell_size,
+            width,
+            height,
+        };
+
+        // counting sort of the points by cell
+        let cells: Vec<usize> = points.iter().map(|p| grid.cell_index(grid.cell_of(*p))).collect();
+        for &cell in cells.iter() {
+            grid.cell_starts[cell + 1] += 1;
+        }
+        for c in 0..width * height {
+            grid.cell_starts[c + 1] += grid.cell_starts[c];
+        }
+        for (i, &cell) in cells.iter().enumerate() {
+            grid.indices[grid.cell_starts[cell] + grid.cell_lens[cell]] = i;
+            grid.cell_lens[cell] += 1;
+        }
+        grid
+    }
+
+    // puts every point back
+    pub fn reset(&mut self) {
+        for (c, len) in self.cell_lens.iter_mut().enumerate() {
+            *len = self.cell_starts[c + 1] - self.cell_starts[c];
+        }
+    }
+
+    fn cell_of(&self, point: Vec2) -> (usize, usize) {
+        let cell = ((point - self.origin) / self.cell_size).max(Vec2::ZERO);
+        (
+            (cell.x as usize).min(self.width - 1),
+            (cell.y as usize).min(self.height - 1),
+        )
+    }
+
+    fn cell_index(&self, (x, y): (usize, usize)) -> usize {
+        y * self.width + x
+    }
+
+    // squared distance to the nearest point that's still there, which is then taken out
+    pub fn take_nearest(&mut self, query: Vec2) -> f32 {
+        let (cx, cy) = self.cell_of(query);
+        let mut nearest_dist = f32::MAX;
+        // (cell, position in the cell's list)
+        let mut nearest = None;
+
+        // rings of cells around the query's cell, the points in ring r + 1 are
+        // at least r cells away so once the nearest one is closer than that it's final
+        for r in 0..self.width.max(self.height) {
+            let (x0, x1) = (cx.saturating_sub(r), (cx + r).min(self.width - 1));
+            let (y0, y1) = (cy.saturating_sub(r), (cy + r).min(self.height - 1));
+            for y in y0..=y1 {
+                // only the border of the ring, the inside was done already
+                let step = if y.abs_diff(cy) == r { 1 } else { (x1 - x0).max(1) };
+                for x in (x0..=x1).step_by(step) {
+                    if x.abs_diff(cx) != r && y.abs_diff(cy) != r {
+                        continue;
+                    }
+                    let cell = self.cell_index((x, y));
+                    let start = self.cell_starts[cell];
+                    for k in 0..self.cell_lens[cell] {
+                        let d = query.distance_squared(self.points[self.indices[start + k]]);
+                        if d < nearest_dist {
+                            nearest_dist = d;
+                            nearest = Some((cell, k));
+                        }
+                    }
+                }
+            }
+
+            let reach = r as f32 * self.cell_size;
+            if nearest.is_some() && nearest_dist <= reach * reach {
+                break;
+            }
+        }
+
+        if let Some((cell, k)) = nearest {
+            let start = self.cell_starts[cell];
+            self.cell_lens[cell] -= 1;
+            self.indices.swap(start + k, start + self.cell_lens[cell]);
+        }
+        nearest_dist
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    use super::*;
+
+    // what `take_nearest` has to come out the same as
+    fn take_nearest_by_scan(left: &mut Vec<Vec2>, query: Vec2) -> f32 {
+        let nearest = left
+            .iter()
+            .enumerate()
+            .map(|(i, point)| (i, query.distance_squared(*point)))
+            .min_by(|a, b| a.1.total_cmp(&b.1));
+        match nearest {
+            Some((i, d)) => {
+                left.swap_remove(i);
+                d
+            }
+            None => f32::MAX,
+        }
+    }
+
+    #[test]
+    fn take_nearest_matches_a_linear_scan() {
+        let mut rng = StdRng::seed_from_u64(0);
+        for n in [1, 2, 7, 32, 96, 200] {
+            let bounds = (Vec2::splat(-1.0), Vec2::splat(1.0));
+            let random_point = |rng: &mut StdRng| Vec2::new(rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0));
+            // bunched up in a corner, so most cells are empty
+            let points: Vec<Vec2> = (0..n)
+                .map(|i| if i % 3 == 0 { random_point(&mut rng) * 0.1 - 0.9 } else { random_point(&mut rng) })
+                .collect();
+            let mut grid = PointGrid::new(&points, bounds);
+            for _ in 0..2 {
+                let mut left = points.clone();
+                // one more query than there are points, for when they're all taken
+                for _ in 0..=n {
+                    let query = random_point(&mut rng);
+                    assert_eq!(grid.take_nearest(query), take_nearest_by_scan(&mut left, query));
+                }
+                assert_eq!(grid.take_nearest(Vec2::ZERO), f32::MAX);
+                grid.reset();
+            }
+        }
+    }
+}
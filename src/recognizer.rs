@@ -0,0 +1,897 @@
+use core::f32;
+use std::time::Duration;
+
+use bevy::{
+    math::{Rot2, Vec2},
+    prelude::Resource,
+};
+use clap::ValueEnum;
+use serde::Deserialize;
+
+use crate::{
+    corners, dtw,
+    learning::{LearningMode, MISS_PENALTY},
+    library::GestureLibrary,
+    point_grid::PointGrid,
+    templates::Template,
+};
+use web_time::Instant;
+
+// default size of the point clouds, candidates and templates are resampled to
+// however many points the caller asks for
+pub const N_RESAMPLED_POINTS: usize = 32;
+// the resolutions that can be picked at runtime, fewer points is faster but less accurate
+pub const RESOLUTIONS: [usize; 4] = [16, 32, 64, 96];
+const MAX_PROTRACTOR_ROTATION: f32 = f32::consts::FRAC_PI_4;
+// templates drawn with more strokes than this away from the candidate's aren't
+// matched against, unless none of them are close enough
+const STROKE_COUNT_TOLERANCE: usize = 1;
+// a side of a fitted template's bounding box shorter than this (normalized,
+// a line's height) isn't stretched onto the candidate's
+const MIN_FIT_EXTENT: f32 = 0.1;
+
+// the widths of a drawing's points are kept between these, 1 is an average
+// press (or speed) like for the brush
+pub const MIN_WIDTH: f32 = 0.3;
+pub const MAX_WIDTH: f32 = 2.0;
+
+// how the candidate's points are compared with a template's, `--matcher=`
+// takes the names
+#[derive(Clone, Copy, PartialEq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Matcher {
+    // $P's greedy point cloud matching, see `greedy_5`. Stroke order and
+    // direction don't matter
+    #[value(name = "cloud")]
+    #[serde(rename = "cloud")]
+    PointCloud,
+    // dynamic time warping over the points in drawing order, see `dtw::distance`
+    Dtw,
+}
+
+impl Matcher {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::PointCloud => "cloud",
+            Self::Dtw => "dtw",
+        }
+    }
+}
+
+// where the width of a point comes from, see `width_penalty`
+#[derive(Clone, Copy, PartialEq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WidthSource {
+    // how hard the pen was pressed, 1 without a pen
+    Pressure,
+    // how slowly it was drawn there, like ink that has more time to spread
+    Speed,
+}
+
+#[derive(Resource)]
+pub struct RecognitionConfig {
+    pub matcher: Matcher,
+    pub n_points: usize,
+    pub epsilon: f32,
+    // only the k templates closest by Protractor distance go through the greedy
+    // matching, None matches against all of them
+    pub protractor_top_k: Option<usize>,
+    // resample so there's a point on every corner, see `resample`
+    pub preserve_corners: bool,
+    // how much a difference in size counts, see `size_penalty`. 0 ignores it
+    pub size_weight: f32,
+    // how much drawing in another direction counts, see `direction_penalty`. 0 ignores it
+    pub direction_weight: f32,
+    // how much drawing thicker or thinner counts, see `width_penalty`. 0
+    // ignores it, and then drawings don't get widths at all
+    pub width_weight: f32,
+    pub width_source: WidthSource,
+    // what correcting a misrecognized drawing does, see `learning`
+    pub learning: LearningMode,
+    // the app reports matches less confident than this (see
+    // `Calibration::confidence`) as not recognized
+    pub min_confidence: f32,
+    // how long the template matching may take, the templates are gone through
+    // closest first by Protractor distance and whatever is best when it runs
+    // out comes back (see `Match::truncated`). None takes as long as it takes
+    pub latency_budget: Option<Duration>,
+    // recognizes instead of the template matching when set, and then none of
+    // the above but `n_points` and `preserve_corners` matter
+    pub custom: Option<Box<dyn Recognizer + Send + Sync>>,
+}
+
+impl Default for RecognitionConfig {
+    fn default() -> Self {
+        Self {
+            matcher: Matcher::PointCloud,
+            n_points: N_RESAMPLED_POINTS,
+            epsilon: 0.5,
+            protractor_top_k: Some(8),
+            preserve_corners: false,
+            size_weight: 0.0,
+            direction_weight: 0.0,
+            width_weight: 0.0,
+            width_source: WidthSource::Pressure,
+            learning: LearningMode::Off,
+            min_confidence: 0.0,
+            latency_budget: None,
+            custom: None,
+        }
+    }
+}
+
+// turns a candidate into matches, for trying out another algorithm (a neural
+// network, some heuristic) behind the same drawing, history and score chart
+pub trait Recognizer {
+    // the best match of each of the closest gesture classes, closest first and
+    // empty if nothing matches. The confidence shown for a match is relative to
+    // the `cloud_distance` between a class's own templates, so distances should
+    // be on about the same scale
+    fn recognize(&self, candidate: &Candidate, templates: &GestureLibrary) -> Vec<Match>;
+}
+
+// the built-in one, the matching set up by a `RecognitionConfig`
+pub struct TemplateMatcher<'a> {
+    pub config: &'a RecognitionConfig,
+    // how many classes it ranks
+    pub classes: usize,
+}
+
+impl Recognizer for TemplateMatcher<'_> {
+    fn recognize(&self, candidate: &Candidate, templates: &GestureLibrary) -> Vec<Match> {
+        match_templates(templates, candidate, self.config, self.classes)
+    }
+}
+
+// a drawing that has been resampled and normalized, ready to be matched
+#[derive(Default)]
+pub struct Candidate {
+    pub points: Vec<Vec2>,
+    pub stroke_count: usize,
+    // diagonal of the bounding box relative to the canvas's, see `on_canvas`
+    pub size: Option<f32>,
+    // undo the normalization, see `denormalize`
+    scale: f32,
+    offset: Vec2,
+    // of the bounding box, before normalizing
+    diagonal: f32,
+    // the gestures and sets it's matched against, None for all of them. See `restrict`
+    classes: Option<Vec<String>>,
+    // how thick every point was drawn, in the order of `points`. See `with_widths`
+    pub widths: Option<Vec<f32>>,
+}
+
+impl Candidate {
+    pub fn new(candidate_vectors: &[Vec<Vec2>], n: usize, preserve_corners: bool) -> Self {
+        let resampled_strokes = resample(candidate_vectors, n, preserve_corners);
+        let stroke_count = resampled_strokes.len();
+        let mut points = resampled_strokes.concat();
+        let (min, max) = bounds(&points, &[]);
+        let (scale, offset) = scale_and_translate(&mut points);
+
+        Self {
+            points,
+            stroke_count,
+            size: None,
+            scale,
+            offset,
+            diagonal: if min.x <= max.x { min.distance(max) } else { 0.0 },
+            classes: None,
+            widths: None,
+        }
+    }
+
+    // gives every resampled point the width of the closest point it was
+    // resampled from, `widths` goes with the strokes the candidate was made of
+    pub fn with_widths(mut self, strokes: &[Vec<Vec2>], widths: &[Vec<f32>]) -> Self {
+        let drawn: Vec<(Vec2, f32)> = strokes
+            .iter()
+            .zip(widths)
+            .flat_map(|(stroke, widths)| stroke.iter().copied().zip(widths.iter().copied()))
+            .collect();
+        if drawn.is_empty() {
+            return self;
+        }
+        let widths = self
+            .points
+            .iter()
+            .map(|point| {
+                let point = self.denormalize(*point);
+                drawn
+                    .iter()
+                    .min_by(|a, b| a.0.distance_squared(point).total_cmp(&b.0.distance_squared(point)))
+                    .map_or(1.0, |(_, width)| width.clamp(MIN_WIDTH, MAX_WIDTH))
+            })
+            .collect();
+        self.widths = Some(widths);
+        self
+    }
+
+    // only matches templates of the gestures, or of the sets, named in `classes`
+    pub fn restrict(mut self, classes: Option<Vec<String>>) -> Self {
+        self.classes = classes;
+        self
+    }
+
+    pub fn allows(&self, set: &str, name: &str) -> bool {
+        self.classes
+            .as_ref()
+            .is_none_or(|classes| classes.iter().any(|class| class == set || class == name))
+    }
+
+    // sets the size, for a drawing made on a canvas of `canvas_size`
+    pub fn on_canvas(mut self, canvas_size: Vec2) -> Self {
+        self.size = Some(self.diagonal / canvas_size.length().max(1.0));
+        self
+    }
+
+    // maps a normalized point (of this or any other cloud) to where it would
+    // be on the board if it had been drawn with the candidate's size and position
+    pub fn denormalize(&self, point: Vec2) -> Vec2 {
+        point * self.scale + self.offset
+    }
+
+    // maps the points of a matched template's cloud onto the board, stretched
+    // over the candidate's bounding box and turned the way it was drawn (as far
+    // as `best_rotation` goes), for drawing the template in its place
+    pub fn fit(&self, cloud: &[Vec2]) -> impl Fn(Vec2) -> Vec2 + '_ {
+        let rotation = Rot2::radians(best_rotation(&self.points, cloud));
+        // the bounding box of the candidate turned back to the template's angle
+        let unturned: Vec<Vec2> = self.points.iter().map(|point| rotation.inverse() * *point).collect();
+        let (template_min, template_max) = bounds(cloud, &[]);
+        let (min, max) = bounds(&unturned, &[]);
+        let (template_size, size) = (template_max - template_min, max - min);
+
+        // a flat side keeps the other side's stretch, a dot keeps its size
+        let stretch_x = (template_size.x > MIN_FIT_EXTENT).then(|| size.x / template_size.x);
+        let stretch_y = (template_size.y > MIN_FIT_EXTENT).then(|| size.y / template_size.y);
+        let stretch = Vec2::new(
+            stretch_x.or(stretch_y).unwrap_or(1.0),
+            stretch_y.or(stretch_x).unwrap_or(1.0),
+        );
+        let (template_center, center) = ((template_min + template_max) / 2.0, (min + max) / 2.0);
+        move |point| self.denormalize(rotation * (center + (point - template_center) * stretch))
+    }
+
+    pub fn stroke_count_matches(&self, template: &Template) -> bool {
+        template.stroke_count.abs_diff(self.stroke_count) <= STROKE_COUNT_TOLERANCE
+    }
+}
+
+pub fn stroke_length(points: &[Vec2]) -> f32 {
+    points.windows(2).map(|w| w[0].distance(w[1])).sum()
+}
+
+pub fn resample_stroke(points: &[Vec2], length: f32, n: usize) -> Vec<Vec2> {
+    let mut resampled_points: Vec<Vec2> = Vec::with_capacity(n);
+    resampled_points.push(points[0]);
+    if n == 1 {
+        return resampled_points;
+    }
+
+    // both ends of the stroke are kept, so n points make n - 1 intervals
+    let increment = length / (n - 1) as f32;
+    let mut accumulated_distance = 0.0;
+    let mut previous_point = points[0];
+
+    for &current_point in points.iter().skip(1) {
+        let mut segment_distance = previous_point.distance(current_point);
+
+        while segment_distance + accumulated_distance >= increment && resampled_points.len() < n {
+            let alpha = (increment - accumulated_distance) / segment_distance;
+            let dv = previous_point.lerp(current_point, alpha);
+
+            resampled_points.push(dv);
+
+            previous_point = dv;
+            accumulated_distance = 0.0;
+            segment_distance = dv.distance(current_point);
+        }
+
+        accumulated_distance += segment_distance;
+        previous_point = current_point;
+    }
+
+    // floating point error can leave the last point out
+    while resampled_points.len() < n {
+        resampled_points.push(points[points.len() - 1]);
+    }
+
+    resampled_points
+}
+
+// every entry gets 1 and the other n - lengths.len() are handed out by
+// length with the largest remainder method, so they always add up to n
+fn share_out(lengths: &[f32], n: usize) -> Vec<usize> {
+    let total_length: f32 = lengths.iter().sum();
+    let spare = n - lengths.len();
+    let shares: Vec<f32> = lengths.iter().map(|length| length / total_length * spare as f32).collect();
+    let mut counts: Vec<usize> = shares.iter().map(|share| 1 + share.floor() as usize).collect();
+
+    let mut by_remainder: Vec<usize> = (0..lengths.len()).collect();
+    by_remainder.sort_by(|&a, &b| shares[b].fract().total_cmp(&shares[a].fract()));
+    let allocated: usize = counts.iter().sum();
+    for &i in by_remainder.iter().take(n - allocated) {
+        counts[i] += 1;
+    }
+    counts
+}
+
+// the pieces between the corners are resampled on their own, with their share
+// of the intervals, so every corner is one of the points. Uniform if there
+// are more corners than points to put on them
+fn resample_stroke_preserving_corners(points: &[Vec2], length: f32, n: usize) -> Vec<Vec2> {
+    let pieces = corners::split_at_corners(points);
+    if pieces.len() < 2 || n < pieces.len() + 1 {
+        return resample_stroke(points, length, n);
+    }
+
+    let lengths: Vec<f32> = pieces.iter().map(|piece| stroke_length(piece)).collect();
+    let mut resampled_points = vec![pieces[0][0]];
+    for ((piece, length), intervals) in pieces.iter().zip(&lengths).zip(share_out(&lengths, n - 1)) {
+        // the first point is where the previous piece ended
+        resampled_points.extend_from_slice(&resample_stroke(piece, *length, intervals + 1)[1..]);
+    }
+    resampled_points
+}
+
+// every stroke is resampled on its own, with a share of the points proportional
+// to its length, so nothing is interpolated across the gap between two strokes.
+// Always returns exactly n points in total, unless there were no points at all.
+// With `preserve_corners` sharp turns are kept even when few points are left
+// for them, which uniform resampling would round off
+pub fn resample(candidate_vectors: &[Vec<Vec2>], n: usize, preserve_corners: bool) -> Vec<Vec<Vec2>> {
+    let strokes: Vec<(&Vec<Vec2>, f32)> = candidate_vectors
+        .iter()
+        .map(|stroke| (stroke, stroke_length(stroke)))
+        .filter(|(_, length)| *length > 0.0)
+        .take(n)
+        .collect();
+    if strokes.is_empty() {
+        // nothing but taps (or n == 0), there's no path to walk along so the
+        // whole cloud sits on the first point
+        return match candidate_vectors.iter().find_map(|stroke| stroke.first()) {
+            Some(&point) if n > 0 => vec![vec![point; n]],
+            _ => Vec::new(),
+        };
+    }
+
+    // every stroke gets at least one point, so a short one like the dot of an
+    // exclamation mark isn't dropped
+    let lengths: Vec<f32> = strokes.iter().map(|(_, length)| *length).collect();
+    strokes
+        .iter()
+        .zip(share_out(&lengths, n))
+        .map(|((points, length), n)| {
+            if preserve_corners {
+                resample_stroke_preserving_corners(points, *length, n)
+            } else {
+                resample_stroke(points, *length, n)
+            }
+        })
+        .collect()
+}
+
+pub fn get_centroid(points: &[Vec2]) -> Vec2 {
+    let mut c_x = 0.0;
+    let mut c_y = 0.0;
+    for point in points.iter() {
+        c_x += point.x;
+        c_y += point.y;
+    }
+    c_x /= points.len() as f32;
+    c_y /= points.len() as f32;
+    Vec2::new(c_x, c_y)
+}
+
+// returns the scale and the offset that undo the normalization:
+// original point = normalized point * scale + offset
+pub fn scale_and_translate(points: &mut [Vec2]) -> (f32, Vec2) {
+    // bounding box
+    let (mut min_x, mut min_y, mut max_x, mut max_y) = (f32::MAX, f32::MAX, f32::MIN, f32::MIN);
+    for point in points.iter() {
+        min_x = min_x.min(point.x);
+        min_y = min_y.min(point.y);
+        max_x = max_x.max(point.x);
+        max_y = max_y.max(point.y);
+    }
+
+    // scale, a tap has no size so leave it as is instead of dividing by zero
+    let scale = f32::max(max_x - min_x, max_y - min_y);
+    let scale = if scale > 0.0 { scale } else { 1.0 };
+    for point in points.iter_mut() {
+        point.x = (point.x - min_x) / scale;
+        point.y = (point.y - min_y) / scale;
+    }
+
+    // translate to origin
+    let centroid = get_centroid(points);
+    for point in points.iter_mut() {
+        point.x -= centroid.x;
+        point.y -= centroid.y;
+    }
+
+    (scale, Vec2::new(min_x, min_y) + centroid * scale)
+}
+
+// added to the distance of templates drawn at another size, the squared log
+// of the ratio so twice as big counts as much as half as big. Per point like
+// the distance itself, and only if both know their size
+fn size_penalty(candidate: &Candidate, template: &Template, config: &RecognitionConfig) -> f32 {
+    match (candidate.size, template.size) {
+        (Some(a), Some(b)) if config.size_weight > 0.0 && a > 0.0 && b > 0.0 => {
+            config.size_weight * candidate.points.len() as f32 * (a / b).ln().powi(2)
+        }
+        _ => 0.0,
+    }
+}
+
+// the point clouds don't know in which order their points were drawn, so a
+// clockwise circle matches a counter-clockwise one perfectly. This compares
+// the points in drawing order instead, like $1 does: the first with the first
+// and so on, which is small only for drawings that went the same way (and
+// started about where the template did)
+fn direction_penalty(candidate: &Candidate, template: &Template, config: &RecognitionConfig) -> f32 {
+    if config.direction_weight <= 0.0 {
+        return 0.0;
+    }
+    let ordered_distance: f32 = candidate
+        .points
+        .iter()
+        .zip(&template.cloud)
+        .map(|(a, b)| a.distance_squared(*b))
+        .sum();
+    config.direction_weight * ordered_distance
+}
+
+// a thick line and a thin one are the same shape. This compares the width of
+// every point of the candidate with the width of the template's closest
+// point, as the squared log of the ratio like the size, and only if both
+// have widths
+fn width_penalty(candidate: &Candidate, template: &Template, config: &RecognitionConfig) -> f32 {
+    let (Some(widths), Some(template_widths)) = (&candidate.widths, &template.widths) else {
+        return 0.0;
+    };
+    if config.width_weight <= 0.0 || template_widths.len() != template.points.len() {
+        return 0.0;
+    }
+    let difference: f32 = candidate
+        .points
+        .iter()
+        .zip(widths)
+        .filter_map(|(point, width)| {
+            let closest = template
+                .points
+                .iter()
+                .zip(template_widths)
+                .min_by(|a, b| a.0.distance_squared(*point).total_cmp(&b.0.distance_squared(*point)))?;
+            Some((width / closest.1).ln().powi(2))
+        })
+        .sum();
+    config.width_weight * difference
+}
+
+// templates that wrong matches were corrected for count as further away, per
+// point like the distance
+fn miss_penalty(candidate: &Candidate, template: &Template, config: &RecognitionConfig) -> f32 {
+    if config.learning != LearningMode::Weight {
+        return 0.0;
+    }
+    MISS_PENALTY * candidate.points.len() as f32 * template.misses as f32
+}
+
+// what's added to the greedy distance of a template, for the options that
+// look at more than the shape
+pub fn penalty(candidate: &Candidate, template: &Template, config: &RecognitionConfig) -> f32 {
+    size_penalty(candidate, template, config)
+        + direction_penalty(candidate, template, config)
+        + width_penalty(candidate, template, config)
+        + miss_penalty(candidate, template, config)
+}
+
+fn get_weights(n: usize) -> Vec<f32> {
+    (0..n).map(|i| 1.0 - i as f32 / n as f32).collect()
+}
+
+pub struct Match {
+    pub set: String,
+    pub name: String,
+    pub distance: f32,
+    // the matched template's point cloud
+    pub cloud: Vec<Vec2>,
+    pub stroke_count: usize,
+    // the matching ran out of its `latency_budget` before it got through all
+    // the templates, a closer one might have been left out
+    pub truncated: bool,
+}
+
+// smallest box around both clouds
+fn bounds(a: &[Vec2], b: &[Vec2]) -> (Vec2, Vec2) {
+    a.iter().chain(b).fold(
+        (Vec2::splat(f32::MAX), Vec2::splat(f32::MIN)),
+        |(min, max), point| (min.min(*point), max.max(*point)),
+    )
+}
+
+// matches every point of `from`, beginning at `starting_point`, with the
+// nearest point of `to` that hasn't been taken yet. Earlier points weigh more.
+// Gives up as soon as the sum goes over `bound`, the result is then only known
+// to be larger than it
+fn greedy_5_match(from: &[Vec2], to: &mut PointGrid, weights: &[f32], starting_point: usize, bound: f32) -> f32 {
+    to.reset();
+    let mut total_distance = 0.0;
+    let order = (starting_point..from.len()).chain(0..starting_point);
+    for (step, i) in order.enumerate() {
+        total_distance += weights[step] * to.take_nearest(from[i]);
+        if total_distance > bound {
+            break;
+        }
+    }
+    total_distance
+}
+
+// O(n^(1 + epsilon)) or so with the grids, distance between the candidate's and
+// a template's cloud. Anything above `least_shape_distance` (the best match so
+// far) can't win, so that gets abandoned early and some value above the bound
+// comes back instead
+fn greedy_5(
+    resampled_points: &[Vec2],
+    template: &[Vec2],
+    weights: &[f32],
+    n_starting_points: usize,
+    least_shape_distance: f32,
+) -> f32 {
+    let mut bound = least_shape_distance;
+    let mut least_distance = f32::MAX;
+
+    // built once and reused for every starting point
+    let bounds = bounds(resampled_points, template);
+    let mut template_grid = PointGrid::new(template, bounds);
+    let mut candidate_grid = PointGrid::new(resampled_points, bounds);
+
+    for starting_point in 0..n_starting_points {
+        // matching candidate with template, then template with candidate
+        for (from, to) in [
+            (resampled_points, &mut template_grid),
+            (template, &mut candidate_grid),
+        ] {
+            let distance = greedy_5_match(from, to, weights, starting_point, bound);
+            least_distance = least_distance.min(distance);
+            bound = bound.min(distance);
+        }
+    }
+
+    least_distance
+}
+
+// the distance of the matcher in `config`, abandoned above `bound` like `greedy_5`
+fn shape_distance(
+    candidate: &[Vec2],
+    template: &[Vec2],
+    weights: &[f32],
+    n_starting_points: usize,
+    bound: f32,
+    config: &RecognitionConfig,
+) -> f32 {
+    match config.matcher {
+        Matcher::PointCloud => greedy_5(candidate, template, weights, n_starting_points, bound),
+        Matcher::Dtw => dtw::distance(candidate, template, bound),
+    }
+}
+
+// the same distance `recognize` ranks templates by, for two clouds of the same size
+pub fn cloud_distance(a: &[Vec2], b: &[Vec2], config: &RecognitionConfig) -> f32 {
+    shape_distance(a, b, &get_weights(a.len()), n_starting_points(a.len(), config), f32::MAX, config)
+}
+
+// the point pairs the greedy matching settles on for the candidate and a
+// template of the same size, as (candidate index, template index), from the
+// starting point and direction that came closest. Quadratic, for looking at a
+// match rather than for finding one
+pub fn matched_pairs(candidate: &[Vec2], template: &[Vec2], config: &RecognitionConfig) -> Vec<(usize, usize)> {
+    let weights = get_weights(candidate.len());
+    let mut best = (f32::MAX, Vec::new());
+    for starting_point in 0..n_starting_points(candidate.len(), config).min(candidate.len()) {
+        for reversed in [false, true] {
+            let (from, to) = if reversed { (template, candidate) } else { (candidate, template) };
+            let mut taken = vec![false; to.len()];
+            let mut distance = 0.0;
+            let mut pairs = Vec::with_capacity(from.len());
+            let order = (starting_point..from.len()).chain(0..starting_point);
+            for (step, i) in order.enumerate() {
+                let nearest = (0..to.len())
+                    .filter(|&j| !taken[j])
+                    .min_by(|&a, &b| from[i].distance_squared(to[a]).total_cmp(&from[i].distance_squared(to[b])));
+                let Some(j) = nearest else {
+                    break;
+                };
+                taken[j] = true;
+                distance += weights[step] * from[i].distance_squared(to[j]);
+                pairs.push(if reversed { (j, i) } else { (i, j) });
+            }
+            if distance < best.0 {
+                best = (distance, pairs);
+            }
+        }
+    }
+    best.1
+}
+
+// how many points of an n point cloud the greedy matching starts from
+pub fn n_starting_points(n: usize, config: &RecognitionConfig) -> usize {
+    (n as f32).powf(config.epsilon).ceil() as usize
+}
+
+// (set, gesture name, template) for every template of the active sets, clouds
+// of different sizes can't be matched point for point so only those with `n` points
+pub fn active_templates(library: &GestureLibrary, n: usize) -> Vec<(&str, &String, &Template)> {
+    library
+        .active_sets()
+        .flat_map(|(set, templates)| {
+            templates.iter().flat_map(move |(name, templates)| {
+                templates.iter().map(move |template| (set, name, template))
+            })
+        })
+        .filter(|(_, _, template)| template.cloud.len() == n)
+        .collect()
+}
+
+// Protractor (Li, 2010): the (already centered) cloud as a single unit vector
+fn vectorize(points: &[Vec2]) -> Vec<Vec2> {
+    let magnitude = points.iter().map(|p| p.length_squared()).sum::<f32>().sqrt();
+    if magnitude == 0.0 {
+        return points.to_vec();
+    }
+    points.iter().map(|p| *p / magnitude).collect()
+}
+
+// the angle `template` has to be turned by to line up with `candidate` point
+// for point, limited like `protractor_distance`. Both centered and in
+// drawing order
+pub fn best_rotation(candidate: &[Vec2], template: &[Vec2]) -> f32 {
+    let (mut a, mut b) = (0.0, 0.0);
+    for (c, t) in candidate.iter().zip(template) {
+        a += t.dot(*c);
+        b += t.perp_dot(*c);
+    }
+    if a == 0.0 && b == 0.0 {
+        return 0.0;
+    }
+    b.atan2(a).clamp(-MAX_PROTRACTOR_ROTATION, MAX_PROTRACTOR_ROTATION)
+}
+
+// angular distance between two vectorized clouds at the best rotation, which
+// has a closed form, limited to MAX_PROTRACTOR_ROTATION either way since $P
+// itself isn't rotation invariant
+fn protractor_distance(candidate: &[Vec2], template: &[Vec2]) -> f32 {
+    let (mut a, mut b) = (0.0, 0.0);
+    for (c, t) in candidate.iter().zip(template) {
+        a += c.dot(*t);
+        b += c.perp_dot(*t);
+    }
+    let angle = b
+        .atan2(a)
+        .clamp(-MAX_PROTRACTOR_ROTATION, MAX_PROTRACTOR_ROTATION);
+    (a * angle.cos() + b * angle.sin()).clamp(-1.0, 1.0).acos()
+}
+
+// resamples, normalizes and matches strokes from anywhere (in any coordinates,
+// matching ignores position and size) without the app around it, like
+// `recognize_top` for the `k` closest gesture classes. The same strokes,
+// library and config always give the same matches
+pub fn recognize_points(
+    strokes: &[Vec<Vec2>],
+    library: &GestureLibrary,
+    config: &RecognitionConfig,
+    k: usize,
+) -> Vec<Match> {
+    let candidate = Candidate::new(strokes, config.n_points, config.preserve_corners);
+    if candidate.points.is_empty() {
+        return Vec::new();
+    }
+    recognize_top(library, &candidate, config, k)
+}
+
+// the best match of each of the `k` closest gesture classes, closest first,
+// from the custom recognizer if there is one. None at all for a `k` of 0
+pub fn recognize_top(
+    library: &GestureLibrary,
+    candidate: &Candidate,
+    config: &RecognitionConfig,
+    k: usize,
+) -> Vec<Match> {
+    let mut matches = match &config.custom {
+        Some(custom) => custom.recognize(candidate, library),
+        None => TemplateMatcher { config, classes: k }.recognize(candidate, library),
+    };
+    // a custom recognizer might not know about them
+    matches.retain(|result| candidate.allows(&result.set, &result.name));
+    matches.truncate(k);
+    matches
+}
+
+fn match_templates(
+    library: &GestureLibrary,
+    candidate: &Candidate,
+    config: &RecognitionConfig,
+    k: usize,
+) -> Vec<Match> {
+    if k == 0 {
+        return Vec::new();
+    }
+    let deadline = config.latency_budget.map(|budget| Instant::now() + budget);
+    let n = candidate.points.len();
+
+    let mut templates = active_templates(library, n);
+    templates.retain(|(set, name, _)| candidate.allows(set, name));
+
+    if templates.iter().any(|(_, _, template)| candidate.stroke_count_matches(template)) {
+        templates.retain(|(_, _, template)| candidate.stroke_count_matches(template));
+    }
+
+    // cheap pre-filter so the greedy matching only runs on the closest few,
+    // and with a budget the order to go through them in
+    let top_k = config.protractor_top_k.filter(|&k| k < templates.len());
+    if top_k.is_some() || deadline.is_some() {
+        let candidate_vector = vectorize(&candidate.points);
+        let mut scored: Vec<(f32, (&str, &String, &Template))> = templates
+            .into_iter()
+            .map(|entry| (protractor_distance(&candidate_vector, &vectorize(&entry.2.cloud)), entry))
+            .collect();
+        if let Some(k) = top_k {
+            scored.select_nth_unstable_by(k - 1, |a, b| a.0.total_cmp(&b.0));
+            scored.truncate(k);
+        }
+        // closest first, so a good match is found early on and the rest can
+        // be abandoned sooner
+        scored.sort_by(|a, b| a.0.total_cmp(&b.0));
+        templates = scored.into_iter().map(|(_, entry)| entry).collect();
+    }
+
+    let n_starting_points = n_starting_points(n, config);
+    let weights = get_weights(n);
+    // closest first, one entry per (set, gesture name)
+    let mut ranked: Vec<(f32, (&str, &String, &Template))> = Vec::with_capacity(k.min(templates.len()) + 1);
+    let mut truncated = false;
+    for (i, (set, name, template)) in templates.into_iter().enumerate() {
+        // at least one template is matched, so there's always a best so far
+        if i > 0 && deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            truncated = true;
+            break;
+        }
+        // a template only matters if it beats its own class's best so far, or
+        // the last of the ranking while that's full
+        let same_class = ranked.iter().position(|(_, (s, n, _))| *s == set && *n == name);
+        let bound = match same_class {
+            Some(i) => ranked[i].0,
+            None if ranked.len() < k => f32::MAX,
+            None => ranked[ranked.len() - 1].0,
+        };
+        // a template that's too far off in size or direction alone can't make it
+        let penalty = penalty(candidate, template, config);
+        if penalty >= bound {
+            continue;
+        }
+        let distance = penalty
+            + shape_distance(
+                &candidate.points,
+                &template.cloud,
+                &weights,
+                n_starting_points,
+                bound - penalty,
+                config,
+            );
+        if distance < bound || bound == f32::MAX {
+            if let Some(i) = same_class {
+                ranked.remove(i);
+            }
+            let at = ranked.partition_point(|(d, _)| *d <= distance);
+            ranked.insert(at, (distance, (set, name, template)));
+            ranked.truncate(k);
+        }
+    }
+
+    ranked
+        .into_iter()
+        .map(|(distance, (set, name, template))| Match {
+            set: set.to_string(),
+            name: name.clone(),
+            distance,
+            cloud: template.cloud.clone(),
+            stroke_count: template.stroke_count,
+            truncated,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::templates::stroke_templates;
+
+    fn default_library() -> GestureLibrary {
+        GestureLibrary::new("default", stroke_templates())
+    }
+
+    fn total_points(strokes: &[Vec<Vec2>]) -> usize {
+        strokes.iter().map(Vec::len).sum()
+    }
+
+    #[test]
+    fn a_single_tap_resamples_to_n_points() {
+        for preserve_corners in [false, true] {
+            let resampled = resample(&[vec![Vec2::new(5.0, 5.0)]], 32, preserve_corners);
+            assert_eq!(resampled, vec![vec![Vec2::new(5.0, 5.0); 32]]);
+        }
+    }
+
+    #[test]
+    fn zero_length_strokes_resample_to_n_points() {
+        let tap = vec![Vec2::new(1.0, 1.0); 3];
+        assert_eq!(total_points(&resample(&[tap.clone(), tap.clone()], 32, false)), 32);
+
+        let line = vec![Vec2::new(0.0, 0.0), Vec2::new(100.0, 0.0)];
+        for preserve_corners in [false, true] {
+            let resampled = resample(&[tap.clone(), line.clone(), tap.clone()], 32, preserve_corners);
+            assert_eq!(total_points(&resampled), 32);
+        }
+    }
+
+    #[test]
+    fn a_far_longer_stroke_leaves_every_stroke_a_point() {
+        let long: Vec<Vec2> = (0..=100).map(|i| Vec2::new(i as f32 * 50.0, (i % 2) as f32 * 50.0)).collect();
+        let short = vec![Vec2::new(0.0, 0.0), Vec2::new(0.01, 0.0)];
+        for n in [3, 16, 32, 64, 96] {
+            for preserve_corners in [false, true] {
+                let resampled = resample(&[short.clone(), long.clone(), short.clone()], n, preserve_corners);
+                assert_eq!(total_points(&resampled), n);
+                assert!(resampled.iter().all(|stroke| !stroke.is_empty()));
+            }
+        }
+    }
+
+    #[test]
+    fn more_strokes_than_points_resample_to_n_points() {
+        let strokes: Vec<Vec<Vec2>> =
+            (0..5).map(|i| vec![Vec2::new(i as f32, 0.0), Vec2::new(i as f32, 10.0)]).collect();
+        assert_eq!(total_points(&resample(&strokes, 3, false)), 3);
+    }
+
+    #[test]
+    fn every_default_template_is_recognized_as_itself() {
+        let library = default_library();
+        let config = RecognitionConfig::default();
+        for (name, templates) in stroke_templates() {
+            for template in templates {
+                let matches = recognize_points(&template.strokes(), &library, &config, 3);
+                assert_eq!(matches[0].set, "default");
+                assert_eq!(matches[0].name, name);
+            }
+        }
+    }
+
+    #[test]
+    fn the_same_strokes_give_the_same_matches() {
+        let library = default_library();
+        let config = RecognitionConfig::default();
+        let strokes = vec![
+            vec![Vec2::new(10.0, 10.0), Vec2::new(60.0, 80.0), Vec2::new(110.0, 10.0)],
+            vec![Vec2::new(30.0, 45.0), Vec2::new(90.0, 45.0)],
+        ];
+        let first = recognize_points(&strokes, &library, &config, 5);
+        let second = recognize_points(&strokes, &library, &config, 5);
+        assert_eq!(first.len(), 5);
+        assert_eq!(first.len(), second.len());
+        for (a, b) in first.iter().zip(&second) {
+            assert_eq!((&a.set, &a.name), (&b.set, &b.name));
+            assert_eq!(a.distance.to_bits(), b.distance.to_bits());
+            assert_eq!(a.cloud, b.cloud);
+        }
+    }
+
+    #[test]
+    fn no_classes_asked_for_gives_no_matches() {
+        let strokes = vec![vec![Vec2::new(0.0, 0.0), Vec2::new(100.0, 100.0)]];
+        assert!(recognize_points(&strokes, &default_library(), &RecognitionConfig::default(), 0).is_empty());
+    }
+}
@@ -0,0 +1,87 @@
+use bevy::math::Vec2;
+
+// templates don't keep any timing, so they're drawn at a steady pace instead
+const TEMPLATE_REPLAY_SPEED: f32 = 600.0; // pixels per second
+const TEMPLATE_STROKE_PAUSE: f32 = 0.25; // seconds between two strokes
+
+// plays strokes back point by point, at the time each point was drawn
+pub struct Replay {
+    strokes: Vec<Vec<Vec2>>,
+    // seconds since the first point and pen pressure, same shape as `strokes`
+    times: Vec<Vec<f32>>,
+    pressures: Vec<Vec<f32>>,
+    elapsed: f32,
+    // the next point to be painted, as (stroke, point)
+    next: (usize, usize),
+}
+
+impl Replay {
+    // `times` can be measured from anywhere, the replay starts at the first point
+    pub fn recorded(
+        strokes: Vec<Vec<Vec2>>,
+        mut times: Vec<Vec<f32>>,
+        pressures: Vec<Vec<f32>>,
+    ) -> Self {
+        let start = times.iter().find_map(|t| t.first()).copied().unwrap_or(0.0);
+        for time in times.iter_mut().flatten() {
+            *time -= start;
+        }
+        Self {
+            strokes,
+            times,
+            pressures,
+            elapsed: 0.0,
+            next: (0, 0),
+        }
+    }
+
+    pub fn constant_speed(strokes: Vec<Vec<Vec2>>) -> Self {
+        let mut time = 0.0;
+        let mut times = Vec::with_capacity(strokes.len());
+        for stroke in strokes.iter() {
+            let mut stroke_times = Vec::with_capacity(stroke.len());
+            for (i, point) in stroke.iter().enumerate() {
+                if i > 0 {
+                    time += stroke[i - 1].distance(*point) / TEMPLATE_REPLAY_SPEED;
+                }
+                stroke_times.push(time);
+            }
+            times.push(stroke_times);
+            time += TEMPLATE_STROKE_PAUSE;
+        }
+        let pressures = strokes.iter().map(|stroke| vec![1.0; stroke.len()]).collect();
+        Self::recorded(strokes, times, pressures)
+    }
+
+    // moves the clock forward and returns the points that are due, each with
+    // the point before it in the same stroke (None for the first one) and its pressure
+    pub fn advance(&mut self, delta_seconds: f32) -> Vec<(Option<Vec2>, Vec2, f32)> {
+        self.elapsed += delta_seconds;
+
+        let mut due = Vec::new();
+        loop {
+            // skip over empty strokes so they don't stall the replay
+            while self.strokes.get(self.next.0).is_some_and(Vec::is_empty) {
+                self.next = (self.next.0 + 1, 0);
+            }
+            let (stroke, point) = self.next;
+            match self.times.get(stroke).and_then(|times| times.get(point)) {
+                Some(&time) if time <= self.elapsed => {}
+                _ => break,
+            }
+
+            let previous = point.checked_sub(1).map(|i| self.strokes[stroke][i]);
+            due.push((previous, self.strokes[stroke][point], self.pressures[stroke][point]));
+            self.next = if point + 1 < self.strokes[stroke].len() {
+                (stroke, point + 1)
+            } else {
+                (stroke + 1, 0)
+            };
+        }
+        due
+    }
+
+    pub fn finished(&self) -> bool {
+        self.next.0 >= self.strokes.len()
+    }
+}
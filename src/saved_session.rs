@@ -0,0 +1,155 @@
+use bevy::prelude::*;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    bindings::{Action, InputBindings},
+    paint_strokes,
+    session_log::{recorded_strokes, RecordedPoint},
+    start_drawing, storage,
+    theme::Theme,
+    ui_strings::{fill, UiStrings},
+    ActiveReplay, BrushEnabled, CandidateVectors, DrawMoment, DrawState, DrawingBoard, IsTyping, MatchOverlay,
+    PenSample, ResultText,
+};
+
+// file the session is saved to and restored from when there's no `--session=<file>`
+pub const DEFAULT_FILE: &str = "saved_session.json";
+
+// what's on the board, with the same points as the session log, so a
+// drawing of many strokes can be finished after a restart
+#[derive(Serialize, Deserialize)]
+struct SavedSession {
+    saved: DateTime<Utc>,
+    strokes: Vec<Vec<RecordedPoint>>,
+    // between strokes, the next one adds to the drawing instead of starting
+    // a new one
+    in_progress: bool,
+}
+
+// F8 saves the session and F9 restores it (see `bindings`), `--resume`
+// restores it on startup. A file natively, localStorage in the browser
+#[derive(Resource)]
+pub struct SessionFile {
+    pub path: String,
+    pub resume: bool,
+}
+
+pub fn save_session(
+    file: Res<SessionFile>,
+    bindings: Res<InputBindings>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    buttons: Res<ButtonInput<MouseButton>>,
+    typing: Res<IsTyping>,
+    candidate_vectors: Res<CandidateVectors>,
+    draw_state: Res<DrawState>,
+    strings: Res<UiStrings>,
+    mut result_text: Single<&mut Text, With<ResultText>>,
+) {
+    if typing.0 || !bindings.just_pressed(Action::SaveSession, &keyboard, &buttons) {
+        return;
+    }
+    let strokes = recorded_strokes(&candidate_vectors);
+    let session = SavedSession {
+        saved: Utc::now(),
+        in_progress: !strokes.is_empty()
+            && matches!(draw_state.0, DrawMoment::Paused | DrawMoment::Began(..) | DrawMoment::Drawing(..)),
+        strokes,
+    };
+    let text = serde_json::to_string(&session).expect("sessions are always valid JSON");
+    result_text.0 = match storage::write(&file.path, &text) {
+        Ok(()) => fill(&strings.session_saved, &[("path", &file.path)]),
+        Err(e) => fill(&strings.session_save_failed, &[("error", &e)]),
+    };
+}
+
+// the saved drawing, ending now
+fn drawing(session: &SavedSession, now: f32) -> CandidateVectors {
+    let end = session.strokes.iter().flatten().map(|point| point.t).fold(0.0, f32::max);
+    let start = now - end;
+    CandidateVectors {
+        strokes: session
+            .strokes
+            .iter()
+            .map(|stroke| stroke.iter().map(|point| Vec2::new(point.x, point.y)).collect())
+            .collect(),
+        times: session
+            .strokes
+            .iter()
+            .map(|stroke| stroke.iter().map(|point| start + point.t).collect())
+            .collect(),
+        pen: session
+            .strokes
+            .iter()
+            .map(|stroke| {
+                stroke
+                    .iter()
+                    .map(|point| PenSample {
+                        pressure: point.pressure,
+                        tilt: point.tilt,
+                    })
+                    .collect()
+            })
+            .collect(),
+        // the points were smoothed when they were drawn
+        filters: session.strokes.iter().map(|_| None).collect(),
+    }
+}
+
+pub fn restore_session(
+    mut file: ResMut<SessionFile>,
+    bindings: Res<InputBindings>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    buttons: Res<ButtonInput<MouseButton>>,
+    typing: Res<IsTyping>,
+    mut candidate_vectors: ResMut<CandidateVectors>,
+    mut draw_state: ResMut<DrawState>,
+    mut drawingboard: ResMut<DrawingBoard>,
+    mut images: ResMut<Assets<Image>>,
+    mut overlay: ResMut<MatchOverlay>,
+    mut replay: ResMut<ActiveReplay>,
+    time: Res<Time>,
+    brush_enabled: Res<BrushEnabled>,
+    theme: Res<Theme>,
+    strings: Res<UiStrings>,
+    mut result_text: Single<&mut Text, With<ResultText>>,
+) {
+    let resume = std::mem::take(&mut file.resume);
+    if !resume && (typing.0 || !bindings.just_pressed(Action::RestoreSession, &keyboard, &buttons)) {
+        return;
+    }
+    let session = storage::read(&file.path).and_then(|text| {
+        text.map(|text| serde_json::from_str::<SavedSession>(&text).map_err(|e| format!("{}: {}", file.path, e)))
+            .transpose()
+    });
+    let session = match session {
+        Ok(Some(session)) => session,
+        Ok(None) => {
+            result_text.0 = fill(&strings.no_saved_session, &[("path", &file.path)]);
+            return;
+        }
+        Err(e) => {
+            result_text.0 = fill(&strings.session_restore_failed, &[("error", &e)]);
+            return;
+        }
+    };
+
+    let board = images.get_mut(&drawingboard.image).expect("Board not found!!");
+    let size = board.size_f32();
+    start_drawing(board, &mut drawingboard.painted, size, &mut candidate_vectors, &mut overlay, &theme);
+    *candidate_vectors = drawing(&session, time.elapsed_secs());
+    paint_strokes(board, &mut drawingboard.painted, &candidate_vectors, brush_enabled.0, &theme);
+    replay.0 = None;
+    draw_state.0 = if session.in_progress {
+        DrawMoment::Paused
+    } else {
+        DrawMoment::Idle
+    };
+    result_text.0 = fill(
+        &strings.session_restored,
+        &[
+            ("strokes", &session.strokes.len()),
+            ("saved", &session.saved.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M")),
+        ],
+    );
+}
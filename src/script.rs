@@ -0,0 +1,302 @@
+use std::{collections::VecDeque, time::Duration};
+
+use bevy::{
+    input::{
+        keyboard::{Key, KeyboardInput, NativeKey},
+        mouse::{MouseButtonInput, MouseMotion},
+        ButtonState,
+    },
+    prelude::*,
+    window::PrimaryWindow,
+};
+use bevy_simple_text_input::{TextInput, TextInputSubmitEvent};
+use serde::Deserialize;
+
+use crate::{
+    bindings::{Action, Binding, InputBindings},
+    history::RecognitionHistory,
+    session_log::RecordedPoint,
+    storage,
+    ui_strings::{fill, UiStrings},
+    BoardView, CanvasRect, GestureRecognized,
+};
+
+// a frame every 1/60 s however fast the machine is, so a script draws the same
+// strokes at the same times on every run
+pub const FRAME_TIME: Duration = Duration::from_nanos(16_666_667);
+// the board and the UI are spawned and laid out in the first frames
+const WARMUP_FRAMES: u32 = 3;
+// how long a drawing gets to be recognized (on the GPU it takes a few frames)
+// and 'Add' to ask for a name
+const MAX_WAIT_FRAMES: u32 = 600;
+
+// a line of the script, which is a session log (see `session_log`): the app
+// draws every drawing and expects it to be recognized the way it was
+// recorded, and 'Add's it again where it was corrected. Fields other than
+// these are left alone, so a script can also be written by hand:
+// {"kind":"drawing","strokes":[[{"x":10,"y":10,"t":0,"pressure":1}, ...]],"recognized":{"name":"circle"}}
+#[derive(Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum Line {
+    Drawing {
+        strokes: Vec<Vec<RecordedPoint>>,
+        // None is expected not to be recognized
+        recognized: Option<Expected>,
+    },
+    Correction {
+        name: String,
+    },
+}
+
+#[derive(Deserialize)]
+struct Expected {
+    // any set if there's none
+    set: Option<String>,
+    name: String,
+}
+
+impl Expected {
+    fn matches(&self, (set, name): &(String, String)) -> bool {
+        self.name == *name && self.set.as_ref().is_none_or(|expected| expected == set)
+    }
+}
+
+fn describe_expected(expected: &Option<Expected>, strings: &UiStrings) -> String {
+    match expected {
+        Some(Expected { set: Some(set), name }) => fill(&strings.gesture_in_set, &[("name", name), ("set", set)]),
+        Some(Expected { set: None, name }) => name.clone(),
+        None => strings.nothing_matched.clone(),
+    }
+}
+
+fn describe_recognized(recognized: &Option<(String, String)>, strings: &UiStrings) -> String {
+    match recognized {
+        Some((set, name)) => fill(&strings.gesture_in_set, &[("name", name), ("set", set)]),
+        None => strings.nothing_matched.clone(),
+    }
+}
+
+// what the script does in a frame, up to the next `NextFrame`
+enum Step {
+    // in board coordinates, moving the mouse there
+    MoveTo(Vec2),
+    Press(Binding),
+    Release(Binding),
+    NextFrame,
+    // waits for the drawing on `line` of the script to be recognized
+    Expect { line: usize, expected: Option<Expected> },
+    // waits for the name input 'Add' opens and submits `name` in it
+    Name { line: usize, name: String },
+}
+
+// `--script=<file>` runs the app without a window and feeds it the mouse and
+// key presses that draw, recognize and 'Add' the drawings of the script,
+// before the input systems so every other system sees them as real ones. At
+// the end it reports the drawings that weren't recognized as expected and
+// exits, with an error if there were any
+#[derive(Resource)]
+pub struct ScriptedInput {
+    path: String,
+    steps: VecDeque<Step>,
+    frame: u32,
+    // by the current `Expect` or `Name`
+    waited: u32,
+    // in window coordinates, where the last `MoveTo` went
+    cursor: Option<Vec2>,
+    // the newest recognition before the one being waited for
+    last_entry: Option<u64>,
+    // the last drawing recognized on the main canvas, as (set, name)
+    recognized: Option<(String, String)>,
+    drawings: usize,
+    as_expected: usize,
+    failures: Vec<String>,
+}
+
+impl ScriptedInput {
+    pub fn load(path: &str, bindings: &InputBindings) -> Result<Self, String> {
+        let text = storage::read(path)?.ok_or_else(|| format!("{}: no such file", path))?;
+        let mut steps = VecDeque::new();
+        let binding = |action: Action, name: &str| {
+            bindings
+                .first(action)
+                .ok_or_else(|| format!("{}: there's nothing bound to {} to press", path, name))
+        };
+        for (index, line) in text.lines().enumerate().filter(|(_, line)| !line.trim().is_empty()) {
+            let line_number = index + 1;
+            match serde_json::from_str(line).map_err(|e| format!("{}:{}: {}", path, line_number, e))? {
+                Line::Drawing { strokes, recognized } => {
+                    let draw = binding(Action::Draw, "draw")?;
+                    let recognize = binding(Action::Recognize, "recognize")?;
+                    for stroke in strokes.iter().filter(|stroke| !stroke.is_empty()) {
+                        steps.push_back(Step::MoveTo(Vec2::new(stroke[0].x, stroke[0].y)));
+                        steps.push_back(Step::Press(draw));
+                        steps.push_back(Step::NextFrame);
+                        for point in &stroke[1..] {
+                            steps.push_back(Step::MoveTo(Vec2::new(point.x, point.y)));
+                            steps.push_back(Step::NextFrame);
+                        }
+                        steps.push_back(Step::Release(draw));
+                        steps.push_back(Step::NextFrame);
+                    }
+                    steps.push_back(Step::Press(recognize));
+                    steps.push_back(Step::NextFrame);
+                    steps.push_back(Step::Release(recognize));
+                    steps.push_back(Step::NextFrame);
+                    steps.push_back(Step::Expect {
+                        line: line_number,
+                        expected: recognized,
+                    });
+                }
+                Line::Correction { name } => {
+                    let add = binding(Action::AddGesture, "add_gesture")?;
+                    steps.push_back(Step::Press(add));
+                    steps.push_back(Step::NextFrame);
+                    steps.push_back(Step::Release(add));
+                    steps.push_back(Step::NextFrame);
+                    steps.push_back(Step::Name {
+                        line: line_number,
+                        name,
+                    });
+                    // the name is taken in this frame, drawing goes on in the next
+                    steps.push_back(Step::NextFrame);
+                }
+            }
+        }
+        Ok(Self {
+            path: path.to_string(),
+            steps,
+            frame: 0,
+            waited: 0,
+            cursor: None,
+            last_entry: None,
+            recognized: None,
+            drawings: 0,
+            as_expected: 0,
+            failures: Vec::new(),
+        })
+    }
+
+    fn report(&self) {
+        println!(
+            "{}: {} of {} drawings recognized as expected",
+            self.path,
+            self.as_expected,
+            self.drawings
+        );
+        for failure in &self.failures {
+            println!("  {}", failure);
+        }
+    }
+}
+
+pub fn run_script(
+    mut script: ResMut<ScriptedInput>,
+    mut window: Single<(Entity, &mut Window), With<PrimaryWindow>>,
+    canvas: Res<CanvasRect>,
+    view: Res<BoardView>,
+    history: Res<RecognitionHistory>,
+    name_inputs: Query<Entity, With<TextInput>>,
+    mut recognized: EventReader<GestureRecognized>,
+    mut mouse_buttons: EventWriter<MouseButtonInput>,
+    mut mouse_motion: EventWriter<MouseMotion>,
+    mut keys: EventWriter<KeyboardInput>,
+    mut submit: EventWriter<TextInputSubmitEvent>,
+    mut exit: EventWriter<AppExit>,
+    strings: Res<UiStrings>,
+) {
+    let script = &mut *script;
+    // the extra canvases recognize their own drawings
+    if let Some(event) = recognized.read().filter(|event| event.canvas == 0).last() {
+        script.recognized = Some((event.set.clone(), event.name.clone()));
+    }
+    script.frame += 1;
+    if script.frame <= WARMUP_FRAMES {
+        return;
+    }
+
+    let (window_entity, window) = &mut *window;
+    let mut send = |binding: Binding, state: ButtonState| match binding {
+        Binding::Key(key_code) => {
+            keys.send(KeyboardInput {
+                key_code,
+                logical_key: Key::Unidentified(NativeKey::Unidentified),
+                state,
+                repeat: false,
+                window: *window_entity,
+            });
+        }
+        Binding::Mouse { mouse: button } => {
+            mouse_buttons.send(MouseButtonInput {
+                button,
+                state,
+                window: *window_entity,
+            });
+        }
+    };
+    while let Some(step) = script.steps.pop_front() {
+        match step {
+            Step::MoveTo(point) => {
+                let position = view.to_window(&canvas, point);
+                let delta = script.cursor.map_or(Vec2::ZERO, |cursor| position - cursor);
+                mouse_motion.send(MouseMotion { delta });
+                window.set_cursor_position(Some(position));
+                script.cursor = Some(position);
+            }
+            Step::Press(binding) => send(binding, ButtonState::Pressed),
+            Step::Release(binding) => send(binding, ButtonState::Released),
+            Step::NextFrame => return,
+            Step::Expect { line, expected } => {
+                let newest = history.entries().next();
+                if newest.map(|entry| entry.id) == script.last_entry && script.waited < MAX_WAIT_FRAMES {
+                    script.waited += 1;
+                    script.steps.push_front(Step::Expect { line, expected });
+                    return;
+                }
+                script.waited = 0;
+                script.drawings += 1;
+                let Some(newest) = newest.filter(|entry| Some(entry.id) != script.last_entry) else {
+                    script.failures.push(format!("line {}: wasn't recognized in time", line));
+                    continue;
+                };
+                script.last_entry = Some(newest.id);
+                let recognized = script.recognized.take().filter(|_| newest.confidence.is_some());
+                let as_expected = match (&expected, &recognized) {
+                    (Some(expected), Some(recognized)) => expected.matches(recognized),
+                    (None, None) => true,
+                    _ => false,
+                };
+                if as_expected {
+                    script.as_expected += 1;
+                } else {
+                    script.failures.push(format!(
+                        "line {}: expected {}, got {}",
+                        line,
+                        describe_expected(&expected, &strings),
+                        describe_recognized(&recognized, &strings)
+                    ));
+                }
+            }
+            Step::Name { line, name } => {
+                let Some(entity) = name_inputs.iter().next() else {
+                    if script.waited < MAX_WAIT_FRAMES {
+                        script.waited += 1;
+                        script.steps.push_front(Step::Name { line, name });
+                        return;
+                    }
+                    script.waited = 0;
+                    script.failures.push(format!("line {}: 'Add' didn't ask for a name", line));
+                    continue;
+                };
+                script.waited = 0;
+                submit.send(TextInputSubmitEvent { entity, value: name });
+            }
+        }
+    }
+
+    script.report();
+    exit.send(if script.failures.is_empty() {
+        AppExit::Success
+    } else {
+        AppExit::error()
+    });
+}
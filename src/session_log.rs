@@ -0,0 +1,190 @@
+use bevy::prelude::*;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{recognizer::Match, storage, CandidateVectors};
+
+// file the drawings are recorded to when there's no `--record=<file>`
+pub const DEFAULT_FILE: &str = "session.jsonl";
+
+// also how `saved_session` keeps the drawing
+#[derive(Serialize, Deserialize)]
+pub struct RecordedPoint {
+    pub x: f32,
+    pub y: f32,
+    // seconds since the drawing's first point
+    pub t: f32,
+    pub pressure: f32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tilt: Option<f32>,
+}
+
+// in pixels from the top left corner of the canvas, a list of points per stroke
+pub fn recorded_strokes(drawing: &CandidateVectors) -> Vec<Vec<RecordedPoint>> {
+    let start = drawing.times.iter().flatten().copied().fold(f32::MAX, f32::min);
+    drawing
+        .strokes
+        .iter()
+        .zip(&drawing.times)
+        .zip(&drawing.pen)
+        .map(|((points, times), pen)| {
+            points
+                .iter()
+                .zip(times)
+                .zip(pen)
+                .map(|((point, time), pen)| RecordedPoint {
+                    x: point.x,
+                    y: point.y,
+                    t: time - start,
+                    pressure: pen.pressure,
+                    tilt: pen.tilt,
+                })
+                .collect()
+        })
+        .collect()
+}
+
+#[derive(Serialize)]
+struct RecordedMatch<'a> {
+    set: &'a str,
+    name: &'a str,
+    distance: f32,
+    confidence: f32,
+}
+
+impl<'a> RecordedMatch<'a> {
+    fn new((result, confidence): &'a (Match, f32)) -> Self {
+        Self {
+            set: &result.set,
+            name: &result.name,
+            distance: result.distance,
+            confidence: *confidence,
+        }
+    }
+}
+
+// one line of the log. A correction comes after the drawing it's for, the
+// log is only ever appended to
+#[derive(Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum Entry<'a> {
+    Drawing {
+        // (session, id) is unique across runs appending to the same file
+        session: DateTime<Utc>,
+        id: u64,
+        recorded: DateTime<Utc>,
+        // in pixels from the top left corner of the canvas
+        strokes: Vec<Vec<RecordedPoint>>,
+        // the closest classes, closest first
+        matches: Vec<RecordedMatch<'a>>,
+        // what the app reported, None if it wasn't confident enough in any
+        recognized: Option<RecordedMatch<'a>>,
+        latency_ms: f64,
+    },
+    // the drawing was 'Add'ed as this gesture
+    Correction {
+        session: DateTime<Utc>,
+        id: u64,
+        name: &'a str,
+    },
+}
+
+// the "record session" toggle, every recognized drawing is appended to the
+// file (see `storage`) as a line of JSON while it's on
+#[derive(Resource)]
+pub struct SessionLog {
+    path: String,
+    recording: bool,
+    session: DateTime<Utc>,
+    next_id: u64,
+    // the drawing an 'Add' would correct
+    last_id: Option<u64>,
+}
+
+impl SessionLog {
+    pub fn new(path: impl Into<String>) -> Self {
+        Self {
+            path: path.into(),
+            recording: false,
+            session: Utc::now(),
+            next_id: 0,
+            last_id: None,
+        }
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recording
+    }
+
+    pub fn path(&self) -> String {
+        self.path.clone()
+    }
+
+    // makes the file if there isn't one, so it fails now if it can't be written
+    pub fn start(&mut self) -> Result<(), String> {
+        storage::append(&self.path, "")?;
+        self.recording = true;
+        Ok(())
+    }
+
+    pub fn stop(&mut self) {
+        self.recording = false;
+        self.last_id = None;
+    }
+
+    // stops recording if the file can't be written to
+    fn write(&mut self, entry: &Entry) {
+        if !self.recording {
+            return;
+        }
+        let mut line = serde_json::to_string(entry).expect("log entries are always valid JSON");
+        line.push('\n');
+        if let Err(e) = storage::append(&self.path, &line) {
+            warn!("stopped recording the session, {}", e);
+            self.stop();
+        }
+    }
+
+    // `matches` with their confidences, `recognized` the one the app reported
+    pub fn record_drawing(
+        &mut self,
+        drawing: &CandidateVectors,
+        matches: &[(Match, f32)],
+        recognized: Option<&(Match, f32)>,
+        latency_ms: f64,
+    ) {
+        if !self.is_recording() {
+            return;
+        }
+        let strokes = recorded_strokes(drawing);
+        let id = self.next_id;
+        self.next_id += 1;
+        self.last_id = Some(id);
+        self.write(&Entry::Drawing {
+            session: self.session,
+            id,
+            recorded: Utc::now(),
+            strokes,
+            matches: matches.iter().map(RecordedMatch::new).collect(),
+            recognized: recognized.map(RecordedMatch::new),
+            latency_ms,
+        });
+    }
+
+    // for a drawing that isn't recorded (a training sample), an 'Add' after it
+    // isn't a correction of the last one that was
+    pub fn skip_drawing(&mut self) {
+        self.last_id = None;
+    }
+
+    // the last recorded drawing was added as `name`
+    pub fn record_correction(&mut self, name: &str) {
+        if let Some(id) = self.last_id {
+            self.write(&Entry::Correction {
+                session: self.session,
+                id,
+                name,
+            });
+        }
+    }
+}
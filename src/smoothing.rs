@@ -0,0 +1,77 @@
+use std::f32::consts::TAU;
+
+use bevy::math::Vec2;
+
+// 1€ filter (Casiez, Roussel and Vogel, 2012): a low-pass filter whose cutoff
+// goes up with the speed of the pen, so slow movements lose their jitter and
+// fast ones don't lag behind
+
+// every filtered point is at least this long after the previous one, points
+// of the same frame have the same time
+const MIN_TIME_STEP: f32 = 1.0 / 1000.0;
+
+#[derive(Clone, Copy)]
+pub struct OneEuroParams {
+    // cutoff frequency in Hz at a standstill, lower is smoother
+    pub min_cutoff: f32,
+    // how much the cutoff goes up per pixel per second of speed, higher lags less
+    pub beta: f32,
+    // cutoff for the speed itself
+    pub derivative_cutoff: f32,
+}
+
+impl Default for OneEuroParams {
+    fn default() -> Self {
+        Self {
+            min_cutoff: 1.0,
+            beta: 0.007,
+            derivative_cutoff: 1.0,
+        }
+    }
+}
+
+// how much of the new value goes into the filtered one
+fn smoothing_factor(cutoff: f32, time_step: f32) -> f32 {
+    let time_constant = 1.0 / (TAU * cutoff);
+    1.0 / (1.0 + time_constant / time_step)
+}
+
+// the state of one stroke's filter
+#[derive(Clone)]
+pub struct OneEuroFilter {
+    params: OneEuroParams,
+    // filtered position, speed and the time of the last point
+    last: Option<(Vec2, Vec2, f32)>,
+}
+
+impl OneEuroFilter {
+    pub fn new(params: OneEuroParams) -> Self {
+        Self { params, last: None }
+    }
+
+    // `time` in seconds, the first point goes through as it is
+    pub fn filter(&mut self, point: Vec2, time: f32) -> Vec2 {
+        let Some((last_point, last_speed, last_time)) = self.last else {
+            self.last = Some((point, Vec2::ZERO, time));
+            return point;
+        };
+
+        let time_step = (time - last_time).max(MIN_TIME_STEP);
+        let speed = last_speed.lerp(
+            (point - last_point) / time_step,
+            smoothing_factor(self.params.derivative_cutoff, time_step),
+        );
+        let cutoff = self.params.min_cutoff + self.params.beta * speed.length();
+        let filtered = last_point.lerp(point, smoothing_factor(cutoff, time_step));
+
+        self.last = Some((filtered, speed, last_time + time_step));
+        filtered
+    }
+
+    // for when the stroke it's filtering is moved
+    pub fn shift(&mut self, offset: Vec2) {
+        if let Some((point, _, _)) = self.last.as_mut() {
+            *point += offset;
+        }
+    }
+}
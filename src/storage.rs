@@ -0,0 +1,167 @@
+use std::collections::BTreeMap;
+
+use bevy::prelude::*;
+
+use crate::{library::GestureLibrary, templates::Template};
+
+// small text files that are kept between runs: in the working directory on
+// desktop, in the browser's localStorage (under the file name) on the web
+
+// None if there's no such file yet
+#[cfg(not(target_arch = "wasm32"))]
+pub fn read(name: &str) -> Result<Option<String>, String> {
+    match std::fs::read_to_string(name) {
+        Ok(text) => Ok(Some(text)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(format!("{}: {}", name, e)),
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn read(name: &str) -> Result<Option<String>, String> {
+    local_storage()?
+        .get_item(name)
+        .map_err(|_| format!("{}: couldn't read it from localStorage", name))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn write(name: &str, text: &str) -> Result<(), String> {
+    std::fs::write(name, text).map_err(|e| format!("{}: {}", name, e))
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn write(name: &str, text: &str) -> Result<(), String> {
+    local_storage()?
+        .set_item(name, text)
+        .map_err(|_| format!("{}: couldn't write it to localStorage", name))
+}
+
+// adds `text` to the end, making the file if there isn't one
+#[cfg(not(target_arch = "wasm32"))]
+pub fn append(name: &str, text: &str) -> Result<(), String> {
+    use std::io::Write;
+
+    std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(name)
+        .and_then(|mut file| file.write_all(text.as_bytes()))
+        .map_err(|e| format!("{}: {}", name, e))
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn append(name: &str, text: &str) -> Result<(), String> {
+    let mut appended = read(name)?.unwrap_or_default();
+    appended.push_str(text);
+    write(name, &appended)
+}
+
+#[cfg(target_arch = "wasm32")]
+fn local_storage() -> Result<web_sys::Storage, String> {
+    web_sys::window()
+        .and_then(|window| window.local_storage().ok().flatten())
+        .ok_or_else(|| "localStorage isn't available".to_string())
+}
+
+// set -> gesture name -> templates, oldest first
+pub type SavedTemplates = BTreeMap<String, BTreeMap<String, Vec<Template>>>;
+
+// keeps the gestures added while the app is running for the next time
+pub trait TemplateStore {
+    // nothing saved yet is an empty library, not an error
+    fn load(&self) -> Result<SavedTemplates, String>;
+    fn save(&self, templates: &SavedTemplates) -> Result<(), String>;
+}
+
+// a TOML file, on desktop
+#[cfg(not(target_arch = "wasm32"))]
+pub struct FileTemplateStore {
+    pub path: std::path::PathBuf,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl TemplateStore for FileTemplateStore {
+    fn load(&self) -> Result<SavedTemplates, String> {
+        match std::fs::read_to_string(&self.path) {
+            Ok(text) => toml::from_str(&text).map_err(|e| format!("{}: {}", self.path.display(), e)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(SavedTemplates::new()),
+            Err(e) => Err(format!("{}: {}", self.path.display(), e)),
+        }
+    }
+
+    fn save(&self, templates: &SavedTemplates) -> Result<(), String> {
+        let text = toml::to_string(templates).map_err(|e| e.to_string())?;
+        std::fs::write(&self.path, text).map_err(|e| format!("{}: {}", self.path.display(), e))
+    }
+}
+
+// the same TOML under a localStorage key, in the browser
+#[cfg(target_arch = "wasm32")]
+pub struct LocalStorageTemplateStore {
+    pub key: String,
+}
+
+#[cfg(target_arch = "wasm32")]
+impl TemplateStore for LocalStorageTemplateStore {
+    fn load(&self) -> Result<SavedTemplates, String> {
+        match read(&self.key)? {
+            Some(text) => toml::from_str(&text).map_err(|e| format!("{}: {}", self.key, e)),
+            None => Ok(SavedTemplates::new()),
+        }
+    }
+
+    fn save(&self, templates: &SavedTemplates) -> Result<(), String> {
+        let text = toml::to_string(templates).map_err(|e| e.to_string())?;
+        write(&self.key, &text)
+    }
+}
+
+// where the templates are kept when there's no `--templates=<file>`
+const USER_TEMPLATES_FILE: &str = "user_templates.toml";
+
+#[derive(Resource)]
+pub struct TemplateStorage(pub Box<dyn TemplateStore + Send + Sync>);
+
+impl TemplateStorage {
+    // a file on desktop, localStorage on the web, `path` (or `user_templates.toml`)
+    // is the file name or the key
+    pub fn for_platform(path: Option<String>) -> Self {
+        let path = path.unwrap_or_else(|| USER_TEMPLATES_FILE.to_string());
+        #[cfg(not(target_arch = "wasm32"))]
+        let store = FileTemplateStore { path: path.into() };
+        #[cfg(target_arch = "wasm32")]
+        let store = LocalStorageTemplateStore { key: path };
+        Self(Box::new(store))
+    }
+}
+
+// saves the user's templates whenever the library changes
+pub fn save_templates(
+    library: Res<GestureLibrary>,
+    storage: Res<TemplateStorage>,
+    // what was saved (or loaded) last, so switching sets doesn't rewrite the
+    // same thing. As TOML, templates are equal if their points are but a miss
+    // has to be saved too
+    mut last_saved: Local<Option<String>>,
+) {
+    let templates = library.user_templates();
+    let text = match toml::to_string(&templates) {
+        Ok(text) => text,
+        Err(e) => {
+            warn!("Couldn't save the templates: {}", e);
+            return;
+        }
+    };
+    // the first time round it's what was just loaded
+    if last_saved.is_none() {
+        *last_saved = Some(text);
+        return;
+    }
+    if last_saved.as_ref() == Some(&text) {
+        return;
+    }
+    if let Err(e) = storage.0.save(&templates) {
+        warn!("Couldn't save the templates: {}", e);
+    }
+    *last_saved = Some(text);
+}
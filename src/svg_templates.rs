@@ -0,0 +1,444 @@
+use std::{f32::consts::TAU, fs, path::Path};
+
+use bevy::math::Vec2;
+
+use crate::{
+    dataset::{self, Gesture},
+    library::GestureSet,
+};
+
+// where the SVG templates are looked for without `--template-dir=<dir>`
+pub const DEFAULT_DIR: &str = "templates";
+
+// every curve (and arc) becomes this many straight segments
+const CURVE_SEGMENTS: usize = 16;
+
+// the numbers of path data, which can be run together: "M10-5.5.5" is M 10 -5.5 .5
+struct Numbers<'a> {
+    text: &'a [u8],
+    position: usize,
+}
+
+impl Numbers<'_> {
+    fn skip_separators(&mut self) {
+        while self.text.get(self.position).is_some_and(|c| c.is_ascii_whitespace() || *c == b',') {
+            self.position += 1;
+        }
+    }
+
+    // the next command letter, if that's what comes next
+    fn command(&mut self) -> Option<u8> {
+        self.skip_separators();
+        let c = *self.text.get(self.position)?;
+        (c.is_ascii_alphabetic() && c != b'e' && c != b'E').then(|| {
+            self.position += 1;
+            c
+        })
+    }
+
+    fn has_number(&mut self) -> bool {
+        self.skip_separators();
+        self.text
+            .get(self.position)
+            .is_some_and(|c| c.is_ascii_digit() || matches!(c, b'-' | b'+' | b'.'))
+    }
+
+    fn number(&mut self) -> Result<f32, String> {
+        self.skip_separators();
+        let start = self.position;
+        let mut seen_dot = false;
+        let mut seen_exponent = false;
+        while let Some(&c) = self.text.get(self.position) {
+            let sign_allowed = self.position == start || matches!(self.text[self.position - 1], b'e' | b'E');
+            match c {
+                b'0'..=b'9' => {}
+                b'-' | b'+' if sign_allowed => {}
+                b'.' if !seen_dot && !seen_exponent => seen_dot = true,
+                b'e' | b'E' if !seen_exponent && self.position > start => seen_exponent = true,
+                _ => break,
+            }
+            self.position += 1;
+        }
+        let number = std::str::from_utf8(&self.text[start..self.position]).unwrap_or_default();
+        number.parse().map_err(|_| format!("invalid number {:?} in path data", number))
+    }
+
+    // arc flags are a single 0 or 1 and don't need anything after them: "a5 5 0 1010 10"
+    fn flag(&mut self) -> Result<bool, String> {
+        self.skip_separators();
+        let flag = match self.text.get(self.position) {
+            Some(b'0') => false,
+            Some(b'1') => true,
+            _ => return Err("invalid arc flag in path data".to_string()),
+        };
+        self.position += 1;
+        Ok(flag)
+    }
+
+    fn point(&mut self) -> Result<Vec2, String> {
+        Ok(Vec2::new(self.number()?, self.number()?))
+    }
+}
+
+fn cubic(stroke: &mut Vec<Vec2>, from: Vec2, control_1: Vec2, control_2: Vec2, to: Vec2) {
+    for i in 1..=CURVE_SEGMENTS {
+        let t = i as f32 / CURVE_SEGMENTS as f32;
+        let u = 1.0 - t;
+        stroke.push(from * u * u * u + control_1 * 3.0 * u * u * t + control_2 * 3.0 * u * t * t + to * t * t * t);
+    }
+}
+
+fn quadratic(stroke: &mut Vec<Vec2>, from: Vec2, control: Vec2, to: Vec2) {
+    for i in 1..=CURVE_SEGMENTS {
+        let t = i as f32 / CURVE_SEGMENTS as f32;
+        let u = 1.0 - t;
+        stroke.push(from * u * u + control * 2.0 * u * t + to * t * t);
+    }
+}
+
+// the endpoint to center conversion from the SVG spec's implementation notes
+fn arc(stroke: &mut Vec<Vec2>, from: Vec2, radii: Vec2, rotation: f32, large_arc: bool, sweep: bool, to: Vec2) {
+    let mut radii = radii.abs();
+    if from == to {
+        return;
+    }
+    if radii.x == 0.0 || radii.y == 0.0 {
+        stroke.push(to);
+        return;
+    }
+
+    let (sin, cos) = rotation.to_radians().sin_cos();
+    let half = (from - to) / 2.0;
+    let p = Vec2::new(cos * half.x + sin * half.y, -sin * half.x + cos * half.y);
+    // radii too small to reach are scaled up until they just do
+    let lambda = (p.x / radii.x).powi(2) + (p.y / radii.y).powi(2);
+    if lambda > 1.0 {
+        radii *= lambda.sqrt();
+    }
+
+    let (rx2, ry2) = (radii.x * radii.x, radii.y * radii.y);
+    let numerator = (rx2 * ry2 - rx2 * p.y * p.y - ry2 * p.x * p.x).max(0.0);
+    let mut factor = (numerator / (rx2 * p.y * p.y + ry2 * p.x * p.x)).sqrt();
+    if large_arc == sweep {
+        factor = -factor;
+    }
+    let center_prime = Vec2::new(factor * radii.x * p.y / radii.y, -factor * radii.y * p.x / radii.x);
+    let center = Vec2::new(cos * center_prime.x - sin * center_prime.y, sin * center_prime.x + cos * center_prime.y)
+        + (from + to) / 2.0;
+
+    let start = Vec2::new((p.x - center_prime.x) / radii.x, (p.y - center_prime.y) / radii.y);
+    let end = Vec2::new((-p.x - center_prime.x) / radii.x, (-p.y - center_prime.y) / radii.y);
+    let start_angle = start.y.atan2(start.x);
+    let mut delta = (start.perp_dot(end)).atan2(start.dot(end));
+    if !sweep && delta > 0.0 {
+        delta -= TAU;
+    } else if sweep && delta < 0.0 {
+        delta += TAU;
+    }
+
+    for i in 1..=CURVE_SEGMENTS {
+        let angle = start_angle + delta * i as f32 / CURVE_SEGMENTS as f32;
+        let on_ellipse = Vec2::new(radii.x * angle.cos(), radii.y * angle.sin());
+        stroke.push(center + Vec2::new(cos * on_ellipse.x - sin * on_ellipse.y, sin * on_ellipse.x + cos * on_ellipse.y));
+    }
+}
+
+// every subpath is a stroke
+fn parse_path(d: &str) -> Result<Vec<Vec<Vec2>>, String> {
+    let mut numbers = Numbers {
+        text: d.as_bytes(),
+        position: 0,
+    };
+    let mut strokes: Vec<Vec<Vec2>> = Vec::new();
+    let mut stroke: Vec<Vec2> = Vec::new();
+    let mut current = Vec2::ZERO;
+    let mut subpath_start = Vec2::ZERO;
+    // the second control point of the last curve, for S and T
+    let mut last_control: Option<(u8, Vec2)> = None;
+    let mut command = None;
+
+    loop {
+        command = match numbers.command() {
+            Some(next) => Some(next),
+            // further coordinates repeat the command, a moveto's are linetos
+            None if numbers.has_number() => match command {
+                Some(b'M') => Some(b'L'),
+                Some(b'm') => Some(b'l'),
+                Some(b'Z' | b'z') | None => return Err("coordinates without a command in path data".to_string()),
+                command => command,
+            },
+            None if numbers.position >= numbers.text.len() => break,
+            None => return Err(format!("unexpected {:?} in path data", numbers.text[numbers.position] as char)),
+        };
+        let Some(c) = command else {
+            break;
+        };
+        let relative = c.is_ascii_lowercase();
+        let offset = if relative { current } else { Vec2::ZERO };
+
+        let mut control = None;
+        match c.to_ascii_uppercase() {
+            b'M' => {
+                if stroke.len() > 1 {
+                    strokes.push(std::mem::take(&mut stroke));
+                }
+                current = offset + numbers.point()?;
+                subpath_start = current;
+                stroke = vec![current];
+            }
+            b'L' => {
+                current = offset + numbers.point()?;
+                stroke.push(current);
+            }
+            b'H' => {
+                current.x = if relative { current.x } else { 0.0 } + numbers.number()?;
+                stroke.push(current);
+            }
+            b'V' => {
+                current.y = if relative { current.y } else { 0.0 } + numbers.number()?;
+                stroke.push(current);
+            }
+            b'C' | b'S' => {
+                let control_1 = if c.eq_ignore_ascii_case(&b'C') {
+                    offset + numbers.point()?
+                } else {
+                    // the reflection of the previous curve's, if it was one of these
+                    match last_control {
+                        Some((b'C' | b'S', last)) => current * 2.0 - last,
+                        _ => current,
+                    }
+                };
+                let control_2 = offset + numbers.point()?;
+                let to = offset + numbers.point()?;
+                cubic(&mut stroke, current, control_1, control_2, to);
+                control = Some((b'C', control_2));
+                current = to;
+            }
+            b'Q' | b'T' => {
+                let control_1 = if c.eq_ignore_ascii_case(&b'Q') {
+                    offset + numbers.point()?
+                } else {
+                    match last_control {
+                        Some((b'Q' | b'T', last)) => current * 2.0 - last,
+                        _ => current,
+                    }
+                };
+                let to = offset + numbers.point()?;
+                quadratic(&mut stroke, current, control_1, to);
+                control = Some((b'Q', control_1));
+                current = to;
+            }
+            b'A' => {
+                let radii = numbers.point()?;
+                let rotation = numbers.number()?;
+                let large_arc = numbers.flag()?;
+                let sweep = numbers.flag()?;
+                let to = offset + numbers.point()?;
+                arc(&mut stroke, current, radii, rotation, large_arc, sweep, to);
+                current = to;
+            }
+            b'Z' => {
+                current = subpath_start;
+                stroke.push(current);
+            }
+            _ => return Err(format!("unsupported path command {:?}", c as char)),
+        }
+        last_control = control;
+    }
+
+    if stroke.len() > 1 {
+        strokes.push(stroke);
+    }
+    Ok(strokes)
+}
+
+// "x1,y1 x2,y2 ..." of <polyline> and <polygon>
+fn parse_points(points: &str) -> Result<Vec<Vec2>, String> {
+    let mut numbers = Numbers {
+        text: points.as_bytes(),
+        position: 0,
+    };
+    let mut stroke = Vec::new();
+    while numbers.has_number() {
+        stroke.push(numbers.point()?);
+    }
+    Ok(stroke)
+}
+
+fn ellipse(center: Vec2, radii: Vec2) -> Vec<Vec2> {
+    // from the rightmost point, clockwise on screen like a path's arcs with the sweep flag set
+    (0..=4 * CURVE_SEGMENTS)
+        .map(|i| {
+            let angle = TAU * i as f32 / (4 * CURVE_SEGMENTS) as f32;
+            center + radii * Vec2::new(angle.cos(), angle.sin())
+        })
+        .collect()
+}
+
+// the strokes of every shape in the file, in document order. Transforms aren't applied
+pub fn parse(svg: &str) -> Result<Vec<Vec<Vec2>>, String> {
+    let document = roxmltree::Document::parse(svg).map_err(|e| e.to_string())?;
+    if !document.root_element().has_tag_name("svg") {
+        return Err(format!("expected <svg>, found <{}>", document.root_element().tag_name().name()));
+    }
+
+    let mut strokes = Vec::new();
+    for node in document.descendants().filter(|node| node.is_element()) {
+        let number = |attribute: &str| -> Result<f32, String> {
+            node.attribute(attribute).map_or(Ok(0.0), |value| {
+                value
+                    .trim()
+                    .trim_end_matches("px")
+                    .parse()
+                    .map_err(|_| format!("invalid {} {:?} on <{}>", attribute, value, node.tag_name().name()))
+            })
+        };
+        match node.tag_name().name() {
+            "path" => strokes.extend(parse_path(node.attribute("d").unwrap_or_default())?),
+            "polyline" => strokes.push(parse_points(node.attribute("points").unwrap_or_default())?),
+            "polygon" => {
+                let mut stroke = parse_points(node.attribute("points").unwrap_or_default())?;
+                if let Some(&first) = stroke.first() {
+                    stroke.push(first);
+                }
+                strokes.push(stroke);
+            }
+            "line" => strokes.push(vec![
+                Vec2::new(number("x1")?, number("y1")?),
+                Vec2::new(number("x2")?, number("y2")?),
+            ]),
+            // the background of an exported drawing isn't part of the gesture
+            "rect" if [node.attribute("width"), node.attribute("height")]
+                .iter()
+                .any(|size| size.is_some_and(|size| size.ends_with('%'))) => {}
+            "rect" => {
+                let (min, size) = (Vec2::new(number("x")?, number("y")?), Vec2::new(number("width")?, number("height")?));
+                strokes.push(vec![min, min + size.with_y(0.0), min + size, min + size.with_x(0.0), min]);
+            }
+            "circle" => {
+                let r = number("r")?;
+                strokes.push(ellipse(Vec2::new(number("cx")?, number("cy")?), Vec2::splat(r)));
+            }
+            "ellipse" => strokes.push(ellipse(
+                Vec2::new(number("cx")?, number("cy")?),
+                Vec2::new(number("rx")?, number("ry")?),
+            )),
+            _ => {}
+        }
+    }
+
+    strokes.retain(|stroke| stroke.len() > 1);
+    if strokes.is_empty() {
+        return Err("no paths or shapes in it".to_string());
+    }
+    Ok(strokes)
+}
+
+// every .svg file in `dir` as a template named after the file: `circle.svg`
+// is "circle", and `circle~2.svg` another "circle"
+pub fn load_dir(dir: impl AsRef<Path>) -> Result<GestureSet, String> {
+    let dir = dir.as_ref();
+    let mut paths = Vec::new();
+    for entry in fs::read_dir(dir).map_err(|e| format!("{}: {}", dir.display(), e))? {
+        let path = entry.map_err(|e| e.to_string())?.path();
+        if path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("svg")) {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+
+    let mut templates = GestureSet::new();
+    for path in paths {
+        let svg = fs::read_to_string(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let strokes = parse(&svg).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let stem = path.file_stem().map(|stem| stem.to_string_lossy().to_string()).unwrap_or_default();
+        let gesture = Gesture {
+            name: stem.split_once('~').map_or(stem.as_str(), |(name, _)| name).to_string(),
+            subject: None,
+            strokes,
+        };
+        if let Some(template) = dataset::to_template(&gesture, &path.to_string_lossy()) {
+            templates.entry(gesture.name).or_default().insert(template);
+        }
+    }
+    Ok(templates)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{export, theme::Theme};
+
+    fn numbers(text: &str) -> Numbers<'_> {
+        Numbers {
+            text: text.as_bytes(),
+            position: 0,
+        }
+    }
+
+    fn path(d: &str) -> Vec<Vec<Vec2>> {
+        parse_path(d).unwrap()
+    }
+
+    #[test]
+    fn numbers_can_run_together() {
+        let mut numbers = numbers("M10-5.5.5");
+        assert_eq!(numbers.command(), Some(b'M'));
+        assert_eq!([numbers.number(), numbers.number(), numbers.number()], [Ok(10.0), Ok(-5.5), Ok(0.5)]);
+        assert!(!numbers.has_number());
+    }
+
+    #[test]
+    fn arc_flags_need_nothing_after_them() {
+        let mut numbers = numbers("a5 5 0 1010 10");
+        assert_eq!(numbers.command(), Some(b'a'));
+        assert_eq!(numbers.point(), Ok(Vec2::new(5.0, 5.0)));
+        assert_eq!(numbers.number(), Ok(0.0));
+        assert_eq!([numbers.flag(), numbers.flag()], [Ok(true), Ok(false)]);
+        assert_eq!(numbers.point(), Ok(Vec2::new(10.0, 10.0)));
+    }
+
+    #[test]
+    fn relative_commands_go_from_the_current_point() {
+        let expected = vec![vec![Vec2::new(1.0, 1.0), Vec2::new(4.0, 5.0), Vec2::new(4.0, 8.0), Vec2::new(0.0, 8.0)]];
+        assert_eq!(path("M1 1 L4 5 V8 H0"), expected);
+        assert_eq!(path("m1 1 l3 4 v3 h-4"), expected);
+        // a moveto's further coordinates are linetos
+        assert_eq!(path("m1 1 3 4 0 3 -4 0"), expected);
+    }
+
+    #[test]
+    fn smooth_curves_reflect_the_last_control_point() {
+        assert_eq!(path("M0 0 C0 10 10 10 10 0 S20 -10 20 0"), path("M0 0 C0 10 10 10 10 0 C10 -10 20 -10 20 0"));
+        assert_eq!(path("M0 0 Q5 10 10 0 T20 0"), path("M0 0 Q5 10 10 0 Q15 -10 20 0"));
+        // without a curve of the same kind before, the control point is the current point
+        assert_eq!(path("M0 0 L10 0 T20 0"), path("M0 0 L10 0 Q10 0 20 0"));
+    }
+
+    #[test]
+    fn z_closes_back_to_the_start_of_the_subpath() {
+        assert_eq!(
+            path("M2 2 h10 v10 z m1 1 h1"),
+            vec![
+                vec![Vec2::new(2.0, 2.0), Vec2::new(12.0, 2.0), Vec2::new(12.0, 12.0), Vec2::new(2.0, 2.0)],
+                vec![Vec2::new(3.0, 3.0), Vec2::new(4.0, 3.0)],
+            ]
+        );
+    }
+
+    #[test]
+    fn only_svg_documents_are_parsed() {
+        assert!(parse("<g><path d=\"M0 0 L1 1\"/></g>").is_err());
+        assert!(parse("<svg><path d=\"M0 0 L1 1\"/></svg>").is_ok());
+    }
+
+    #[test]
+    fn an_exported_drawing_parses_back_to_its_strokes() {
+        let strokes = vec![
+            vec![Vec2::new(10.0, 20.0), Vec2::new(30.5, 40.0), Vec2::new(50.0, 20.0)],
+            vec![Vec2::new(5.0, 5.0), Vec2::new(60.0, 60.0)],
+        ];
+        let svg = export::to_svg(&strokes, Vec2::new(100.0, 80.0), &Theme::default());
+        assert_eq!(parse(&svg), Ok(strokes));
+    }
+}
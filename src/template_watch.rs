@@ -0,0 +1,102 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use bevy::prelude::*;
+
+use crate::{
+    library::{GestureLibrary, GestureSet},
+    svg_templates,
+    ui_strings::{fill, UiStrings},
+    ResultText,
+};
+
+// how often the directory is checked for changes
+const POLL_INTERVAL_SECONDS: f32 = 1.0;
+
+// the set main adds the SVG templates to
+const SET: &str = "default";
+
+// every .svg file in the directory with when it was last modified and its size,
+// empty if the directory can't be read (or doesn't exist yet)
+fn snapshot(dir: &Path) -> Vec<(PathBuf, Option<SystemTime>, u64)> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut files: Vec<_> = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().extension().is_some_and(|ext| ext.eq_ignore_ascii_case("svg")))
+        .map(|entry| {
+            let metadata = entry.metadata().ok();
+            (
+                entry.path(),
+                metadata.as_ref().and_then(|metadata| metadata.modified().ok()),
+                metadata.map_or(0, |metadata| metadata.len()),
+            )
+        })
+        .collect();
+    files.sort();
+    files
+}
+
+// polls the template directory, there's no file watching without the OS's
+// notification APIs and checking a few files once a second is cheap
+#[derive(Resource)]
+pub struct TemplateWatch {
+    dir: PathBuf,
+    files: Vec<(PathBuf, Option<SystemTime>, u64)>,
+    timer: Timer,
+}
+
+impl TemplateWatch {
+    // the files as they are now are the ones already loaded
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        let dir = dir.into();
+        Self {
+            files: snapshot(&dir),
+            dir,
+            timer: Timer::from_seconds(POLL_INTERVAL_SECONDS, TimerMode::Repeating),
+        }
+    }
+}
+
+// replaces the SVG templates when a file in the directory is added, changed or
+// removed. If one of them doesn't load, the ones from before stay
+pub fn reload_templates(
+    time: Res<Time>,
+    mut watch: ResMut<TemplateWatch>,
+    mut library: ResMut<GestureLibrary>,
+    mut result_text: Single<&mut Text, With<ResultText>>,
+    strings: Res<UiStrings>,
+) {
+    if !watch.timer.tick(time.delta()).just_finished() {
+        return;
+    }
+    let files = snapshot(&watch.dir);
+    if files == watch.files {
+        return;
+    }
+
+    let loaded = if files.is_empty() {
+        Ok(GestureSet::new())
+    } else {
+        svg_templates::load_dir(&watch.dir)
+    };
+    match loaded {
+        Ok(set) => {
+            let count = set.values().map(|templates| templates.len()).sum::<usize>();
+            let dir = watch.dir.as_path();
+            library.replace_templates(SET, |template| Path::new(&template.source).parent() == Some(dir), set);
+            result_text.0 = fill(&strings.reloaded_svg, &[("count", &count)]);
+        }
+        // a file that was still being written is tried again once it changes
+        // to its final size
+        Err(e) => {
+            warn!("Couldn't reload the SVG templates: {}", e);
+            result_text.0 = fill(&strings.reload_failed, &[("error", &e)]);
+        }
+    }
+    watch.files = files;
+}
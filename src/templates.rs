@@ -1,11 +1,143 @@
-use std::{collections::{HashMap, HashSet}, hash::Hash};
+use std::{
+    cmp::Ordering,
+    collections::{BTreeSet, HashMap, HashSet},
+    hash::Hash,
+};
+
 use bevy::math::Vec2;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::recognizer::Candidate;
+
+// a cloud doesn't keep its stroke boundaries, but the strokes were resampled
+// separately, so the largest jumps between consecutive points are where one
+// stroke ends and the next one begins
+pub fn split_strokes(points: &[Vec2], stroke_count: usize) -> Vec<Vec<Vec2>> {
+    let mut gaps: Vec<(usize, f32)> = points
+        .windows(2)
+        .map(|w| w[0].distance(w[1]))
+        .enumerate()
+        .collect();
+    gaps.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+    let mut cuts: Vec<usize> = gaps
+        .iter()
+        .take(stroke_count.saturating_sub(1))
+        .map(|(i, _)| i + 1)
+        .collect();
+    cuts.sort();
+
+    let mut strokes = Vec::new();
+    let mut start = 0;
+    for cut in cuts.into_iter().chain([points.len()]) {
+        strokes.push(points[start..cut].to_vec());
+        start = cut;
+    }
+    strokes
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Template {
+    // normalized point cloud, at whatever resolution the template was made with
+    pub points: Vec<Vec2>,
+    // `points` resampled to the resolution the recognizer is running at
+    #[serde(skip)]
+    pub cloud: Vec<Vec2>,
+    // where the template came from: "default", "user", a dataset, a file name...
+    pub source: String,
+    pub created: DateTime<Utc>,
+    #[serde(default)]
+    pub author: Option<String>,
+    pub stroke_count: usize,
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub tags: BTreeSet<String>,
+    // how big it was drawn, see `Candidate::size`. Templates without one match
+    // drawings of any size
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<f32>,
+    // how thick every one of `points` was drawn, see `Candidate::with_widths`.
+    // Templates without them match drawings of any width
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub widths: Option<Vec<f32>>,
+    // how often a drawing of another gesture was matched with it, see `learning`
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub misses: u32,
+    // a template that isn't saved itself (see `library::is_user_source`) but
+    // was removed for its misses, saved so it stays removed
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub removed: bool,
+}
+
+fn is_zero(n: &u32) -> bool {
+    *n == 0
+}
+
+fn is_false(b: &bool) -> bool {
+    !b
+}
+
+impl Template {
+    pub fn new(points: Vec<Vec2>, source: impl Into<String>, stroke_count: usize) -> Self {
+        Self {
+            cloud: points.clone(),
+            points,
+            source: source.into(),
+            created: Utc::now(),
+            author: None,
+            stroke_count,
+            tags: BTreeSet::new(),
+            size: None,
+            widths: None,
+            misses: 0,
+            removed: false,
+        }
+    }
 
-pub struct Template(pub Vec<Vec2>);
+    // a drawing as a template, with its size
+    pub fn from_candidate(candidate: &Candidate, source: impl Into<String>) -> Self {
+        let mut template = Self::new(candidate.points.clone(), source, candidate.stroke_count);
+        template.size = candidate.size;
+        template.widths = candidate.widths.clone();
+        template
+    }
+
+    pub fn strokes(&self) -> Vec<Vec<Vec2>> {
+        split_strokes(&self.points, self.stroke_count)
+    }
+
+    // the points are only used as they are if they were resampled the same way
+    // already, uniformly with n points
+    pub fn with_resolution(mut self, n: usize, preserve_corners: bool) -> Self {
+        self.cloud = if self.points.len() == n && !preserve_corners {
+            self.points.clone()
+        } else {
+            Candidate::new(&self.strokes(), n, preserve_corners).points
+        };
+        self
+    }
+
+    // the same order every run, whatever order a `HashSet` has them in: oldest
+    // first, templates added at the same moment by their points
+    pub fn stable_cmp(&self, other: &Self) -> Ordering {
+        let bits = |template: &Self| {
+            template
+                .points
+                .iter()
+                .map(|point| (point.x.to_bits(), point.y.to_bits()))
+                .collect::<Vec<_>>()
+        };
+        self.created.cmp(&other.created).then_with(|| bits(self).cmp(&bits(other)))
+    }
+}
 
+// two templates are the same if their points are, metadata doesn't matter.
+// Compared bit for bit like `hash` hashes them, so equal templates hash the same
 impl PartialEq for Template {
     fn eq(&self, other: &Self) -> bool {
-        self.0.iter().zip(other.0.iter()).all(|(x,y)| x == y)
+        self.points.len() == other.points.len()
+            && self.points.iter().zip(other.points.iter()).all(|(a, b)| {
+                a.x.to_bits() == b.x.to_bits() && a.y.to_bits() == b.y.to_bits()
+            })
     }
 }
 
@@ -13,7 +145,7 @@ impl Eq for Template {}
 
 impl Hash for Template {
     fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
-        for element in &self.0 {
+        for element in &self.points {
             element.x.to_bits().hash(state);
             element.y.to_bits().hash(state);
         }
@@ -22,7 +154,7 @@ impl Hash for Template {
 
 pub fn stroke_templates() -> HashMap<String, HashSet<Template>> {
     let mut h = HashMap::new();
-    h.insert(String::from("T"), HashSet::from([Template([
+    h.insert(String::from("T"), HashSet::from([Template::new([
         Vec2::new(-0.5054096, -0.13772926),
         Vec2::new(-0.44709215, -0.14136828),
         Vec2::new(-0.38968176, -0.14864641),
@@ -55,8 +187,8 @@ pub fn stroke_templates() -> HashMap<String, HashSet<Template>> {
         Vec2::new(0.025891364, 0.48020276),
         Vec2::new(0.029530346, 0.53878856),
         Vec2::new(0.03316939, 0.59737456),
-    ].to_vec())]));
-    h.insert(String::from("N"), HashSet::from([Template([
+    ].to_vec(), "default", 2)]));
+    h.insert(String::from("N"), HashSet::from([Template::new([
         Vec2::new(-0.39062697, -0.39900354),
         Vec2::new(-0.3941015, -0.3024491),
         Vec2::new(-0.39757618, -0.20527583),
@@ -89,8 +221,8 @@ pub fn stroke_templates() -> HashMap<String, HashSet<Template>> {
         Vec2::new(0.37540534, 0.090453476),
         Vec2::new(0.391156, 0.1865528),
         Vec2::new(0.39810506, 0.28338215),
-    ].to_vec())]));
-    h.insert(String::from("D"), HashSet::from([Template([
+    ].to_vec(), "default", 3)]));
+    h.insert(String::from("D"), HashSet::from([Template::new([
         Vec2::new(-0.30522466, -0.46329862),
         Vec2::new(-0.29935434, -0.37315106),
         Vec2::new(-0.29449645, -0.28299192),
@@ -123,8 +255,8 @@ pub fn stroke_templates() -> HashMap<String, HashSet<Template>> {
         Vec2::new(0.023872316, 0.5018143),
         Vec2::new(-0.06536159, 0.5167014),
         Vec2::new(-0.15623188, 0.5167013),
-    ].to_vec())]));
-    h.insert(String::from("P"), HashSet::from([Template([
+    ].to_vec(), "default", 2)]));
+    h.insert(String::from("P"), HashSet::from([Template::new([
         Vec2::new(-0.15006813, 0.6290101),
         Vec2::new(-0.16159555, 0.56451595),
         Vec2::new(-0.16735938, 0.49887374),
@@ -157,8 +289,8 @@ pub fn stroke_templates() -> HashMap<String, HashSet<Template>> {
         Vec2::new(0.052265614, 0.08722335),
         Vec2::new(-0.014290631, 0.09005016),
         Vec2::new(-0.08039593, 0.09298706),
-    ].to_vec())]));
-    h.insert(String::from("X"), HashSet::from([Template([
+    ].to_vec(), "default", 2)]));
+    h.insert(String::from("X"), HashSet::from([Template::new([
         Vec2::new(-0.46817684, -0.4227704),
         Vec2::new(-0.39787135, -0.3771518),
         Vec2::new(-0.32851604, -0.32513535),
@@ -191,8 +323,8 @@ pub fn stroke_templates() -> HashMap<String, HashSet<Template>> {
         Vec2::new(-0.32400936, 0.3269207),
         Vec2::new(-0.3832207, 0.38990486),
         Vec2::new(-0.42548642, 0.46237445),
-    ].to_vec())]));
-    h.insert(String::from("H"), HashSet::from([Template([
+    ].to_vec(), "default", 2)]));
+    h.insert(String::from("H"), HashSet::from([Template::new([
         Vec2::new(-0.34785324, -0.42290664),
         Vec2::new(-0.3337278, -0.34016746),
         Vec2::new(-0.32469487, -0.2566492),
@@ -225,8 +357,8 @@ pub fn stroke_templates() -> HashMap<String, HashSet<Template>> {
         Vec2::new(0.32623202, 0.24767178),
         Vec2::new(0.32771683, 0.33154386),
         Vec2::new(0.33097816, 0.4151551),
-    ].to_vec())]));
-    h.insert(String::from("I"), HashSet::from([Template([
+    ].to_vec(), "default", 3)]));
+    h.insert(String::from("I"), HashSet::from([Template::new([
         Vec2::new(-0.5068255, -0.4727352),
         Vec2::new(-0.41295764, -0.46860296),
         Vec2::new(-0.3186804, -0.46860296),
@@ -259,8 +391,8 @@ pub fn stroke_templates() -> HashMap<String, HashSet<Template>> {
         Vec2::new(0.28323847, 0.49420694),
         Vec2::new(0.37751573, 0.49420676),
         Vec2::new(0.47179294, 0.49420694),
-    ].to_vec())]));
-    h.insert(String::from("Exclamation"), HashSet::from([Template([
+    ].to_vec(), "default", 3)]));
+    h.insert(String::from("Exclamation"), HashSet::from([Template::new([
         Vec2::new(-0.022258049, -0.46243614),
         Vec2::new(-0.022258049, -0.43277434),
         Vec2::new(-0.022258205, -0.40311253),
@@ -293,8 +425,8 @@ pub fn stroke_templates() -> HashMap<String, HashSet<Template>> {
         Vec2::new(0.018789371, 0.3915553),
         Vec2::new(0.0064751767, 0.5103877),
         Vec2::new(0.014684694, 0.53756386),
-    ].to_vec())]));
-    h.insert(String::from("Line"), HashSet::from([Template([
+    ].to_vec(), "default", 2)]));
+    h.insert(String::from("Line"), HashSet::from([Template::new([
         Vec2::new(-0.49976534, 0.004943978),
         Vec2::new(-0.46746632, 0.004943978),
         Vec2::new(-0.43516734, 0.004943978),
@@ -327,8 +459,8 @@ pub fn stroke_templates() -> HashMap<String, HashSet<Template>> {
         Vec2::new(0.43563664, 0.0009887933),
         Vec2::new(0.46793568, 0.0009887933),
         Vec2::new(0.50023466, 0.0009887933),
-    ].to_vec())]));
-    h.insert(String::from("Five-point Star"), HashSet::from([Template([
+    ].to_vec(), "default", 1)]));
+    h.insert(String::from("Five-point Star"), HashSet::from([Template::new([
         Vec2::new(-0.4267801, 0.379862),
         Vec2::new(-0.34821773, 0.24384773),
         Vec2::new(-0.27524576, 0.10209057),
@@ -361,8 +493,8 @@ pub fn stroke_templates() -> HashMap<String, HashSet<Template>> {
         Vec2::new(-0.012000263, 0.1415422),
         Vec2::new(-0.15657473, 0.20663875),
         Vec2::new(-0.29408112, 0.2863704),
-    ].to_vec())]));
-    h.insert(String::from("Null"), HashSet::from([Template([
+    ].to_vec(), "default", 1)]));
+    h.insert(String::from("Null"), HashSet::from([Template::new([
         Vec2::new(-0.047185153, -0.3952313),
         Vec2::new(-0.18414849, -0.3750285),
         Vec2::new(-0.3103959, -0.32062322),
@@ -395,8 +527,8 @@ pub fn stroke_templates() -> HashMap<String, HashSet<Template>> {
         Vec2::new(-0.16804698, 0.17067987),
         Vec2::new(-0.23942071, 0.2896738),
         Vec2::new(-0.31875402, 0.4037475),
-    ].to_vec())]));
-    h.insert(String::from("Arrowhead"), HashSet::from([Template([
+    ].to_vec(), "default", 2)]));
+    h.insert(String::from("Arrowhead"), HashSet::from([Template::new([
         Vec2::new(-0.04565096, -0.32721698),
         Vec2::new(0.003425777, -0.28609857),
         Vec2::new(0.05232781, -0.24419944),
@@ -429,8 +561,8 @@ pub fn stroke_templates() -> HashMap<String, HashSet<Template>> {
         Vec2::new(-0.5268005, 0.05030775),
         Vec2::new(-0.5910373, 0.054121196),
         Vec2::new(-0.65533483, 0.056234866),
-    ].to_vec())]));
-    h.insert(String::from("Pitchfork"), HashSet::from([Template([
+    ].to_vec(), "default", 2)]));
+    h.insert(String::from("Pitchfork"), HashSet::from([Template::new([
         Vec2::new(1.7017126e-5, -0.31697795),
         Vec2::new(1.7017126e-5, -0.23511922),
         Vec2::new(1.7017126e-5, -0.15326053),
@@ -463,8 +595,8 @@ pub fn stroke_templates() -> HashMap<String, HashSet<Template>> {
         Vec2::new(0.37652513, -0.2245585),
         Vec2::new(0.38040146, -0.30584642),
         Vec2::new(0.38040146, -0.38770512),
-    ].to_vec())]));
-    h.insert(String::from("Six-point Star"), HashSet::from([Template([
+    ].to_vec(), "default", 2)]));
+    h.insert(String::from("Six-point Star"), HashSet::from([Template::new([
         Vec2::new(-0.00292629, -0.35811853),
         Vec2::new(-0.10023633, -0.22142069),
         Vec2::new(-0.20865753, -0.09321138),
@@ -497,8 +629,8 @@ pub fn stroke_templates() -> HashMap<String, HashSet<Template>> {
         Vec2::new(-0.3350438, -0.093993515),
         Vec2::new(-0.24766508, 0.049076885),
         Vec2::new(-0.16012871, 0.19034743),
-    ].to_vec())]));
-    h.insert(String::from("Asterisk"), HashSet::from([Template([
+    ].to_vec(), "default", 2)]));
+    h.insert(String::from("Asterisk"), HashSet::from([Template::new([
         Vec2::new(0.013198465, -0.41284576),
         Vec2::new(0.015722632, -0.30594888),
         Vec2::new(0.0182468, -0.19916211),
@@ -531,8 +663,8 @@ pub fn stroke_templates() -> HashMap<String, HashSet<Template>> {
         Vec2::new(0.15660119, -0.1776169),
         Vec2::new(0.23781168, -0.24693348),
         Vec2::new(0.32154328, -0.30993977),
-    ].to_vec())]));
-    h.insert(String::from("Half-note"), HashSet::from([Template([
+    ].to_vec(), "default", 3)]));
+    h.insert(String::from("Half-note"), HashSet::from([Template::new([
         Vec2::new(0.07885161, -0.63273996),
         Vec2::new(0.08262721, -0.59060997),
         Vec2::new(0.08902171, -0.5386963),
@@ -565,6 +697,31 @@ pub fn stroke_templates() -> HashMap<String, HashSet<Template>> {
         Vec2::new(0.030670077, 0.29550272),
         Vec2::new(0.06483635, 0.25864393),
         Vec2::new(0.08853653, 0.21294248),
-    ].to_vec())]));
+    ].to_vec(), "default", 2)]));
     h
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use std::hash::{BuildHasher, RandomState};
+
+    use super::*;
+
+    #[test]
+    fn a_prefix_is_a_different_template() {
+        let points = vec![Vec2::new(0.0, 0.0), Vec2::new(0.5, 0.5), Vec2::new(1.0, 0.0)];
+        let whole = Template::new(points.clone(), "default", 1);
+        let prefix = Template::new(points[..2].to_vec(), "default", 1);
+        assert!(whole != prefix);
+        assert_eq!(HashSet::from([whole, prefix]).len(), 2);
+    }
+
+    #[test]
+    fn equal_templates_hash_the_same() {
+        let points = vec![Vec2::new(0.0, 0.0), Vec2::new(-0.0, 1.0)];
+        let a = Template::new(points.clone(), "default", 1);
+        let b = Template::new(points, "user", 1);
+        let hasher = RandomState::new();
+        assert!(a == b);
+        assert_eq!(hasher.hash_one(&a), hasher.hash_one(&b));
+    }
+}
@@ -0,0 +1,175 @@
+use bevy::{
+    dev_tools::fps_overlay::FpsOverlayConfig,
+    prelude::*,
+};
+
+use crate::{
+    bindings::{Action, InputBindings},
+    canvases::ExtraCanvas,
+    history::RecognitionHistory,
+    DrawingBoard, IsTyping,
+};
+
+// the colors of the board, the brush and the UI. `--high-contrast` starts with
+// `Theme::high_contrast`, F7 (see `bindings`) switches between the two
+#[derive(Resource, Clone, Copy, PartialEq)]
+pub struct Theme {
+    pub high_contrast: bool,
+    pub board: Color,
+    pub brush: Color,
+    // the matched template's points, the gamepad cursor and the compare view's
+    // template
+    pub overlay: Color,
+    // the compare view's drawing, the lines between its matched points and the
+    // line between the two halves
+    pub compare_candidate: Color,
+    pub compare_pair: Color,
+    pub compare_divider: Color,
+    // the window around the board and the panels
+    pub background: Color,
+    // the result, the hint, the FPS and latency and the winner in the chart
+    pub text: Color,
+    // the runners-up in the chart
+    pub text_dim: Color,
+    pub button_background: Color,
+    pub button_border: Color,
+    // the border of a button while it's pressed
+    pub button_pressed: Color,
+    // on the buttons, in the history list and over the chart's bars
+    pub button_text: Color,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            high_contrast: false,
+            board: Color::linear_rgb(0.0, 0.0, 0.0),
+            brush: Color::linear_rgb(255.0, 255.0, 255.0),
+            overlay: Color::srgba(0.0, 0.8, 1.0, 0.5),
+            compare_candidate: Color::WHITE,
+            compare_pair: Color::srgba(1.0, 1.0, 1.0, 0.25),
+            compare_divider: Color::srgb(0.3, 0.3, 0.3),
+            background: ClearColor::default().0,
+            text: Color::linear_rgb(0.0, 255.0, 0.0),
+            text_dim: Color::srgb(0.4, 0.6, 0.4),
+            button_background: Color::srgb(0.15, 0.15, 0.15),
+            button_border: Color::WHITE,
+            button_pressed: bevy::color::palettes::css::LIGHT_GREEN.into(),
+            button_text: Color::srgb(0.9, 0.9, 0.9),
+        }
+    }
+}
+
+impl Theme {
+    // black on white on the board, white and yellow on black around it, and
+    // nothing see-through
+    pub fn high_contrast() -> Self {
+        Self {
+            high_contrast: true,
+            board: Color::WHITE,
+            brush: Color::BLACK,
+            overlay: Color::srgb(1.0, 0.0, 1.0),
+            compare_candidate: Color::WHITE,
+            compare_pair: Color::srgb(0.0, 1.0, 1.0),
+            compare_divider: Color::WHITE,
+            background: Color::BLACK,
+            text: Color::srgb(1.0, 1.0, 0.0),
+            text_dim: Color::WHITE,
+            button_background: Color::BLACK,
+            button_border: Color::WHITE,
+            button_pressed: Color::srgb(0.0, 1.0, 1.0),
+            button_text: Color::WHITE,
+        }
+    }
+
+    fn toggled(&self) -> Self {
+        if self.high_contrast {
+            Self::default()
+        } else {
+            Self::high_contrast()
+        }
+    }
+}
+
+pub fn toggle_contrast(
+    mut theme: ResMut<Theme>,
+    bindings: Res<InputBindings>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    buttons: Res<ButtonInput<MouseButton>>,
+    typing: Res<IsTyping>,
+) {
+    if typing.0 || !bindings.just_pressed(Action::ToggleContrast, &keyboard, &buttons) {
+        return;
+    }
+    *theme = theme.toggled();
+}
+
+// `to`'s color for whatever had one of `from`'s
+fn swap(color: Color, pairs: &[(Color, Color)]) -> Color {
+    pairs.iter().find(|(from, _)| *from == color).map_or(color, |(_, to)| *to)
+}
+
+// the board and the thumbnails keep their drawings, only the colors change
+fn recolor_image(image: &mut Image, from: &Theme, to: &Theme) {
+    let board = (from.board.to_srgba().to_u8_array(), to.board.to_srgba().to_u8_array());
+    let brush = (from.brush.to_srgba().to_u8_array(), to.brush.to_srgba().to_u8_array());
+    for pixel in image.data.chunks_exact_mut(4) {
+        if *pixel == board.0 {
+            pixel.copy_from_slice(&board.1);
+        } else if *pixel == brush.0 {
+            pixel.copy_from_slice(&brush.1);
+        }
+    }
+}
+
+// everything that was spawned or painted with the old theme's colors gets the
+// new one's, so nothing has to be respawned or redrawn
+pub fn apply_theme(
+    theme: Res<Theme>,
+    mut applied: Local<Option<Theme>>,
+    mut clear_color: ResMut<ClearColor>,
+    mut fps_overlay: ResMut<FpsOverlayConfig>,
+    mut text_colors: Query<&mut TextColor>,
+    mut backgrounds: Query<&mut BackgroundColor>,
+    mut borders: Query<&mut BorderColor>,
+    mut images: ResMut<Assets<Image>>,
+    drawingboard: Res<DrawingBoard>,
+    canvases: Query<&ExtraCanvas>,
+    history: Res<RecognitionHistory>,
+) {
+    // the first time round everything was just spawned with it
+    let Some(old) = applied.replace(*theme) else {
+        return;
+    };
+    if old == *theme {
+        return;
+    }
+
+    clear_color.0 = theme.background;
+    fps_overlay.text_color = theme.text;
+    let texts = [(old.text, theme.text), (old.button_text, theme.button_text), (old.brush, theme.brush)];
+    for mut color in &mut text_colors {
+        color.0 = swap(color.0, &texts);
+    }
+    let fills = [
+        (old.button_background, theme.button_background),
+        (old.text, theme.text),
+        (old.text_dim, theme.text_dim),
+    ];
+    for mut color in &mut backgrounds {
+        color.0 = swap(color.0, &fills);
+    }
+    let outlines = [(old.button_border, theme.button_border), (old.button_pressed, theme.button_pressed)];
+    for mut color in &mut borders {
+        color.0 = swap(color.0, &outlines);
+    }
+
+    let boards = std::iter::once(&drawingboard.image)
+        .chain(canvases.iter().map(|canvas| &canvas.board))
+        .chain(history.entries().map(|entry| &entry.thumbnail));
+    for handle in boards {
+        if let Some(image) = images.get_mut(handle) {
+            recolor_image(image, &old, &theme);
+        }
+    }
+}
@@ -0,0 +1,57 @@
+use bevy::{math::Vec2, prelude::Resource};
+
+use crate::{
+    recognizer::{cloud_distance, RecognitionConfig},
+    ui_strings::{fill, UiStrings},
+};
+
+// how many times the user is asked to draw the gesture
+pub const TRAINING_SAMPLES: usize = 5;
+
+// collects samples of one gesture, every one of them is also added to the
+// library as a template as soon as it's drawn
+pub struct Training {
+    pub name: String,
+    samples: Vec<Vec<Vec2>>,
+}
+
+impl Training {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            samples: Vec::with_capacity(TRAINING_SAMPLES),
+        }
+    }
+
+    pub fn add_sample(&mut self, cloud: Vec<Vec2>) {
+        self.samples.push(cloud);
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.samples.len() >= TRAINING_SAMPLES
+    }
+
+    pub fn prompt(&self, strings: &UiStrings) -> String {
+        fill(
+            &strings.training_prompt,
+            &[("name", &self.name), ("sample", &(self.samples.len() + 1)), ("samples", &TRAINING_SAMPLES)],
+        )
+    }
+
+    // mean and largest distance between any two samples, small numbers mean
+    // the gesture was drawn consistently
+    pub fn spread(&self, config: &RecognitionConfig) -> (f32, f32) {
+        let mut distances = Vec::new();
+        for (i, a) in self.samples.iter().enumerate() {
+            for b in self.samples.iter().skip(i + 1) {
+                distances.push(cloud_distance(a, b, config));
+            }
+        }
+        let mean = distances.iter().sum::<f32>() / distances.len().max(1) as f32;
+        let max = distances.iter().copied().fold(0.0, f32::max);
+        (mean, max)
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct ActiveTraining(pub Option<Training>);
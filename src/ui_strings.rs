@@ -0,0 +1,193 @@
+use std::fmt::Display;
+
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::storage;
+
+// where `--locale=<code>` looks for `<code>.toml`
+const LOCALES_DIR: &str = "locales";
+
+// the text of the buttons, hints and result messages. A locale file has a
+// line for every string it translates, the rest stay English. Words in braces
+// are filled in by `fill` and have to be kept as they are
+#[derive(Resource, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct UiStrings {
+    pub window_title: String,
+
+    // buttons
+    pub add: String,
+    pub toggle_brush: String,
+    pub brush_on: String,
+    pub brush_off: String,
+    pub recognize: String,
+    pub replay: String,
+    pub compact: String,
+    pub export: String,
+    pub train: String,
+    pub all_sets: String,
+    pub set: String,
+    pub points: String,
+    // what screen readers call the buttons that show a setting rather than
+    // their name
+    pub gesture_set: String,
+    pub resolution: String,
+
+    // hints
+    pub hint: String,
+    pub keypad_hint: String,
+
+    // results
+    pub recognized: String,
+    pub not_recognized: String,
+    // the line under a result that ran out of its `--latency-budget`
+    pub out_of_time: String,
+    // what a drawing that wasn't recognized is called in the history panel
+    pub nothing_matched: String,
+    // a gesture and its set, in the history, the chart and the compare view
+    pub gesture_in_set: String,
+    // a recognition in the history panel
+    pub history_entry: String,
+    pub history_entry_not_recognized: String,
+    // a bar of the chart in the top left
+    pub score: String,
+    pub gesture_added: String,
+    pub augmented: String,
+    pub draw_before_adding: String,
+    pub removed_template: String,
+    pub template_missed_once: String,
+    pub template_missed: String,
+    pub nothing_to_replay: String,
+    pub exported: String,
+    pub export_failed: String,
+    pub nothing_to_compact: String,
+    pub merged_one: String,
+    pub merged: String,
+    pub training_prompt: String,
+    pub trained: String,
+    pub stopped_training: String,
+    pub nothing_to_train: String,
+    pub recording: String,
+    pub stopped_recording: String,
+    pub recording_failed: String,
+    pub comparing: String,
+    pub comparing_nothing: String,
+    pub stopped_comparing: String,
+    pub reloaded_svg: String,
+    pub reload_failed: String,
+    pub session_saved: String,
+    pub session_save_failed: String,
+    pub session_restored: String,
+    pub session_restore_failed: String,
+    pub no_saved_session: String,
+    pub latency: String,
+    // what F6 found, see `diagnostics`
+    pub diagnostics: String,
+    pub conflict: String,
+    pub outlier_closer: String,
+    pub outlier: String,
+    pub diagnostics_more: String,
+
+    // the titles of the `--canvases` windows
+    pub canvas: String,
+    pub canvas_classes: String,
+    pub canvas_recognized: String,
+    pub canvas_not_recognized: String,
+}
+
+impl Default for UiStrings {
+    fn default() -> Self {
+        let s = String::from;
+        Self {
+            window_title: s("Stroke Recognizer"),
+
+            add: s("Add"),
+            toggle_brush: s("Toggle Brush"),
+            brush_on: s("ON"),
+            brush_off: s("OFF"),
+            recognize: s("Recognize"),
+            replay: s("Replay"),
+            compact: s("Compact"),
+            export: s("Export"),
+            train: s("Train"),
+            all_sets: s("Set: All"),
+            set: s("Set: {set}"),
+            points: s("Points: {points}"),
+            gesture_set: s("Gesture set"),
+            resolution: s("Points per gesture"),
+
+            hint: s("Misrecognized? 'Add' stroke as a gesture\n\n'Toggle Brush' for performance"),
+            keypad_hint: s("Write a digit, + - * / = or < to delete"),
+
+            recognized: s("{name} ({set})\n{confidence}% confident\n{latency} milliseconds"),
+            not_recognized: s("not recognized\n{latency} milliseconds"),
+            out_of_time: s("out of time, best so far"),
+            nothing_matched: s("not recognized"),
+            gesture_in_set: s("{name} ({set})"),
+            history_entry: s("{name}\n{confidence}%, {latency} ms"),
+            history_entry_not_recognized: s("{name}\n{latency} ms"),
+            score: s("{name} {confidence}%"),
+            gesture_added: s("{name} gesture added to {set}!"),
+            augmented: s("with {count} augmented variants"),
+            draw_before_adding: s("Draw a gesture before adding it"),
+            removed_template: s("removed a {name} template that kept matching wrong"),
+            template_missed_once: s("the {name} template it matched has 1 miss"),
+            template_missed: s("the {name} template it matched has {misses} misses"),
+            nothing_to_replay: s("Nothing to replay"),
+            exported: s("Saved {files}"),
+            export_failed: s("Couldn't export: {error}"),
+            nothing_to_compact: s("Nothing to compact"),
+            merged_one: s("Merged 1 template"),
+            merged: s("Merged {count} templates"),
+            training_prompt: s("Draw {name} ({sample}/{samples})"),
+            trained: s("Trained {name}\nspread: mean {mean}, max {max}"),
+            stopped_training: s("Stopped training {name}"),
+            nothing_to_train: s("No gestures to train, shift-click to name one"),
+            recording: s("Recording to {path}"),
+            stopped_recording: s("Stopped recording to {path}"),
+            recording_failed: s("Couldn't record: {error}"),
+            comparing: s("Comparing with {name}"),
+            comparing_nothing: s("Comparing: nothing recognized yet"),
+            stopped_comparing: s("Stopped comparing"),
+            reloaded_svg: s("Reloaded {count} SVG templates"),
+            reload_failed: s("Couldn't reload the SVG templates:\n{error}"),
+            session_saved: s("Saved the session to {path}"),
+            session_save_failed: s("Couldn't save the session: {error}"),
+            session_restored: s("Restored {strokes} strokes from {saved}"),
+            session_restore_failed: s("Couldn't restore the session: {error}"),
+            no_saved_session: s("No session saved in {path}"),
+            latency: s("last {count} in ms:\nmin {min} avg {mean}\np95 {p95}"),
+            diagnostics: s("{conflicts} conflicts, {outliers} outliers among {templates} templates of {gestures} gestures"),
+            conflict: s("{a} ~ {b}: {confidence}% alike (distance {distance})"),
+            outlier_closer: s("{gesture} {template}: closer to {other} than to its own"),
+            outlier: s("{gesture} {template}: {spread}x as far from the rest as usual"),
+            diagnostics_more: s("{count} more in the log"),
+
+            canvas: s("Canvas {index}"),
+            canvas_classes: s("Canvas {index}: {classes}"),
+            canvas_recognized: s("Canvas {index}: {name} ({confidence}%)"),
+            canvas_not_recognized: s("Canvas {index}: not recognized"),
+        }
+    }
+}
+
+impl UiStrings {
+    // `locales/<code>.toml` (see `storage`), a locale that was asked for has
+    // to be there
+    pub fn load(locale: &str) -> Result<Self, String> {
+        let name = format!("{}/{}.toml", LOCALES_DIR, locale);
+        let text = storage::read(&name)?.ok_or_else(|| format!("{}: no such locale", name))?;
+        toml::from_str(&text).map_err(|e| format!("{}: {}", name, e))
+    }
+}
+
+// `template` with every `{key}` replaced by its value. Numbers are passed in
+// already formatted, to the precision they're shown with
+pub fn fill(template: &str, values: &[(&str, &dyn Display)]) -> String {
+    let mut text = template.to_string();
+    for (key, value) in values {
+        text = text.replace(&format!("{{{}}}", key), &value.to_string());
+    }
+    text
+}
@@ -0,0 +1,80 @@
+use bevy::{prelude::*, window::PrimaryWindow};
+
+use crate::{theme::Theme, BoardView, CanvasRect};
+
+// zones are outlined in the brush's color, faintly
+const ZONE_ALPHA: f32 = 0.15;
+
+// a part of the canvas, as fractions of its size from the top left corner,
+// where drawings are only matched against some gestures
+#[derive(Clone)]
+pub struct Zone {
+    rect: Rect,
+    // gesture or set names
+    classes: Vec<String>,
+}
+
+// `--zones=<left>,<top>,<right>,<bottom>:<name>,<name>;...` picks what a
+// drawing can be by where its centroid is, the first zone it's in wins and
+// outside of all of them it can be anything
+#[derive(Resource, Default, Clone)]
+pub struct HotZones(Vec<Zone>);
+
+impl HotZones {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        text.split(';')
+            .map(|zone| {
+                let (rect, classes) = zone.split_once(':').ok_or_else(|| format!("{}: no gestures", zone))?;
+                let corners: Vec<f32> = rect
+                    .split(',')
+                    .map(|x| x.trim().parse())
+                    .collect::<Result<_, _>>()
+                    .map_err(|e| format!("{}: {}", zone, e))?;
+                let [left, top, right, bottom] = corners[..] else {
+                    return Err(format!("{}: a zone is left,top,right,bottom", zone));
+                };
+                let classes: Vec<String> = classes
+                    .split(',')
+                    .map(|name| name.trim().to_string())
+                    .filter(|name| !name.is_empty())
+                    .collect();
+                if classes.is_empty() {
+                    return Err(format!("{}: no gestures", zone));
+                }
+                Ok(Zone {
+                    rect: Rect::new(left, top, right, bottom),
+                    classes,
+                })
+            })
+            .collect::<Result<_, _>>()
+            .map(Self)
+    }
+
+    // what a drawing centered on `board_pos` can be, None for anything
+    pub fn classes_at(&self, board_pos: Vec2, canvas_size: Vec2) -> Option<Vec<String>> {
+        let position = board_pos / canvas_size.max(Vec2::ONE);
+        self.0
+            .iter()
+            .find(|zone| zone.rect.contains(position))
+            .map(|zone| zone.classes.clone())
+    }
+}
+
+// the outlines of the zones, so it's clear where which gestures go
+pub fn draw_zones(
+    mut gizmos: Gizmos,
+    zones: Res<HotZones>,
+    canvas: Res<CanvasRect>,
+    view: Res<BoardView>,
+    window: Single<&Window, With<PrimaryWindow>>,
+    theme: Res<Theme>,
+) {
+    let size = canvas.0.size();
+    for zone in &zones.0 {
+        // y goes up in the world, so the top left corner is the bottom left there
+        let min = view.to_world(&canvas, window.size(), zone.rect.min * size);
+        let max = view.to_world(&canvas, window.size(), zone.rect.max * size);
+        let world = Isometry2d::from_translation((min + max) / 2.0);
+        gizmos.rect_2d(world, (max - min).abs(), theme.brush.with_alpha(ZONE_ALPHA));
+    }
+}